@@ -0,0 +1,82 @@
+//! Defines the "Ask AI about this image" message context menu command, which shows the image
+//! attached to a message to a vision model.
+
+use poise::serenity_prelude::Message;
+
+use super::images::{encode_image, is_image};
+use super::streaming_reply::{MAX_INLINE_MESSAGES, StreamingReply};
+use super::*;
+use crate::utils::ollama_client::OLLAMA_CLIENT;
+use tracing::{debug, error, info};
+
+/// The question asked about images posted without text.
+const DEFAULT_QUESTION: &str = "What is in this image?";
+
+/// Asks the AI about the first image attached to a message.
+///
+/// The message's text is asked along with the image, or "What is in this image?" if it has
+/// none. The question and answer continue your conversation in this channel, like `/chat`,
+/// but the image itself isn't remembered. Your model must support vision.
+#[poise::command(context_menu_command = "Ask AI about this image", category = "AI")]
+pub async fn ask_about_image(
+    ctx: Context<'_>,
+    #[description = "Message with an image"] message: Message,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Image question received from user {}", author.name);
+
+    let Some(attachment) = message.attachments.iter().find(|&a| is_image(a)) else {
+        return reply(ctx, "That message has no image attached.").await;
+    };
+
+    // Defer the response while the image is downloaded and the AI responds.
+    ctx.defer().await?;
+    let image = encode_image(attachment).await?;
+
+    let question = match message.content.trim() {
+        "" => DEFAULT_QUESTION,
+        content => content,
+    };
+    info!(
+        "Asking the AI about image {} for {} ({})",
+        attachment.filename, author.name, author.id
+    );
+
+    let conversation = conversation(ctx);
+    let prompt = attributed_prompt(conversation, author, question);
+    let persona = active_persona(ctx.guild_id(), author.id);
+    match OLLAMA_CLIENT
+        .chat_stream(
+            author,
+            conversation,
+            ChatSettings {
+                persona: persona.as_ref(),
+                images: vec![image],
+                ..ChatSettings::default()
+            },
+            &prompt,
+        )
+        .await
+    {
+        Ok(stream) => {
+            // Start the reply like `/chat` replies, with a link to the image's message.
+            let header = format!(
+                "**{}**: {} ({})\n\n**AI**: ",
+                author.name,
+                question,
+                message.link()
+            );
+            let reply = StreamingReply::new(ctx, header).attach_after(MAX_INLINE_MESSAGES);
+
+            // Remember the exchange once the response is complete.
+            if let Some(response) = reply.relay(stream).await? {
+                OLLAMA_CLIENT.record_exchange(conversation, &prompt, &response);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to get AI response for {}: {}", author.name, e);
+            Err(e.into())
+        }
+    }
+}
@@ -2,20 +2,53 @@
 
 use crate::utils::ollama_client::OLLAMA_CLIENT;
 
+use super::images::encode_image;
+use super::streaming_reply::{MAX_INLINE_MESSAGES, StreamingReply};
 use super::*;
+use poise::serenity_prelude::Attachment;
 use tracing::{debug, error, info};
 
 /// Sends a message to the configured AI model and displays the response.
 ///
-/// This command takes a user's message, sends it to the Ollama client,
-/// formats the user's message and the AI's response, and sends it back
-/// to the channel, handling potential message length limits by chunking.
+/// This command takes a user's message, sends it to the Ollama client along with the user's
+/// conversation in this channel, and streams the AI's response back to the channel below the
+/// user's message, editing the reply as it is written and continuing in follow-up messages when
+/// it gets long. The conversation is remembered until it is reset with `/chat_reset`.
+///
+/// In channels and threads with a shared conversation (see `/chat_mode` and `/chat_thread`),
+/// everyone's messages go into the same conversation, each starting with its author's name.
+/// The AI takes on your persona, or the server's default one (see `/persona`).
+///
+/// An attached image is shown to the AI along with your message, if your model supports
+/// vision. The image isn't remembered; later messages only see the text.
+///
+/// If your model supports tools, the AI can search the web, look up coin prices, queue music
+/// in your voice channel, and check the time to answer; the tools it used are listed at the end.
+///
+/// The generation options given here apply to this message only, and win over your saved
+/// defaults (see `/chat_options`), the persona's options, and the model's defaults.
 #[poise::command(slash_command, category = "AI")]
+#[allow(clippy::too_many_arguments)] // Each argument is an option of the command.
 pub async fn chat(
     ctx: Context<'_>,
     #[description = "Your chat message"]
     #[rest]
     message: String,
+    #[description = "An image for the AI to look at"] image: Option<Attachment>,
+    #[description = "Send long responses as a Markdown file (default: true)"] as_file: Option<bool>,
+    #[description = "Sampling temperature (higher is more creative)"]
+    #[min = 0.0]
+    #[max = 2.0]
+    temperature: Option<f32>,
+    #[description = "Most tokens in the response"]
+    #[min = 1]
+    #[max = 32768]
+    max_tokens: Option<u32>,
+    #[description = "Random seed, for reproducible responses"] seed: Option<i32>,
+    #[description = "Context window size in tokens"]
+    #[min = 512]
+    #[max = 131072]
+    context_size: Option<u32>,
 ) -> CommandResult {
     let author = ctx.author();
     debug!("Chat request received from user {}", author.name);
@@ -23,20 +56,58 @@ pub async fn chat(
     // Defer the response to indicate the bot is processing.
     ctx.defer().await?;
 
-    info!("Processing chat request from {} ({})", author.name, author.id);
+    // Read the attached image, if any.
+    let images = match &image {
+        Some(image) => vec![encode_image(image).await?],
+        None => Vec::new(),
+    };
+
+    info!(
+        "Processing chat request from {} ({})",
+        author.name, author.id
+    );
 
-    // Call the Ollama client to get the chat response.
-    match OLLAMA_CLIENT.clone().chat(author, &message).await {
-        Ok(response) => {
-            let content = response.message.content;
-            debug!("Received AI response for {}", author.name);
+    // Ask the Ollama client to stream the chat response.
+    let conversation = conversation(ctx);
+    let prompt = attributed_prompt(conversation, author, &message);
+    let persona = active_persona(ctx.guild_id(), author.id);
+    let overrides = GenerationOptions {
+        temperature,
+        num_predict: max_tokens,
+        seed,
+        num_ctx: context_size,
+        ..GenerationOptions::default()
+    };
+    match OLLAMA_CLIENT
+        .clone()
+        .chat_stream(
+            author,
+            conversation,
+            ChatSettings {
+                persona: persona.as_ref(),
+                overrides,
+                images,
+                tools: Some(ToolContext::from_command(ctx)),
+            },
+            &prompt,
+        )
+        .await
+    {
+        Ok(stream) => {
+            debug!("Streaming AI response for {}", author.name);
 
-            // Format the message including the user's prompt and the AI's response.
-            let full_message = format!("**{}**: {message}\n\n**AI**: {content}", author.name);
-            info!("Sending formatted response to {}", author.name);
+            // Start the reply with the user's prompt, followed by the AI's response as it arrives.
+            let header = format!("**{}**: {message}\n\n**AI**: ", author.name);
+            let mut reply = StreamingReply::new(ctx, header);
+            if as_file.unwrap_or(true) {
+                reply = reply.attach_after(MAX_INLINE_MESSAGES);
+            }
 
-            // Send the potentially long response, chunking if necessary.
-            chunk_response(ctx, full_message).await
+            // Remember the exchange once the response is complete.
+            if let Some(response) = reply.relay(stream).await? {
+                OLLAMA_CLIENT.record_exchange(conversation, &prompt, &response);
+            }
+            Ok(())
         }
         Err(e) => {
             error!("Failed to get AI response for {}: {}", author.name, e);
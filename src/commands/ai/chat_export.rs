@@ -0,0 +1,98 @@
+//! Defines the `/chat_export` command for downloading the user's AI conversation.
+
+use chrono::DateTime;
+use poise::{CreateReply, serenity_prelude::CreateAttachment};
+use serde_json::json;
+
+use super::*;
+use tracing::{debug, error};
+
+/// A file format conversations can be exported to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ExportFormat {
+    /// A readable transcript.
+    #[default]
+    #[name = "Markdown"]
+    Markdown,
+    /// Every message with its role and timestamp, as a JSON array.
+    #[name = "JSON"]
+    Json,
+}
+
+/// Sends you your whole conversation with the AI in this channel as a file.
+///
+/// In channels with a shared conversation, the shared conversation is exported. Markdown gives
+/// a readable transcript; JSON keeps the role and time of every message.
+#[poise::command(slash_command, category = "AI")]
+pub async fn chat_export(
+    ctx: Context<'_>,
+    #[description = "File format (default: Markdown)"] format: Option<ExportFormat>,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat export request received from user {}", author.name);
+
+    let conversation = conversation(ctx);
+    let messages = database::get_conversation_messages(conversation);
+    if messages.is_empty() {
+        return reply(
+            ctx,
+            "You have no conversation with the AI in this channel yet.",
+        )
+        .await;
+    }
+
+    // Write the conversation in the requested format.
+    let (content, extension) = match format.unwrap_or_default() {
+        ExportFormat::Markdown => (transcript(conversation, &messages), "md"),
+        ExportFormat::Json => match serde_json::to_string_pretty(&to_json(&messages)) {
+            Ok(json) => (json, "json"),
+            Err(e) => {
+                error!("Failed to serialize conversation: {}", e);
+                ctx.say(format!("Failed to export your conversation: {}", e))
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let attachment =
+        CreateAttachment::bytes(content.into_bytes(), format!("conversation.{}", extension));
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "📤 Your conversation in this channel ({} messages)",
+                messages.len()
+            ))
+            .attachment(attachment)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Writes a conversation as a Markdown transcript.
+fn transcript(conversation: ConversationKey, messages: &[ConversationMessage]) -> String {
+    let mut transcript = String::from("# Conversation\n");
+    for message in messages {
+        let (author, content) = message_author(conversation, message);
+        let time = DateTime::from_timestamp(message.created_at, 0)
+            .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_default();
+        transcript.push_str(&format!("\n## {} — {}\n\n{}\n", author, time, content));
+    }
+    transcript
+}
+
+/// Converts a conversation to JSON, one object per message.
+fn to_json(messages: &[ConversationMessage]) -> serde_json::Value {
+    messages
+        .iter()
+        .map(|message| {
+            json!({
+                "role": message.role,
+                "content": message.content,
+                "created_at": message.created_at,
+            })
+        })
+        .collect()
+}
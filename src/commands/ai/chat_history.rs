@@ -0,0 +1,76 @@
+//! Defines the `/chat_history` command for reviewing the user's AI conversation.
+
+use super::*;
+use tracing::debug;
+
+/// The number of messages shown if no count is given.
+const DEFAULT_HISTORY_COUNT: u32 = 10;
+
+/// The most characters of each message shown in the history.
+const MAX_PREVIEW_LENGTH: usize = 300;
+
+/// Shows the latest messages of your conversation with the AI in this channel.
+///
+/// In channels with a shared conversation, the shared conversation is shown. Long messages are
+/// shortened; use `/chat_export` to get the whole conversation.
+#[poise::command(slash_command, category = "AI")]
+pub async fn chat_history(
+    ctx: Context<'_>,
+    #[description = "Number of messages to show (default: 10)"]
+    #[min = 1]
+    #[max = 50]
+    count: Option<u32>,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat history request received from user {}", author.name);
+
+    let conversation = conversation(ctx);
+    let messages = database::get_conversation_messages(conversation);
+    let content = if messages.is_empty() {
+        "You have no conversation with the AI in this channel yet.".to_string()
+    } else {
+        let count = count.unwrap_or(DEFAULT_HISTORY_COUNT) as usize;
+        history(
+            conversation,
+            &messages[messages.len().saturating_sub(count)..],
+            messages.len(),
+        )
+    };
+
+    reply(ctx, content).await
+}
+
+/// Lists the latest messages of a conversation of `total` messages, dropping the oldest ones
+/// until the list fits in a message.
+fn history(
+    conversation: ConversationKey,
+    messages: &[ConversationMessage],
+    total: usize,
+) -> String {
+    let mut entries: Vec<String> = messages
+        .iter()
+        .map(|message| entry(conversation, message))
+        .collect();
+    loop {
+        let title = format!(
+            "🗒️ Your last {} of {} messages in this channel:",
+            entries.len(),
+            total
+        );
+        let content = format!("{}\n\n{}", title, entries.join("\n\n"));
+        if content.chars().count() <= MAX_MESSAGE_LENGTH || entries.len() <= 1 {
+            return content;
+        }
+        entries.remove(0);
+    }
+}
+
+/// Formats a conversation message: who wrote it, when, and (the start of) what was said.
+fn entry(conversation: ConversationKey, message: &ConversationMessage) -> String {
+    let (author, content) = message_author(conversation, message);
+    let mut preview: String = content.chars().take(MAX_PREVIEW_LENGTH).collect();
+    if preview.len() < content.len() {
+        preview.push('…');
+    }
+    format!("**{}** (<t:{}:R>): {}", author, message.created_at, preview)
+}
@@ -0,0 +1,59 @@
+//! Defines the `/chat_mode` command for sharing a channel's AI conversation among its members.
+
+use super::*;
+use tracing::{debug, error, info};
+
+/// Who takes part in the AI conversation of a channel or thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ConversationMode {
+    /// Each user has their own conversation with the AI.
+    #[name = "Personal"]
+    Personal,
+    /// Everyone's messages go into one conversation.
+    #[name = "Shared"]
+    Shared,
+}
+
+/// Sets whether everyone in this channel or thread shares one conversation with the AI.
+///
+/// In a shared conversation, everyone's `/chat` messages go into the same conversation, each
+/// starting with its author's name, so several people can discuss something with the AI
+/// together. Switching modes keeps both kinds of conversation; each picks up where it left off.
+/// Requires the Manage Channels permission.
+#[poise::command(
+    slash_command,
+    category = "AI",
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    default_member_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn chat_mode(
+    ctx: Context<'_>,
+    #[description = "Personal or shared conversations"] mode: ConversationMode,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat mode request received from user {}", author.name);
+
+    let channel_id = ctx.channel_id();
+    let shared = mode == ConversationMode::Shared;
+    let content = match database::set_conversation_shared(channel_id, shared) {
+        Ok(()) => {
+            info!(
+                "Set conversation mode of channel {} to {:?}",
+                channel_id, mode
+            );
+            if shared {
+                "👥 Everyone in this channel now shares one conversation with the AI.".to_string()
+            } else {
+                "👤 Everyone in this channel now has their own conversation with the AI."
+                    .to_string()
+            }
+        }
+        Err(e) => {
+            error!("Failed to set conversation mode: {}", e);
+            format!("Failed to set the conversation mode: {}", e)
+        }
+    };
+
+    reply(ctx, content).await
+}
@@ -0,0 +1,71 @@
+//! Defines the `/chat_options` command for saving the generation options the AI uses with you.
+
+use super::*;
+use tracing::{debug, error, info};
+
+/// Shows or changes the generation options the AI uses when you chat with it.
+///
+/// Given options are saved as your defaults and the others are kept; `reset` clears the saved
+/// options first. Your defaults win over the persona's options and the model's defaults, and
+/// the options given to `/chat` win over your defaults for a single message.
+#[poise::command(slash_command, category = "AI")]
+pub async fn chat_options(
+    ctx: Context<'_>,
+    #[description = "Sampling temperature (higher is more creative)"]
+    #[min = 0.0]
+    #[max = 2.0]
+    temperature: Option<f32>,
+    #[description = "Most tokens in a response"]
+    #[min = 1]
+    #[max = 32768]
+    max_tokens: Option<u32>,
+    #[description = "Random seed, for reproducible responses"] seed: Option<i32>,
+    #[description = "Context window size in tokens"]
+    #[min = 512]
+    #[max = 131072]
+    context_size: Option<u32>,
+    #[description = "Clear your saved options first (default: false)"] reset: Option<bool>,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat options request received from user {}", author.name);
+
+    let given = GenerationOptions {
+        temperature,
+        num_predict: max_tokens,
+        seed,
+        num_ctx: context_size,
+        ..GenerationOptions::default()
+    };
+    let reset = reset.unwrap_or(false);
+    let saved = database::get_user_options(author.id);
+
+    // Without changes, show the saved options.
+    if given.is_empty() && !reset {
+        return reply(ctx, format!("⚙️ **Your AI options**\n{}", describe(&saved))).await;
+    }
+
+    let options = if reset { given } else { given.or(saved) };
+    let content = match database::set_user_options(author, &options) {
+        Ok(()) => {
+            info!("Saved AI options of {}: {:?}", author.name, options);
+            format!("⚙️ **Your AI options are saved**\n{}", describe(&options))
+        }
+        Err(e) => {
+            error!("Failed to save AI options of {}: {}", author.name, e);
+            format!("Failed to save your AI options: {}", e)
+        }
+    };
+    reply(ctx, content).await
+}
+
+/// Lists a user's saved options, one per line.
+fn describe(options: &GenerationOptions) -> String {
+    let value = |value: Option<String>| value.unwrap_or_else(|| "default".to_string());
+    format!(
+        "Temperature: {}\nMax tokens: {}\nSeed: {}\nContext size: {}",
+        value(options.temperature.map(|value| value.to_string())),
+        value(options.num_predict.map(|value| value.to_string())),
+        value(options.seed.map(|value| value.to_string())),
+        value(options.num_ctx.map(|value| value.to_string())),
+    )
+}
@@ -0,0 +1,79 @@
+//! Defines the `/chat_reset` command for clearing the user's AI conversation history.
+
+use super::*;
+use tracing::{debug, error, info};
+
+/// Makes the AI forget your conversation in this channel.
+///
+/// Your messages and the AI's replies in this channel are deleted, so the next `/chat` starts
+/// a new conversation. In channels with a shared conversation, the shared conversation is
+/// cleared for everyone, which requires the Manage Channels permission. Set `all_channels` to
+/// clear your own conversations in every channel.
+#[poise::command(slash_command, category = "AI")]
+pub async fn chat_reset(
+    ctx: Context<'_>,
+    #[description = "Clear your conversations in every channel"] all_channels: Option<bool>,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat reset request received from user {}", author.name);
+
+    // Clear the conversation in this channel, or the user's own ones in every channel.
+    let all_channels = all_channels.unwrap_or(false);
+    let conversation = conversation(ctx);
+    if !all_channels && conversation.is_shared() && !can_manage_channels(ctx).await {
+        return reply(
+            ctx,
+            "Clearing the shared conversation requires the Manage Channels permission.",
+        )
+        .await;
+    }
+    let result = if all_channels {
+        database::delete_user_conversations(author.id)
+    } else {
+        database::delete_conversation(conversation)
+    };
+
+    let content = match result {
+        Ok(0) => "There was no conversation to clear.".to_string(),
+        Ok(count) => {
+            info!(
+                "Cleared {} conversation messages for user {}",
+                count, author.name
+            );
+            if all_channels {
+                format!("🧹 Cleared your conversations in every channel ({count} messages).")
+            } else if conversation.is_shared() {
+                format!("🧹 Cleared the shared conversation in this channel ({count} messages).")
+            } else {
+                format!("🧹 Cleared your conversation in this channel ({count} messages).")
+            }
+        }
+        Err(e) => {
+            error!("Failed to clear conversation for {}: {}", author.name, e);
+            format!("Failed to clear your conversation: {}", e)
+        }
+    };
+
+    reply(ctx, content).await
+}
+
+/// Checks whether the command author has the Manage Channels permission in the command's
+/// channel.
+async fn can_manage_channels(ctx: Context<'_>) -> bool {
+    let (Some(member), Some(channel)) = (ctx.author_member().await, ctx.guild_channel().await)
+    else {
+        return false;
+    };
+    ctx.guild().is_some_and(|guild| {
+        // Threads take their permissions from their parent channel.
+        let channel = match channel.parent_id {
+            Some(parent_id) if channel.thread_metadata.is_some() => {
+                guild.channels.get(&parent_id).unwrap_or(&channel)
+            }
+            _ => &channel,
+        };
+        guild
+            .user_permissions_in(channel, &member)
+            .manage_channels()
+    })
+}
@@ -0,0 +1,84 @@
+//! Defines the `/chat_thread` command for starting a shared AI conversation in a new thread.
+
+use poise::serenity_prelude::{
+    ChannelType, CreateAllowedMentions, CreateMessage, CreateThread, Mentionable,
+};
+
+use super::*;
+use tracing::{debug, error, info};
+
+/// The maximum length of a thread name.
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+/// Starts a thread for a new conversation with the AI that everyone in it shares.
+///
+/// The thread is named after the topic. Use `/chat` in the thread to talk with the AI;
+/// everyone's messages go into the same conversation, each starting with its author's name.
+/// Requires the Create Public Threads permission.
+#[poise::command(
+    slash_command,
+    category = "AI",
+    guild_only,
+    required_permissions = "CREATE_PUBLIC_THREADS",
+    default_member_permissions = "CREATE_PUBLIC_THREADS"
+)]
+pub async fn chat_thread(
+    ctx: Context<'_>,
+    #[description = "What the conversation is about"] topic: String,
+) -> CommandResult {
+    let author = ctx.author();
+    debug!("Chat thread request received from user {}", author.name);
+
+    // Threads can't be started inside other threads.
+    if ctx
+        .guild_channel()
+        .await
+        .is_some_and(|channel| channel.thread_metadata.is_some())
+    {
+        ctx.say("Threads can't be started inside a thread; use `/chat` here instead.")
+            .await?;
+        return Ok(());
+    }
+
+    // Create the thread and share its conversation.
+    let name: String = topic.chars().take(MAX_THREAD_NAME_LENGTH).collect();
+    let thread = match ctx
+        .channel_id()
+        .create_thread(ctx, CreateThread::new(name).kind(ChannelType::PublicThread))
+        .await
+    {
+        Ok(thread) => thread,
+        Err(e) => {
+            error!("Failed to create chat thread: {}", e);
+            ctx.say(format!("Failed to start a thread: {}", e)).await?;
+            return Ok(());
+        }
+    };
+    database::set_conversation_shared(thread.id, true)?;
+    info!(
+        "User {} started shared chat thread {}",
+        author.name, thread.id
+    );
+
+    // Welcome the author into the thread, and point to it from the command's channel.
+    // Only the author is pinged, whatever mentions the topic contains.
+    thread
+        .id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(format!(
+                    "🧵 {} started a conversation with the AI about **{}**. Use `/chat` here: everyone's messages go into the same conversation.",
+                    author.mention(),
+                    topic
+                ))
+                .allowed_mentions(CreateAllowedMentions::new().users([author.id])),
+        )
+        .await?;
+    ctx.say(format!(
+        "🧵 Started a shared AI conversation in {}.",
+        thread.mention()
+    ))
+    .await?;
+    Ok(())
+}
@@ -31,7 +31,7 @@ pub async fn get_model(ctx: Context<'_>) -> CommandResult {
     };
 
     debug!("Retrieved model '{}' for user {}", model, author.name);
-    
+
     // Send the retrieved model name back to the user.
     ctx.say(format!("Your currently active model is: **{}**", model))
         .await?;
@@ -0,0 +1,73 @@
+//! Reads the images attached to Discord messages, for vision models to look at.
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use ollama_rs::generation::images::Image;
+use poise::serenity_prelude::Attachment;
+use tracing::debug;
+
+use crate::Error;
+
+/// The largest image sent to the AI, in bytes.
+const MAX_IMAGE_SIZE: u32 = 10 * 1024 * 1024;
+
+/// Returns whether an attachment is an image, going by its content type.
+pub fn is_image(attachment: &Attachment) -> bool {
+    attachment
+        .content_type
+        .as_deref()
+        .is_some_and(|content_type| content_type.starts_with("image/"))
+}
+
+/// Downloads an image attachment and encodes it as base64 for the Ollama API.
+///
+/// Returns an error for attachments that aren't images or are larger than `MAX_IMAGE_SIZE`.
+pub async fn encode_image(attachment: &Attachment) -> Result<Image, Error> {
+    if !is_image(attachment) {
+        return Err(format!("**{}** isn't an image.", attachment.filename).into());
+    }
+    if attachment.size > MAX_IMAGE_SIZE {
+        return Err(format!(
+            "**{}** is too large; images can be at most {} MB.",
+            attachment.filename,
+            MAX_IMAGE_SIZE / 1024 / 1024
+        )
+        .into());
+    }
+
+    debug!(
+        "Downloading image {} ({} bytes)",
+        attachment.filename, attachment.size
+    );
+    let bytes = attachment.download().await?;
+    Ok(Image::from_base64(BASE64_STANDARD.encode(bytes)))
+}
+
+/// Module containing tests for reading attached images.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Test helper: Creates an attachment with the given content type.
+    fn attachment(content_type: Option<&str>) -> Attachment {
+        serde_json::from_value(json!({
+            "id": "1",
+            "filename": "screenshot.png",
+            "size": 1024,
+            "url": "https://cdn.discordapp.com/attachments/1/1/screenshot.png",
+            "proxy_url": "https://media.discordapp.net/attachments/1/1/screenshot.png",
+            "content_type": content_type,
+        }))
+        .expect("Valid attachment")
+    }
+
+    /// Tests recognizing image attachments by their content type.
+    #[test]
+    fn test_is_image() {
+        assert!(is_image(&attachment(Some("image/png"))));
+        assert!(is_image(&attachment(Some("image/jpeg"))));
+        assert!(!is_image(&attachment(Some("text/plain"))));
+        assert!(!is_image(&attachment(None)));
+    }
+}
@@ -0,0 +1,269 @@
+//! Splits long Markdown responses into messages that fit Discord's length limit.
+//! Messages are split at paragraph, line, sentence, or word boundaries (in that order of
+//! preference), never inside a link, and code blocks are kept whole where possible or else
+//! closed and reopened with the same language tag.
+
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// The fence that opens or closes a Markdown code block.
+const CODE_FENCE: &str = "```";
+
+/// Matches Markdown links (`[text](url)`), autolinks (`<url>`), and bare URLs.
+static LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[[^\]\n]*\]\([^)\s]*\)|<https?://[^>\s]*>|https?://\S+").unwrap()
+});
+
+/// The places text can be split at, from most to least preferred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Boundary {
+    /// After a blank line.
+    Paragraph,
+    /// After a line break, e.g. between list items.
+    Line,
+    /// After a sentence-ending punctuation mark followed by a space.
+    Sentence,
+    /// After a space.
+    Word,
+}
+
+impl Boundary {
+    /// All boundaries, from most to least preferred.
+    const ALL: [Boundary; 4] = [
+        Boundary::Paragraph,
+        Boundary::Line,
+        Boundary::Sentence,
+        Boundary::Word,
+    ];
+
+    /// How much of the message must come before the boundary for it to be used, as a divisor
+    /// of the message length. Splitting early at a better boundary beats filling the message.
+    fn min_fill_divisor(self) -> usize {
+        match self {
+            Boundary::Paragraph => 3,
+            Boundary::Line | Boundary::Sentence => 2,
+            Boundary::Word => usize::MAX,
+        }
+    }
+}
+
+/// Splits off the first message of `text` if it is longer than `limit` characters.
+/// Returns `None` if the text fits in one message, or the completed message and the remaining
+/// text otherwise.
+///
+/// A code block that would be split is moved to the next message whole if it fits in one; a
+/// longer code block is closed at the end of the message and reopened with the same fence
+/// (including its language tag) at the start of the next.
+pub fn split_chunk(text: &str, limit: usize) -> Option<(String, String)> {
+    if text.chars().count() <= limit {
+        return None;
+    }
+
+    // Leave room for closing a code block at the end of the message.
+    let max = char_index(text, limit.saturating_sub(CODE_FENCE.len() + 1));
+    let window = &text[..max];
+
+    // Cuts inside a link would break it.
+    let links: Vec<Range<usize>> = LINK_REGEX.find_iter(text).map(|m| m.range()).collect();
+    let in_link = |cut: usize| links.iter().any(|link| link.start < cut && cut < link.end);
+
+    // Find the last boundary of each kind outside code blocks, the last line break inside
+    // them, and the code block still open where the window ends (as its start and fence line).
+    let mut boundaries: [Option<usize>; 4] = [None; 4];
+    let mut record = |boundary: Boundary, cut: usize| {
+        if !in_link(cut) {
+            boundaries[boundary as usize] = Some(cut);
+        }
+    };
+    let mut open_block: Option<(usize, &str)> = None;
+    let mut last_break_in_block = None;
+    let mut offset = 0;
+    for line in window.split_inclusive('\n') {
+        let end = offset + line.len();
+        let complete = line.ends_with('\n');
+        let is_fence = complete && line.trim_start().starts_with(CODE_FENCE);
+
+        // Spaces within prose lines are sentence or word boundaries.
+        if open_block.is_none() && !is_fence {
+            for (index, _) in line.match_indices(' ') {
+                let cut = offset + index + 1;
+                if line[..index].ends_with(['.', '!', '?', ':', ';']) {
+                    record(Boundary::Sentence, cut);
+                }
+                record(Boundary::Word, cut);
+            }
+        }
+        if !complete {
+            break;
+        }
+
+        if is_fence {
+            open_block = match open_block {
+                Some(_) => None,
+                None => Some((offset, line.trim_end())),
+            };
+        }
+        match open_block {
+            Some(_) => last_break_in_block = Some(end),
+            None => {
+                if line.trim().is_empty() {
+                    record(Boundary::Paragraph, end);
+                }
+                record(Boundary::Line, end);
+            }
+        }
+        offset = end;
+    }
+
+    if let Some((start, fence)) = open_block {
+        // Move the code block to the next message whole if it fits there.
+        let block_end = text[start + fence.len()..]
+            .find(&format!("\n{}", CODE_FENCE))
+            .map_or(text.len(), |index| {
+                start + fence.len() + index + 1 + CODE_FENCE.len()
+            });
+        if start > 0 && text[start..block_end].chars().count() <= limit {
+            return Some(split_at(text, start));
+        }
+
+        // Otherwise close the block at its last line break that fits, and reopen it in the
+        // next message. Each message has to get past the fence line to make progress.
+        let after_fence = start + fence.len() + 1;
+        let cut = match last_break_in_block.filter(|&cut| cut > after_fence) {
+            Some(cut) => cut,
+            None if start > 0 => return Some(split_at(text, start)),
+            None if max > after_fence => max,
+            None => return Some(split_at(text, max)),
+        };
+        let (chunk, rest) = text.split_at(cut);
+        let chunk = format!("{}\n{}", chunk.trim_end_matches('\n'), CODE_FENCE);
+        let rest = format!("{}\n{}", fence, rest);
+        return Some((chunk, rest));
+    }
+
+    // Use the most preferred boundary that leaves the message reasonably full.
+    let cut = Boundary::ALL
+        .into_iter()
+        .find_map(|boundary| {
+            boundaries[boundary as usize].filter(|&cut| cut > max / boundary.min_fill_divisor())
+        })
+        // With no boundary at all, cut before a link rather than through it.
+        .or_else(|| {
+            links
+                .iter()
+                .find(|link| link.start < max && max < link.end && link.start > 0)
+                .map(|link| link.start)
+        })
+        .unwrap_or(max);
+    Some(split_at(text, cut))
+}
+
+/// Returns the byte index of the character at `count`, or the length of the text if it is
+/// shorter.
+fn char_index(text: &str, count: usize) -> usize {
+    text.char_indices()
+        .nth(count)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+/// Splits text at a byte index, trimming the whitespace around the cut.
+fn split_at(text: &str, index: usize) -> (String, String) {
+    let (chunk, rest) = text.split_at(index);
+    (chunk.trim_end().to_string(), rest.trim_start().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunk_fits() {
+        assert_eq!(split_chunk("short message", 20), None);
+    }
+
+    #[test]
+    fn test_split_chunk_between_words() {
+        let (chunk, rest) = split_chunk("one two three four", 12).unwrap();
+        assert_eq!(chunk, "one two");
+        assert_eq!(rest, "three four");
+    }
+
+    #[test]
+    fn test_split_chunk_prefers_paragraph() {
+        let text = "First paragraph here.\n\nSecond paragraph. It goes on and on.";
+        let (chunk, rest) = split_chunk(text, 48).unwrap();
+        assert_eq!(chunk, "First paragraph here.");
+        assert_eq!(rest, "Second paragraph. It goes on and on.");
+    }
+
+    #[test]
+    fn test_split_chunk_prefers_sentence() {
+        let text = "This is the first sentence. This is the second one.";
+        let (chunk, rest) = split_chunk(text, 44).unwrap();
+        assert_eq!(chunk, "This is the first sentence.");
+        assert_eq!(rest, "This is the second one.");
+    }
+
+    #[test]
+    fn test_split_chunk_between_list_items() {
+        let text = "- first item\n- second item\n- third item";
+        let (chunk, rest) = split_chunk(text, 34).unwrap();
+        assert_eq!(chunk, "- first item\n- second item");
+        assert_eq!(rest, "- third item");
+    }
+
+    #[test]
+    fn test_split_chunk_keeps_links_whole() {
+        let text = "See [the docs page](https://example.com/docs) now";
+        let (chunk, rest) = split_chunk(text, 30).unwrap();
+        assert_eq!(chunk, "See");
+        assert_eq!(rest, "[the docs page](https://example.com/docs) now");
+    }
+
+    #[test]
+    fn test_split_chunk_never_cuts_urls() {
+        let text = "Visit https://example.com/a/very/long/path today";
+        let (chunk, rest) = split_chunk(text, 30).unwrap();
+        assert_eq!(chunk, "Visit");
+        assert_eq!(rest, "https://example.com/a/very/long/path today");
+    }
+
+    #[test]
+    fn test_split_chunk_moves_code_block() {
+        let text = "Intro\n```rust\nfn main() {}\n```\n";
+        let (chunk, rest) = split_chunk(text, 28).unwrap();
+        assert_eq!(chunk, "Intro");
+        assert_eq!(rest, "```rust\nfn main() {}\n```\n");
+    }
+
+    #[test]
+    fn test_split_chunk_reopens_long_code_block() {
+        let text = "```rust\nlet a = 1;\nlet b = 2;\nlet c = 3;\n```";
+        let (chunk, rest) = split_chunk(text, 34).unwrap();
+        assert_eq!(chunk, "```rust\nlet a = 1;\nlet b = 2;\n```");
+        assert_eq!(rest, "```rust\nlet c = 3;\n```");
+    }
+
+    #[test]
+    fn test_split_chunk_respects_limit() {
+        let text = format!(
+            "{}\n\n```python\n{}```\n\n{}",
+            "Some words. ".repeat(300),
+            "print('hello')\n".repeat(300),
+            "- item\n".repeat(300)
+        );
+        let mut rest = text;
+        let mut chunks = 0;
+        while let Some((chunk, remainder)) = split_chunk(&rest, 2000) {
+            assert!(chunk.chars().count() <= 2000);
+            // Every message opens and closes its code blocks.
+            assert_eq!(chunk.matches(CODE_FENCE).count() % 2, 0);
+            rest = remainder;
+            chunks += 1;
+        }
+        assert!(chunks > 3);
+        assert!(rest.chars().count() <= 2000);
+    }
+}
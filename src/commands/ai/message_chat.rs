@@ -0,0 +1,244 @@
+//! Lets users chat with the AI without slash commands, by mentioning the bot or replying to one
+//! of its AI messages. Replies continue the conversation of the reply chain they belong to, and
+//! mentions outside of a reply continue the author's conversation in the channel, like `/chat`.
+
+use ollama_rs::generation::chat::ChatMessage;
+use poise::serenity_prelude::{Context, Message, UserId};
+use tracing::{debug, error, warn};
+
+use super::streaming_reply::{MAX_INLINE_MESSAGES, StreamingReply};
+use super::*;
+use crate::utils::ai_tools;
+use crate::utils::ollama_client::OLLAMA_CLIENT;
+
+/// The most messages of a reply chain sent to the AI as context.
+const MAX_CHAIN_LENGTH: usize = 10;
+
+/// Separates the user's prompt from the AI's response in the first message of a `/chat` reply.
+const RESPONSE_SEPARATOR: &str = "\n\n**AI**: ";
+
+/// Answers a message that mentions the bot or replies to one of its AI messages.
+///
+/// Returns `true` if the message was addressed to the AI, so other handlers can ignore it.
+/// Messages from bots are never answered. Only mentions written in the message count, not the
+/// ping Discord adds to replies, so replies to the bot's other messages (like the music player)
+/// are left alone.
+pub async fn handle_message(ctx: &Context, message: &Message) -> bool {
+    if message.author.bot {
+        return false;
+    }
+    let bot_id = ctx.cache.current_user().id;
+    let replies_to_ai = message
+        .referenced_message
+        .as_deref()
+        .is_some_and(|referenced| {
+            referenced.author.id == bot_id && database::is_ai_message(referenced.id)
+        });
+    if !replies_to_ai && !mentions(&message.content, bot_id) {
+        return false;
+    }
+
+    // A bare mention has nothing to answer.
+    let prompt = strip_mention(&message.content, bot_id);
+    if prompt.is_empty() {
+        return true;
+    }
+    debug!(
+        "Chat message received from user {} in channel {}",
+        message.author.name, message.channel_id
+    );
+
+    if let Err(e) = answer(ctx, message, bot_id, &prompt).await {
+        error!(
+            "Failed to get AI response for {}: {}",
+            message.author.name, e
+        );
+        if let Err(e) = message
+            .reply(&ctx.http, format!("Failed to get an AI response: {}", e))
+            .await
+        {
+            warn!("Failed to report AI response error: {}", e);
+        }
+    }
+    true
+}
+
+/// Streams the AI's answer to a message in reply to it.
+///
+/// Replies are answered with the reply chain as context. Other messages continue the author's
+/// conversation in the channel, and the exchange is saved to it.
+async fn answer(ctx: &Context, message: &Message, bot_id: UserId, prompt: &str) -> CommandResult {
+    // Show that the AI is writing until the reply is sent.
+    if let Err(e) = message.channel_id.broadcast_typing(&ctx.http).await {
+        debug!("Failed to show typing indicator: {}", e);
+    }
+    let reply = StreamingReply::replying_to(ctx, message).attach_after(MAX_INLINE_MESSAGES);
+    let author = &message.author;
+    let persona = active_persona(message.guild_id, author.id);
+
+    if let Some(referenced) = message.referenced_message.as_deref() {
+        // Continue the conversation of the reply chain.
+        let context = reply_chain(ctx, referenced, bot_id).await;
+        let prompt = format!("{}: {}", author.display_name(), prompt);
+        let stream = OLLAMA_CLIENT
+            .chat_stream_with_context(author, context, settings(ctx, message, &persona), &prompt)
+            .await?;
+        reply.relay(stream).await.map(|_| ())
+    } else {
+        // Continue the author's conversation in the channel, like `/chat`.
+        let conversation = database::get_conversation_key(author.id, message.channel_id);
+        let prompt = attributed_prompt(conversation, author, prompt);
+        let stream = OLLAMA_CLIENT
+            .chat_stream(
+                author,
+                conversation,
+                settings(ctx, message, &persona),
+                &prompt,
+            )
+            .await?;
+        if let Some(response) = reply.relay(stream).await? {
+            OLLAMA_CLIENT.record_exchange(conversation, &prompt, &response);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the settings for answering a message: the author's persona, and the bot's tools for
+/// the message's channel.
+fn settings<'a>(
+    ctx: &Context,
+    message: &Message,
+    persona: &'a Option<Persona>,
+) -> ChatSettings<'a> {
+    ChatSettings {
+        persona: persona.as_ref(),
+        tools: Some(ToolContext::from_message(ctx, message)),
+        ..ChatSettings::default()
+    }
+}
+
+/// Collects the chain of replies leading up to and including `start` as chat messages, oldest
+/// first. The bot's messages become the assistant's messages, and everyone else's start with
+/// their author's name. At most `MAX_CHAIN_LENGTH` messages are collected.
+async fn reply_chain(ctx: &Context, start: &Message, bot_id: UserId) -> Vec<ChatMessage> {
+    let mut chain = Vec::new();
+    let mut next = Some(start.clone());
+    while let Some(message) = next.take() {
+        // Collect backwards, so the newest messages are kept if the chain is too long.
+        chain.extend(to_chat_messages(&message, bot_id).into_iter().rev());
+        if chain.len() >= MAX_CHAIN_LENGTH {
+            chain.truncate(MAX_CHAIN_LENGTH);
+            break;
+        }
+
+        // Only the first referenced message comes with the event; fetch the ones before it.
+        next = match (message.referenced_message, message.message_reference) {
+            (Some(referenced), _) => Some(*referenced),
+            (None, Some(reference)) => match reference.message_id {
+                Some(message_id) => reference
+                    .channel_id
+                    .message(&ctx.http, message_id)
+                    .await
+                    .inspect_err(|e| debug!("Failed to fetch replied-to message: {}", e))
+                    .ok(),
+                None => None,
+            },
+            (None, None) => None,
+        };
+    }
+    chain.reverse();
+    chain
+}
+
+/// Converts a Discord message into chat messages, oldest first.
+///
+/// The first message of a `/chat` reply holds both the user's prompt and the AI's response, so
+/// it is split into both. The summary of the tools the AI used is left out of its replies.
+fn to_chat_messages(message: &Message, bot_id: UserId) -> Vec<ChatMessage> {
+    let mut content = strip_mention(&message.content, bot_id);
+    if message.author.id == bot_id {
+        content = ai_tools::strip_tool_summary(&content).to_string();
+    }
+    if content.is_empty() {
+        return Vec::new();
+    }
+    if message.author.id != bot_id {
+        return vec![ChatMessage::user(format!(
+            "{}: {}",
+            message.author.display_name(),
+            content
+        ))];
+    }
+    match split_chat_reply(&content) {
+        Some((prompt, response)) => vec![
+            ChatMessage::user(prompt),
+            ChatMessage::assistant(response.to_string()),
+        ],
+        None => vec![ChatMessage::assistant(content)],
+    }
+}
+
+/// Splits the first message of a `/chat` reply (`**Name**: prompt` followed by
+/// `**AI**: response`) into the prompt, starting with the author's name, and the response.
+fn split_chat_reply(content: &str) -> Option<(String, &str)> {
+    let (name, rest) = content.strip_prefix("**")?.split_once("**: ")?;
+    let (prompt, response) = rest.split_once(RESPONSE_SEPARATOR)?;
+    Some((format!("{}: {}", name, prompt), response))
+}
+
+/// Checks whether a message's text mentions the bot.
+fn mentions(content: &str, bot_id: UserId) -> bool {
+    content.contains(&format!("<@{}>", bot_id)) || content.contains(&format!("<@!{}>", bot_id))
+}
+
+/// Removes mentions of the bot from a message and trims it.
+fn strip_mention(content: &str, bot_id: UserId) -> String {
+    content
+        .replace(&format!("<@{}>", bot_id), "")
+        .replace(&format!("<@!{}>", bot_id), "")
+        .trim()
+        .to_string()
+}
+
+/// Module containing tests for chatting through messages.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests removing mentions of the bot from a message.
+    #[test]
+    fn test_strip_mention() {
+        let bot_id = UserId::new(42);
+        assert_eq!(strip_mention("<@42> hello there", bot_id), "hello there");
+        assert_eq!(
+            strip_mention("hey <@!42>, how are you?", bot_id),
+            "hey , how are you?"
+        );
+        assert_eq!(strip_mention("<@7> hi", bot_id), "<@7> hi");
+        assert_eq!(strip_mention(" <@42> ", bot_id), "");
+    }
+
+    /// Tests recognizing mentions of the bot written in a message.
+    #[test]
+    fn test_mentions() {
+        let bot_id = UserId::new(42);
+        assert!(mentions("<@42> hello there", bot_id));
+        assert!(mentions("hey <@!42>, how are you?", bot_id));
+        assert!(!mentions("<@7> hi", bot_id));
+        assert!(!mentions("skip this one", bot_id));
+    }
+
+    /// Tests splitting the first message of a `/chat` reply.
+    #[test]
+    fn test_split_chat_reply() {
+        assert_eq!(
+            split_chat_reply("**Alice**: What is Rust?\n\n**AI**: A programming language."),
+            Some((
+                "Alice: What is Rust?".to_string(),
+                "A programming language."
+            ))
+        );
+        assert_eq!(split_chat_reply("Just a continued response."), None);
+        assert_eq!(split_chat_reply("**Bold** statement: no prompt"), None);
+    }
+}
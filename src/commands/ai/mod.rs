@@ -1,14 +1,38 @@
 //! This module contains all commands related to Artificial Intelligence features,
 //! primarily interacting with an Ollama client and potentially other services like Brave Search.
 
+/// Submodule defining the "Ask AI about this image" context menu command.
+pub(crate) mod ask_about_image;
 /// Submodule defining the `/chat` command.
 pub(crate) mod chat;
+/// Submodule defining the `/chat_export` command.
+pub(crate) mod chat_export;
+/// Submodule defining the `/chat_history` command.
+pub(crate) mod chat_history;
+/// Submodule defining the `/chat_mode` command.
+pub(crate) mod chat_mode;
+/// Submodule defining the `/chat_options` command.
+pub(crate) mod chat_options;
+/// Submodule defining the `/chat_reset` command.
+pub(crate) mod chat_reset;
+/// Submodule defining the `/chat_thread` command.
+pub(crate) mod chat_thread;
 /// Submodule defining the `/get_model` command.
 pub(crate) mod get_model;
+/// Submodule for reading images attached to messages.
+pub(crate) mod images;
 /// Submodule defining the `/list_models` command.
 pub(crate) mod list_models;
+/// Submodule for splitting long Markdown responses into messages.
+pub(crate) mod markdown_chunker;
+/// Submodule for chatting with the AI by mentioning the bot or replying to it.
+pub(crate) mod message_chat;
+/// Submodule defining the `/persona` command group.
+pub(crate) mod persona;
 /// Submodule defining the `/set_model` command.
 pub(crate) mod set_model;
+/// Submodule for streaming AI responses into progressively edited messages.
+pub(crate) mod streaming_reply;
 
 /// Submodule defining the `/search` command (requires `brave_search` feature).
 #[cfg(feature = "brave_search")]
@@ -16,37 +40,89 @@ pub(crate) mod search;
 
 use crate::CommandResult;
 use crate::Context;
+use crate::utils::ai_tools::ToolContext;
 #[cfg(feature = "brave_search")]
 use crate::utils::brave;
-use crate::utils::database;
+use crate::utils::database::{self, ConversationKey, ConversationMessage, Persona};
+use crate::utils::generation_options::GenerationOptions;
+use crate::utils::ollama_client::ChatSettings;
+use poise::CreateReply;
+use poise::serenity_prelude::{GuildId, User, UserId};
 
 /// The maximum character length allowed for a single Discord message.
 const MAX_MESSAGE_LENGTH: usize = 2000;
 
-/// Sends a potentially long response string by splitting it into chunks
-/// that respect Discord's message length limit.
-///
-/// # Arguments
-///
-/// * `ctx` - The command context.
-/// * `response` - The response string to send. Can be any type that implements `AsRef<str>`.
-pub async fn chunk_response<S: AsRef<str>>(ctx: Context<'_>, response: S) -> CommandResult {
-    let response = response.as_ref();
-    // Create a character iterator for the response string.
-    let mut iter = response.chars();
-    let mut pos = 0;
-    // Loop while there are still characters left to process.
-    while pos < response.len() {
-        let mut len = 0;
-        // Iterate through the next chunk of characters up to the max length.
-        for ch in iter.by_ref().take(MAX_MESSAGE_LENGTH) {
-            len += ch.len_utf8();
-        }
-        // Send the current chunk.
-        ctx.say(&response[pos..pos + len]).await?;
-        // Update the position marker.
-        pos += len;
+/// Sends an ephemeral reply.
+async fn reply(ctx: Context<'_>, content: impl Into<String>) -> CommandResult {
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Returns the conversation the command's author takes part in, in the command's channel.
+fn conversation(ctx: Context<'_>) -> ConversationKey {
+    database::get_conversation_key(ctx.author().id, ctx.channel_id())
+}
+
+/// Returns the persona the AI takes on for a user: the one they picked in the guild, or the
+/// guild's default. There are no personas outside of guilds.
+fn active_persona(guild_id: Option<GuildId>, user_id: UserId) -> Option<Persona> {
+    guild_id.and_then(|guild_id| database::get_active_persona(guild_id, user_id))
+}
+
+/// Starts a prompt with its author's name in shared conversations, so the model can tell the
+/// participants apart.
+fn attributed_prompt(conversation: ConversationKey, author: &User, prompt: &str) -> String {
+    if conversation.is_shared() {
+        format!("{}: {}", author.display_name(), prompt)
+    } else {
+        prompt.to_string()
     }
+}
 
-    Ok(())
+/// Splits a stored conversation message into the name of its author and its text, for showing
+/// it to users. Messages in shared conversations start with their author's name.
+fn message_author(conversation: ConversationKey, message: &ConversationMessage) -> (&str, &str) {
+    match message.role.as_str() {
+        "user" if conversation.is_shared() => message
+            .content
+            .split_once(": ")
+            .unwrap_or(("Someone", &message.content)),
+        "user" => ("You", &message.content),
+        "assistant" => ("AI", &message.content),
+        _ => ("Summary", &message.content),
+    }
+}
+
+/// Module containing tests for the helpers shared by the AI commands.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serenity::model::id::{ChannelId, UserId};
+
+    /// Tests naming the authors of stored conversation messages.
+    #[test]
+    fn test_message_author() {
+        let stored = |role: &str, content: &str| ConversationMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: 0,
+        };
+        let own = ConversationKey::User(UserId::new(1), ChannelId::new(1));
+        let shared = ConversationKey::Shared(ChannelId::new(1));
+
+        assert_eq!(message_author(own, &stored("user", "Hi")), ("You", "Hi"));
+        assert_eq!(
+            message_author(own, &stored("assistant", "Hello")),
+            ("AI", "Hello")
+        );
+        assert_eq!(
+            message_author(shared, &stored("user", "Alice: Hi: there")),
+            ("Alice", "Hi: there")
+        );
+        assert_eq!(
+            message_author(shared, &stored("system", "Earlier")),
+            ("Summary", "Earlier")
+        );
+    }
 }
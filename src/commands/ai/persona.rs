@@ -0,0 +1,294 @@
+//! Defines the `/persona` command group for managing the personas the AI can take on.
+//! A persona is a named system prompt with optional model options, shared within a guild.
+//! Users pick the persona they chat with, and admins can set a default for the guild.
+
+use futures::{Stream, StreamExt};
+
+use super::*;
+use crate::utils::database::Persona;
+use tracing::{debug, error, info};
+
+/// The longest persona name allowed.
+const MAX_NAME_LENGTH: usize = 32;
+
+/// The most characters of each system prompt shown in the persona list.
+const MAX_PROMPT_PREVIEW_LENGTH: usize = 100;
+
+/// Commands for managing the personas the AI takes on in this server.
+///
+/// A persona sets the AI's system prompt and, optionally, its temperature, top_p, and context
+/// size. Your own choice of persona wins over the server's default.
+#[poise::command(
+    slash_command,
+    subcommands("create", "set", "list", "delete", "guild_default"),
+    category = "AI",
+    guild_only
+)]
+pub async fn persona(_: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Creates a persona for everyone in this server to use.
+///
+/// The system prompt tells the AI who to be and how to respond. Options that are omitted use
+/// the model's defaults.
+#[poise::command(slash_command, guild_only)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "Name of the persona"]
+    #[max_length = 32]
+    name: String,
+    #[description = "Instructions for the AI, e.g. \"You are a helpful pirate.\""]
+    system_prompt: String,
+    #[description = "Sampling temperature (higher is more creative)"]
+    #[min = 0.0]
+    #[max = 2.0]
+    temperature: Option<f32>,
+    #[description = "Nucleus sampling probability"]
+    #[min = 0.0]
+    #[max = 1.0]
+    top_p: Option<f32>,
+    #[description = "Context window size in tokens"]
+    #[min = 512]
+    #[max = 131072]
+    num_ctx: Option<u32>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let author = ctx.author();
+    debug!("Persona create request received from user {}", author.name);
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return reply(ctx, "Persona names must be 1 to 32 characters long.").await;
+    }
+
+    let persona = Persona {
+        name: name.clone(),
+        system_prompt,
+        temperature,
+        top_p,
+        num_ctx,
+        created_by: author.id,
+    };
+    let content = match database::add_persona(guild_id, &persona) {
+        Ok(true) => {
+            info!("User {} created persona '{}'", author.name, name);
+            format!(
+                "🎭 Created the persona **{}**. Use `/persona set {}` to chat with it.",
+                name, name
+            )
+        }
+        Ok(false) => format!("There already is a persona named **{}**.", name),
+        Err(e) => {
+            error!("Failed to create persona '{}': {}", name, e);
+            format!("Failed to create the persona: {}", e)
+        }
+    };
+    reply(ctx, content).await
+}
+
+/// Picks the persona the AI takes on when you chat with it in this server.
+///
+/// Omit the name to go back to the server's default persona.
+#[poise::command(slash_command, guild_only)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Name of the persona (omit for the server default)"]
+    #[autocomplete = "autocomplete_persona"]
+    name: Option<String>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let author = ctx.author();
+    debug!("Persona set request received from user {}", author.name);
+
+    // Use the stored spelling of the name.
+    let persona = match name {
+        Some(name) => match database::get_persona(guild_id, &name) {
+            Some(persona) => Some(persona),
+            None => return reply(ctx, format!("There is no persona named **{}**.", name)).await,
+        },
+        None => None,
+    };
+
+    let name = persona.as_ref().map(|persona| persona.name.as_str());
+    let content = match database::set_user_persona(guild_id, author.id, name) {
+        Ok(()) => match name {
+            Some(name) => format!("🎭 The AI now talks to you as **{}**.", name),
+            None => "🎭 The AI now talks to you as this server's default persona.".to_string(),
+        },
+        Err(e) => {
+            error!("Failed to set persona for {}: {}", author.name, e);
+            format!("Failed to set your persona: {}", e)
+        }
+    };
+    reply(ctx, content).await
+}
+
+/// Lists the personas of this server.
+#[poise::command(slash_command, guild_only)]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let personas = database::get_personas(guild_id);
+    if personas.is_empty() {
+        return reply(
+            ctx,
+            "This server has no personas yet. Create one with `/persona create`.",
+        )
+        .await;
+    }
+
+    let guild_default = database::get_guild_persona(guild_id);
+    let chosen = database::get_user_persona(guild_id, ctx.author().id);
+    let is = |selected: &Option<String>, persona: &Persona| {
+        selected
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(&persona.name))
+    };
+
+    // List the personas, stopping before the message gets too long.
+    let mut content = String::from("🎭 **Personas**\n");
+    for persona in &personas {
+        let mut entry = format!("\n**{}**", persona.name);
+        if is(&guild_default, persona) {
+            entry.push_str(" (server default)");
+        }
+        if is(&chosen, persona) {
+            entry.push_str(" (your choice)");
+        }
+        entry.push_str(&format!(
+            " by <@{}>: {}\n",
+            persona.created_by,
+            preview(&persona.system_prompt)
+        ));
+        if content.len() + entry.len() > MAX_MESSAGE_LENGTH {
+            break;
+        }
+        content.push_str(&entry);
+    }
+    reply(ctx, content).await
+}
+
+/// Deletes a persona of this server.
+///
+/// Only the persona's creator and members with the Manage Server permission can delete it.
+/// Anyone using it goes back to the server's default persona.
+#[poise::command(slash_command, guild_only)]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "Name of the persona"]
+    #[autocomplete = "autocomplete_persona"]
+    name: String,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let author = ctx.author();
+    debug!("Persona delete request received from user {}", author.name);
+
+    let Some(persona) = database::get_persona(guild_id, &name) else {
+        return reply(ctx, format!("There is no persona named **{}**.", name)).await;
+    };
+    if persona.created_by != author.id && !can_manage_guild(ctx).await {
+        return reply(
+            ctx,
+            "Only the persona's creator and members with the Manage Server permission can delete it.",
+        )
+        .await;
+    }
+
+    let content = match database::delete_persona(guild_id, &persona.name) {
+        Ok(_) => {
+            info!("User {} deleted persona '{}'", author.name, persona.name);
+            format!("🗑️ Deleted the persona **{}**.", persona.name)
+        }
+        Err(e) => {
+            error!("Failed to delete persona '{}': {}", persona.name, e);
+            format!("Failed to delete the persona: {}", e)
+        }
+    };
+    reply(ctx, content).await
+}
+
+/// Sets the persona used in this server by members who haven't picked one.
+///
+/// Omit the name to remove the default. Requires the Manage Server permission.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn guild_default(
+    ctx: Context<'_>,
+    #[description = "Name of the persona (omit to remove the default)"]
+    #[autocomplete = "autocomplete_persona"]
+    name: Option<String>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    debug!(
+        "Persona guild default request received from user {}",
+        ctx.author().name
+    );
+
+    // Use the stored spelling of the name.
+    let persona = match name {
+        Some(name) => match database::get_persona(guild_id, &name) {
+            Some(persona) => Some(persona),
+            None => return reply(ctx, format!("There is no persona named **{}**.", name)).await,
+        },
+        None => None,
+    };
+
+    let name = persona.as_ref().map(|persona| persona.name.as_str());
+    let content = match database::set_guild_persona(guild_id, name) {
+        Ok(()) => match name {
+            Some(name) => format!("🎭 **{}** is now this server's default persona.", name),
+            None => "🎭 This server no longer has a default persona.".to_string(),
+        },
+        Err(e) => {
+            error!("Failed to set default persona of guild {}: {}", guild_id, e);
+            format!("Failed to set the default persona: {}", e)
+        }
+    };
+    reply(ctx, content).await
+}
+
+/// Checks whether the command author has the Manage Server permission in the guild.
+async fn can_manage_guild(ctx: Context<'_>) -> bool {
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    ctx.guild()
+        .is_some_and(|guild| guild.member_permissions(&member).manage_guild())
+}
+
+/// Shortens a system prompt to a one-line preview.
+fn preview(system_prompt: &str) -> String {
+    let line = system_prompt.lines().next().unwrap_or_default();
+    let mut preview: String = line.chars().take(MAX_PROMPT_PREVIEW_LENGTH).collect();
+    if preview.len() < system_prompt.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Autocomplete function for persona names, suggesting the guild's personas that start with
+/// the user's partial input.
+async fn autocomplete_persona<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+    let personas = ctx
+        .guild_id()
+        .map(database::get_personas)
+        .unwrap_or_default();
+    let partial = partial.to_lowercase();
+    futures::stream::iter(personas)
+        .filter(move |persona| {
+            futures::future::ready(persona.name.to_lowercase().starts_with(&partial))
+        })
+        .map(|persona| persona.name)
+}
@@ -5,6 +5,7 @@
 use crate::utils::ollama_client::OLLAMA_CLIENT;
 use std::env;
 
+use super::streaming_reply::{MAX_INLINE_MESSAGES, StreamingReply};
 use super::*;
 use tracing::{debug, error, info};
 
@@ -13,13 +14,16 @@ use tracing::{debug, error, info};
 /// Uses the Brave Search API for web results based on the user's query.
 /// The results are then passed to an AI model for summarization.
 /// Requires the `BRAVE_API_KEY` environment variable.
-/// The final summary is sent back to Discord, chunked if necessary.
+/// The summary is streamed back to Discord as it is written, continuing in follow-up messages
+/// when it gets long. The query and summary continue your conversation in this channel, so
+/// `/chat` can follow up on them.
 #[poise::command(slash_command, category = "AI")]
 pub async fn search(
     ctx: Context<'_>,
     #[description = "Your search query"]
     #[rest]
     query: String,
+    #[description = "Send long responses as a Markdown file (default: true)"] as_file: Option<bool>,
 ) -> CommandResult {
     // Defer the response immediately as search and AI processing can take time.
     ctx.defer().await?;
@@ -41,7 +45,7 @@ pub async fn search(
     };
 
     // Perform the web search using the utility function.
-    match brave::search(&query, "https://api.search.brave.com", &api_key).await {
+    match brave::search(&query, brave::API_URL, &api_key).await {
         // Pass api_key
         Ok(results) => {
             debug!("Received search results for query: {}", query);
@@ -59,18 +63,37 @@ pub async fn search(
                 author.name
             );
             // Send the prompt to the AI model for summarization.
-            match OLLAMA_CLIENT.clone().chat(author, &prompt).await {
-                Ok(response) => {
-                    let content = response.message.content;
-                    debug!("Received AI summary for search query");
+            let conversation = conversation(ctx);
+            match OLLAMA_CLIENT
+                .clone()
+                .chat_stream(
+                    author,
+                    conversation,
+                    ChatSettings {
+                        persona: active_persona(ctx.guild_id(), author.id).as_ref(),
+                        ..ChatSettings::default()
+                    },
+                    &prompt,
+                )
+                .await
+            {
+                Ok(stream) => {
+                    debug!("Streaming AI summary for search query");
 
-                    let full_message =
-                    // Format the final response including the original query and the AI summary.
-                        format!("**Search Query**: {query}\n\n**AI Summary**: {content}");
-                    info!("Sending formatted search response to {}", author.name);
-
-                    // Send the potentially long response, chunking if necessary.
-                    chunk_response(ctx, full_message).await
+                    // Start the reply with the original query, followed by the AI summary as it arrives.
+                    let header = format!("**Search Query**: {query}\n\n**AI Summary**: ");
+                    let mut reply = StreamingReply::new(ctx, header);
+                    if as_file.unwrap_or(true) {
+                        reply = reply.attach_after(MAX_INLINE_MESSAGES);
+                    }
+                    // Remember the query and summary once the summary is complete. The search
+                    // results themselves are left out to keep the history short.
+                    if let Some(summary) = reply.relay(stream).await? {
+                        let request = format!("Search the web for: {}", query);
+                        let request = attributed_prompt(conversation, author, &request);
+                        OLLAMA_CLIENT.record_exchange(conversation, &request, &summary);
+                    }
+                    Ok(())
                 }
                 Err(e) => {
                     error!("Failed to get AI analysis for search results: {}", e);
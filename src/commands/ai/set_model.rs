@@ -112,7 +112,7 @@ async fn autocomplete_model<'a>(
     let model_list = list_models().await;
 
     // Create a stream from the model list.
-    futures::stream::iter(model_list.into_iter())
+    futures::stream::iter(model_list)
         // Filter the stream: keep models whose names start with the partial input.
         .filter(move |model| futures::future::ready(model.name.starts_with(partial)))
         // Map the filtered models to just their names (as strings).
@@ -0,0 +1,306 @@
+//! Progressively edits a Discord reply as an AI response streams in.
+//! Edits are throttled to stay within Discord's rate limits, and once a message reaches the
+//! length limit, the response rolls over into a follow-up message. Very long responses can be
+//! sent as an attached Markdown file instead of a long run of messages. Replies can answer a
+//! command or a plain message, and the messages they send are remembered as AI messages so
+//! users can reply to them to continue the conversation.
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use futures::StreamExt;
+use ollama_rs::generation::chat::{ChatMessageResponseStream, MessageRole};
+use poise::serenity_prelude::{self as serenity, CreateAttachment, CreateMessage, EditMessage};
+use poise::{CreateReply, ReplyHandle};
+use tracing::warn;
+
+use super::MAX_MESSAGE_LENGTH;
+use super::markdown_chunker::split_chunk;
+use crate::CommandResult;
+use crate::Context;
+use crate::Error;
+use crate::utils::database;
+
+/// The minimum time between two edits of the message being streamed into.
+const EDIT_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// The number of messages a response can take up before the rest of it is attached as a file,
+/// when long responses are sent as files.
+pub const MAX_INLINE_MESSAGES: usize = 3;
+
+/// The note ending the last message of a response that continues in an attached file.
+const ATTACHMENT_NOTE: &str = "\n\n📎 *Continued in the attached file…*";
+
+/// The note ending a response whose stream was cut off.
+const CUT_OFF_NOTE: &str = "\n\n⚠️ *The response was cut off.*";
+
+/// The name of the file long responses are attached as.
+const ATTACHMENT_NAME: &str = "response.md";
+
+/// Where a streaming reply is sent.
+enum Destination<'a> {
+    /// The response to a command.
+    Command(Context<'a>),
+    /// Replies to a message, e.g. one mentioning the bot.
+    Message(&'a serenity::Context, &'a serenity::Message),
+}
+
+/// A message of a streaming reply that has been sent and can be edited.
+enum SentMessage<'a> {
+    /// A message sent in response to a command.
+    Command(Context<'a>, ReplyHandle<'a>),
+    /// A message sent in reply to a message.
+    Message(&'a serenity::Context, Box<serenity::Message>),
+}
+
+impl<'a> Destination<'a> {
+    /// Sends a message of the reply. The first message of a reply to a message references it.
+    async fn send(&self, content: String, first: bool) -> Result<SentMessage<'a>, Error> {
+        let sent = match *self {
+            Destination::Command(ctx) => {
+                let handle = ctx.send(CreateReply::default().content(content)).await?;
+                let message = handle.message().await?;
+                remember_ai_message(&message);
+                SentMessage::Command(ctx, handle)
+            }
+            Destination::Message(ctx, trigger) => {
+                let mut builder = CreateMessage::new().content(content);
+                if first {
+                    builder = builder.reference_message(trigger);
+                }
+                let message = trigger.channel_id.send_message(ctx, builder).await?;
+                remember_ai_message(&message);
+                SentMessage::Message(ctx, Box::new(message))
+            }
+        };
+        Ok(sent)
+    }
+
+    /// Sends an attachment as the last message of the reply.
+    async fn attach(&self, attachment: CreateAttachment) -> CommandResult {
+        match *self {
+            Destination::Command(ctx) => {
+                ctx.send(CreateReply::default().attachment(attachment))
+                    .await?;
+            }
+            Destination::Message(ctx, trigger) => {
+                trigger
+                    .channel_id
+                    .send_message(ctx, CreateMessage::new().add_file(attachment))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SentMessage<'_> {
+    /// Replaces the content of the message.
+    async fn edit(&mut self, content: String) -> CommandResult {
+        match self {
+            SentMessage::Command(ctx, handle) => {
+                handle
+                    .edit(*ctx, CreateReply::default().content(content))
+                    .await?
+            }
+            SentMessage::Message(ctx, message) => {
+                message
+                    .edit(*ctx, EditMessage::new().content(content))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remembers a message as written by the AI, so replies to it continue the conversation.
+fn remember_ai_message(message: &serenity::Message) {
+    if let Err(e) = database::add_ai_message(message.id, message.channel_id, Utc::now().timestamp())
+    {
+        warn!("Failed to remember AI message {}: {}", message.id, e);
+    }
+}
+
+/// A reply that is sent as soon as the first text arrives and then edited as more text streams in.
+pub struct StreamingReply<'a> {
+    /// Where the messages are sent.
+    destination: Destination<'a>,
+    /// The text of the message currently being streamed into.
+    current: String,
+    /// The message currently being streamed into, once it has been sent.
+    handle: Option<SentMessage<'a>>,
+    /// When the current message was last sent or edited.
+    last_update: Option<Instant>,
+    /// Whether `current` has changed since it was last sent or edited.
+    dirty: bool,
+    /// The whole response so far, including the header.
+    response: String,
+    /// The number of messages that have been completed.
+    messages: usize,
+    /// The most messages the response can take up before the rest is attached as a file.
+    max_messages: Option<usize>,
+    /// Whether the response reached `max_messages` and continues in an attached file.
+    overflowed: bool,
+}
+
+impl<'a> StreamingReply<'a> {
+    /// Creates a reply to a command that starts with `header` (e.g. the user's prompt).
+    /// Nothing is sent until the first call to `push` or `finish`.
+    pub fn new(ctx: Context<'a>, header: impl Into<String>) -> Self {
+        Self::to(Destination::Command(ctx), header.into())
+    }
+
+    /// Creates a reply to a message. Nothing is sent until the first call to `push` or `finish`.
+    pub fn replying_to(ctx: &'a serenity::Context, message: &'a serenity::Message) -> Self {
+        Self::to(Destination::Message(ctx, message), String::new())
+    }
+
+    /// Creates a reply sent to `destination` that starts with `header`.
+    fn to(destination: Destination<'a>, header: String) -> Self {
+        Self {
+            destination,
+            current: header.clone(),
+            handle: None,
+            last_update: None,
+            dirty: true,
+            response: header,
+            messages: 0,
+            max_messages: None,
+            overflowed: false,
+        }
+    }
+
+    /// Attaches the whole response as a Markdown file once it needs more than `max_messages`
+    /// messages, instead of continuing in more messages.
+    pub fn attach_after(mut self, max_messages: usize) -> Self {
+        self.max_messages = Some(max_messages.max(1));
+        self
+    }
+
+    /// Appends streamed text to the reply.
+    ///
+    /// Messages that reach the length limit are completed and the rest of the text moves on to
+    /// a new message, or to the attached file once the response takes up `max_messages`. The
+    /// message being streamed into is edited at most once per `EDIT_INTERVAL`.
+    pub async fn push(&mut self, text: &str) -> CommandResult {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.response.push_str(text);
+        if self.overflowed {
+            return Ok(());
+        }
+        self.current.push_str(text);
+        self.dirty = true;
+
+        // Complete full messages and carry the remainder over to a new one.
+        loop {
+            // The last message allowed needs room for the note pointing to the attachment, and
+            // for the cut-off note in case the stream breaks off after the response overflowed.
+            let last = self
+                .max_messages
+                .is_some_and(|max_messages| self.messages + 1 >= max_messages);
+            let limit = if last {
+                MAX_MESSAGE_LENGTH - ATTACHMENT_NOTE.chars().count() - CUT_OFF_NOTE.chars().count()
+            } else {
+                MAX_MESSAGE_LENGTH
+            };
+            let Some((chunk, rest)) = split_chunk(&self.current, limit) else {
+                break;
+            };
+
+            // Past the last message, the rest of the response goes into the attachment.
+            if last {
+                self.current = chunk + ATTACHMENT_NOTE;
+                self.overflowed = true;
+                return self.flush().await;
+            }
+
+            self.current = chunk;
+            self.flush().await?;
+            self.handle = None;
+            self.messages += 1;
+            self.current = rest;
+            self.dirty = true;
+        }
+
+        // Throttle edits of the message being streamed into.
+        if self
+            .last_update
+            .is_none_or(|last_update| last_update.elapsed() >= EDIT_INTERVAL)
+        {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Streams an AI response into the reply until it is complete, then sends the final state.
+    /// Returns the text of the response, or `None` if it was cut off, in which case the reply
+    /// says so. Pieces from the tool role are shown but left out of the returned text.
+    pub async fn relay(
+        mut self,
+        mut stream: ChatMessageResponseStream,
+    ) -> Result<Option<String>, Error> {
+        let mut response = String::new();
+        while let Some(piece) = stream.next().await {
+            match piece {
+                Ok(piece) => {
+                    self.push(&piece.message.content).await?;
+                    // Notes about the tools the AI used are shown, but aren't part of the response.
+                    if piece.message.role != MessageRole::Tool {
+                        response.push_str(&piece.message.content);
+                    }
+                    if piece.done {
+                        break;
+                    }
+                }
+                Err(()) => {
+                    warn!("AI response stream was cut off");
+                    self.push(CUT_OFF_NOTE).await?;
+                    // Past the overflow only the attachment grows, so warn in the message too.
+                    if self.overflowed {
+                        self.current.push_str(CUT_OFF_NOTE);
+                        self.dirty = true;
+                    }
+                    self.finish().await?;
+                    return Ok(None);
+                }
+            }
+        }
+        self.finish().await?;
+        Ok(Some(response))
+    }
+
+    /// Sends the final state of the reply, and the attachment if the response continues in one.
+    pub async fn finish(mut self) -> CommandResult {
+        self.flush().await?;
+
+        if self.overflowed {
+            let attachment = CreateAttachment::bytes(self.response.into_bytes(), ATTACHMENT_NAME);
+            self.destination.attach(attachment).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends or edits the current message if it changed since the last update.
+    async fn flush(&mut self) -> CommandResult {
+        if !self.dirty || self.current.trim().is_empty() {
+            return Ok(());
+        }
+
+        let content = self.current.clone();
+        match &mut self.handle {
+            Some(handle) => handle.edit(content).await?,
+            None => {
+                let first = self.messages == 0;
+                self.handle = Some(self.destination.send(content, first).await?);
+            }
+        }
+        self.last_update = Some(Instant::now());
+        self.dirty = false;
+
+        Ok(())
+    }
+}
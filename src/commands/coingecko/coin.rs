@@ -40,16 +40,9 @@ async fn price(
     let formatted = to_api_format(&symbol);
     // Construct the API path and query parameters.
     let path = format!("coins/{}", formatted);
-    let query = vec![
-        ("localization", "false"),
-        ("tickers", "false"),
-        ("market_data", "true"),
-        ("community_data", "false"),
-        ("developer_data", "false"),
-    ];
 
     // Send the request to the CoinGecko API.
-    let result = send_request(super::API, &path, &query).await?;
+    let result = send_request(super::API, &path, super::COIN_QUERY).await?;
 
     // Parse the JSON response into the CoinInfo struct.
     let coin_data = CoinInfo::from_json(&result);
@@ -11,6 +11,15 @@ pub(crate) mod coin;
 /// Base URL for the CoinGecko API v3.
 const API: &str = "https://api.coingecko.com/api/v3/";
 
+/// Query parameters for the `/coins/{id}` endpoint, asking for market data only.
+const COIN_QUERY: &[(&str, &str)] = &[
+    ("localization", "false"),
+    ("tickers", "false"),
+    ("market_data", "true"),
+    ("community_data", "false"),
+    ("developer_data", "false"),
+];
+
 /// Trait providing helper methods for parsing `serde_json::Value`.
 trait JsonParse {
     /// Safely parses the value as an f64, returning 0.0 on failure or wrong type.
@@ -190,6 +199,21 @@ async fn send_request(base_url: &str, path: &str, query: &[(&str, &str)]) -> Res
     }
 }
 
+/// Fetches a coin's price and 24h change from the CoinGecko API as a line of text,
+/// e.g. for the AI's `coin_price` tool.
+pub(crate) async fn price_summary(coin: &str) -> Result<String, crate::Error> {
+    let path = format!("coins/{}", to_api_format(coin));
+    let json = send_request(API, &path, COIN_QUERY).await?;
+    let info = CoinInfo::from_json(&json).ok_or(CoingeckoError::Invalid)?;
+    Ok(format!(
+        "{}: ${:.2} USD, {:+.2} USD ({:+.2}%) in the last 24 hours",
+        info.name,
+        info.market_data.price_usd,
+        info.market_data.usd_change_24h,
+        info.market_data.perc_change_24h
+    ))
+}
+
 
 
 /// Tests for the CoinGecko utility functions and API interactions.
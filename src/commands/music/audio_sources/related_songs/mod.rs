@@ -1,6 +1,8 @@
 //! This module defines the trait and implementations for fetching songs related
 //! to a given YouTube video, primarily used for the autoplay feature.
 
+/// Engine that merges and ranks the results of several fetchers for autoplay.
+pub(crate) mod recommendation;
 /// Implementation using the SerpAPI (Google Search Results API).
 pub(crate) mod serp_api;
 /// Implementation using `yt-dlp` to extract related videos.
@@ -16,7 +18,7 @@ pub type RelatedSongsResult = Result<Vec<TrackMetadata>, MusicError>;
 /// Defines the common interface for fetching related songs based on a video ID.
 /// Requires `Send + Sync` for safe use across async tasks.
 #[async_trait]
-pub trait RelatedSongsFetcher {
+pub trait RelatedSongsFetcher: Send + Sync {
     /// Asynchronously fetches a list of related songs (as `TrackMetadata`).
     ///
     /// # Arguments
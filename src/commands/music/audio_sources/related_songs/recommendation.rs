@@ -0,0 +1,637 @@
+//! A recommendation engine for autoplay built on top of the `RelatedSongsFetcher` trait.
+//! Merges the results of several fetchers, scores the candidates according to the guild's
+//! `AutoplayMode`, and filters out duplicates, recently played tracks, and overly long tracks.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::join_all;
+use rand::RngExt;
+use tracing::{debug, info, warn};
+
+use crate::commands::music::audio_sources::AudioSourceResult;
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::commands::music::audio_sources::youtube::YoutubeApi;
+use crate::commands::music::utils::autoplay_manager::AutoplayMode;
+use crate::commands::music::utils::music_manager::MusicError;
+
+use super::RelatedSongsFetcher;
+use super::serp_api::{RealSerpApiSearcher, SerpApiFetcher};
+use super::ytdl::YtDlpFetcher;
+
+/// The longest track autoplay will queue by default.
+pub const DEFAULT_MAX_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// Words that carry no information about a song's style and are ignored when comparing titles.
+const STOP_WORDS: &[&str] = &[
+    "the",
+    "a",
+    "an",
+    "and",
+    "of",
+    "in",
+    "on",
+    "to",
+    "ft",
+    "feat",
+    "official",
+    "video",
+    "audio",
+    "lyrics",
+    "lyric",
+    "music",
+    "hd",
+    "hq",
+    "4k",
+    "mv",
+    "visualizer",
+];
+
+/// A track proposed by one or more fetchers, along with its accumulated score.
+struct Candidate {
+    /// Metadata of the proposed track.
+    metadata: TrackMetadata,
+    /// The accumulated score; higher is better.
+    score: f64,
+    /// The number of batches (fetcher and seed pairs) that proposed this track.
+    sources: usize,
+    /// The order in which the track was first seen, used to break ties.
+    first_seen: usize,
+}
+
+/// Merges and ranks the results of several `RelatedSongsFetcher`s.
+pub struct RecommendationEngine {
+    /// The fetchers to query for related songs.
+    fetchers: Vec<Arc<dyn RelatedSongsFetcher>>,
+    /// The strategy used to score candidates.
+    mode: AutoplayMode,
+    /// Candidates longer than this are discarded.
+    max_duration: Duration,
+}
+
+impl RecommendationEngine {
+    /// Creates an engine without any fetchers.
+    pub fn new(mode: AutoplayMode) -> Self {
+        Self {
+            fetchers: Vec::new(),
+            mode,
+            max_duration: DEFAULT_MAX_DURATION,
+        }
+    }
+
+    /// Creates an engine with the default fetchers for the given mode.
+    ///
+    /// SerpAPI is only queried if the `SERP_API_KEY` environment variable is set;
+    /// the `yt-dlp` fetcher is always available.
+    pub fn for_mode(mode: AutoplayMode) -> Self {
+        let mut engine = Self::new(mode);
+
+        // Add the SerpAPI fetcher if a key is configured.
+        if let Ok(serp_api_key) = std::env::var("SERP_API_KEY") {
+            let searcher = Arc::new(RealSerpApiSearcher::new(serp_api_key));
+            engine = engine.with_fetcher(SerpApiFetcher::new(searcher));
+        }
+
+        // Always fall back on the yt-dlp fetcher.
+        engine.with_fetcher(YtDlpFetcher::with_mode(mode))
+    }
+
+    /// Adds a fetcher to the engine.
+    pub fn with_fetcher(mut self, fetcher: impl RelatedSongsFetcher + 'static) -> Self {
+        self.fetchers.push(Arc::new(fetcher));
+        self
+    }
+
+    /// Recommends tracks to play after the given seed tracks, best first.
+    ///
+    /// Every fetcher is queried for every seed that has a YouTube URL, concurrently.
+    /// Fetcher errors are logged and ignored unless every query fails, in which case
+    /// the first error is returned.
+    pub async fn recommend(
+        &self,
+        seeds: &[TrackMetadata],
+        recent: &[TrackMetadata],
+    ) -> AudioSourceResult<Vec<TrackMetadata>> {
+        // Seeds without a YouTube URL cannot be used to look up related songs.
+        let video_ids: Vec<String> = seeds
+            .iter()
+            .filter_map(|seed| seed.url.as_deref())
+            .filter_map(|url| YoutubeApi::extract_video_id(url).ok())
+            .collect();
+        if video_ids.is_empty() {
+            return Err(MusicError::AudioSourceError(
+                "No seed track has a YouTube URL".to_string(),
+            ));
+        }
+
+        info!(
+            "Fetching recommendations for {} seed(s) using {} fetcher(s) in {:?} mode",
+            video_ids.len(),
+            self.fetchers.len(),
+            self.mode
+        );
+
+        // Query every fetcher for every seed concurrently.
+        let results = join_all(video_ids.iter().flat_map(|video_id| {
+            self.fetchers
+                .iter()
+                .map(move |fetcher| fetcher.fetch_related_songs(video_id))
+        }))
+        .await;
+
+        // Separate successful results from errors.
+        let mut first_error = None;
+        let mut batches = Vec::new();
+        for result in results {
+            match result {
+                Ok(batch) => batches.push(batch),
+                Err(e) => {
+                    warn!("Related songs fetcher failed: {}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // Only fail if no query succeeded.
+        if batches.is_empty()
+            && let Some(e) = first_error
+        {
+            return Err(e);
+        }
+
+        Ok(rank_candidates(
+            self.mode,
+            seeds,
+            recent,
+            batches,
+            self.max_duration,
+        ))
+    }
+}
+
+/// Returns a key identifying a track, preferring the YouTube video ID over the raw URL so that
+/// different URL forms of the same video are treated as duplicates.
+fn track_key(metadata: &TrackMetadata) -> Option<String> {
+    let url = metadata.url.as_deref()?;
+    Some(YoutubeApi::extract_video_id(url).unwrap_or_else(|_| url.to_string()))
+}
+
+/// Normalizes a title for comparison: lowercase, with bracketed suffixes such as
+/// "(Official Video)" removed.
+fn normalize_title(title: &str) -> String {
+    let mut normalized = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for ch in title.chars() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => normalized.extend(ch.to_lowercase()),
+            _ => {}
+        }
+    }
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Extracts the artist from a title of the form 'Artist - Song'.
+fn artist_of(title: &str) -> Option<String> {
+    let normalized = normalize_title(title);
+    let (artist, _) = normalized.split_once(" - ")?;
+    Some(artist.trim().to_string())
+}
+
+/// Extracts the meaningful words of a title, ignoring the artist and stop words.
+fn keywords_of(title: &str) -> HashSet<String> {
+    let normalized = normalize_title(title);
+    // Drop the artist part if present.
+    let song = normalized
+        .split_once(" - ")
+        .map(|(_, song)| song.to_string())
+        .unwrap_or(normalized);
+
+    song.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 1 && !STOP_WORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Merges the batches returned by each fetcher into a single ranked list.
+///
+/// Each candidate earns points for its rank within a batch and for appearing in several
+/// batches, whether they come from different fetchers or different seeds. The mode then
+/// adjusts the score based on the seeds' artists and titles. Candidates without a URL,
+/// non-YouTube candidates, the seeds themselves, recently played tracks, and tracks longer
+/// than `max_duration` are discarded.
+fn rank_candidates(
+    mode: AutoplayMode,
+    seeds: &[TrackMetadata],
+    recent: &[TrackMetadata],
+    batches: Vec<Vec<TrackMetadata>>,
+    max_duration: Duration,
+) -> Vec<TrackMetadata> {
+    // Collect the keys and titles of tracks that should not be recommended again.
+    let excluded_keys: HashSet<String> = recent.iter().chain(seeds).filter_map(track_key).collect();
+    let excluded_titles: HashSet<String> = recent
+        .iter()
+        .chain(seeds)
+        .map(|metadata| normalize_title(&metadata.title))
+        .collect();
+
+    // Gather the artists and keywords of the whole seed set.
+    let seed_artists: HashSet<String> = seeds
+        .iter()
+        .filter_map(|seed| artist_of(&seed.title))
+        .collect();
+    let seed_keywords: HashSet<String> = seeds
+        .iter()
+        .flat_map(|seed| keywords_of(&seed.title))
+        .collect();
+
+    // Merge all batches, accumulating rank scores for duplicates.
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    for batch in batches {
+        for (rank, metadata) in batch.into_iter().enumerate() {
+            // Skip candidates that cannot be played.
+            let Some(url) = metadata.url.as_deref() else {
+                continue;
+            };
+            if !YoutubeApi::is_youtube_url(url) {
+                debug!("Skipping non-YouTube recommendation: {}", url);
+                continue;
+            }
+            let Some(key) = track_key(&metadata) else {
+                continue;
+            };
+
+            // Skip the seeds and recently played tracks, including re-uploads with the same title.
+            if excluded_keys.contains(&key)
+                || excluded_titles.contains(&normalize_title(&metadata.title))
+            {
+                continue;
+            }
+
+            // Skip tracks that are too long.
+            if metadata.duration.is_some_and(|d| d > max_duration) {
+                continue;
+            }
+
+            let rank_score = 1.0 / (rank as f64 + 1.0);
+            let next_index = candidates.len();
+            candidates
+                .entry(key)
+                .and_modify(|candidate| {
+                    candidate.score += rank_score;
+                    candidate.sources += 1;
+                })
+                .or_insert(Candidate {
+                    metadata,
+                    score: rank_score,
+                    sources: 1,
+                    first_seen: next_index,
+                });
+        }
+    }
+
+    let mut rng = rand::rng();
+    let mut ranked: Vec<Candidate> = candidates
+        .into_values()
+        .map(|mut candidate| {
+            // Reward consensus between fetchers and seeds.
+            candidate.score += 0.5 * (candidate.sources as f64 - 1.0);
+
+            let same_artist = artist_of(&candidate.metadata.title)
+                .is_some_and(|artist| seed_artists.contains(&artist));
+            let shared_keywords = keywords_of(&candidate.metadata.title)
+                .intersection(&seed_keywords)
+                .count();
+
+            // Apply the mode-specific adjustments.
+            candidate.score += match mode {
+                AutoplayMode::SimilarArtist => {
+                    if same_artist {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                AutoplayMode::SameGenre => {
+                    let artist_penalty = if same_artist { -0.5 } else { 0.0 };
+                    artist_penalty + (0.2 * shared_keywords as f64).min(1.0)
+                }
+                AutoplayMode::Discovery => {
+                    let artist_penalty = if same_artist { -1.0 } else { 0.0 };
+                    let obscurity_bonus = if candidate.sources == 1 { 0.5 } else { 0.0 };
+                    artist_penalty + obscurity_bonus + rng.random_range(0.0..0.5)
+                }
+            };
+
+            candidate
+        })
+        .collect();
+
+    // Sort best first, breaking ties by the order candidates were first seen.
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.first_seen.cmp(&b.first_seen))
+    });
+
+    ranked
+        .into_iter()
+        .map(|candidate| candidate.metadata)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::music::audio_sources::related_songs::RelatedSongsResult;
+    use crate::commands::music::audio_sources::track_metadata::AUTOPLAY_REQUESTER;
+    use pretty_assertions::assert_eq;
+    use serenity::async_trait;
+
+    /// A fetcher that returns a fixed result.
+    struct MockFetcher {
+        result: Result<Vec<TrackMetadata>, String>,
+    }
+
+    #[async_trait]
+    impl RelatedSongsFetcher for MockFetcher {
+        async fn fetch_related_songs(&self, _video_id: &str) -> RelatedSongsResult {
+            self.result.clone().map_err(MusicError::AudioSourceError)
+        }
+    }
+
+    /// A fetcher that returns a different result for each seed video ID.
+    struct PerSeedMockFetcher {
+        results: HashMap<String, Vec<TrackMetadata>>,
+    }
+
+    #[async_trait]
+    impl RelatedSongsFetcher for PerSeedMockFetcher {
+        async fn fetch_related_songs(&self, video_id: &str) -> RelatedSongsResult {
+            Ok(self.results.get(video_id).cloned().unwrap_or_default())
+        }
+    }
+
+    /// Helper function to create `TrackMetadata` with a YouTube URL for the given video ID.
+    fn track(title: &str, video_id: &str, duration_secs: Option<u64>) -> TrackMetadata {
+        TrackMetadata {
+            title: title.to_string(),
+            url: Some(format!("https://www.youtube.com/watch?v={}", video_id)),
+            duration: duration_secs.map(Duration::from_secs),
+            thumbnail: None,
+            requested_by: Some(AUTOPLAY_REQUESTER.into()),
+            requester_id: None,
+            uploader: None,
+            age_limit: None,
+            autoplay: true,
+        }
+    }
+
+    fn titles(tracks: &[TrackMetadata]) -> Vec<&str> {
+        tracks.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rank_candidates_filters_seed_recent_and_long_tracks() {
+        let seed = track("Artist - Seed", "seed", Some(200));
+        let recent = vec![track("Other - Played", "played", Some(200))];
+        let batch = vec![
+            track("Artist - Seed", "seed", Some(200)),
+            track("Other - Played", "played", Some(200)),
+            track("Other - Played (Official Video)", "reupload", Some(200)),
+            track("Other - Too Long", "long", Some(3600)),
+            track("Other - Fresh", "fresh", Some(200)),
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::SimilarArtist,
+            std::slice::from_ref(&seed),
+            &recent,
+            vec![batch],
+            DEFAULT_MAX_DURATION,
+        );
+
+        assert_eq!(titles(&ranked), vec!["Other - Fresh"]);
+    }
+
+    #[test]
+    fn test_rank_candidates_merges_duplicates_and_rewards_consensus() {
+        let seed = track("Seed Song", "seed", None);
+        let first = vec![track("A", "a", None), track("B", "b", None)];
+        let second = vec![
+            track("C", "c", None),
+            // Same video as "B", under a different URL form.
+            TrackMetadata {
+                url: Some("https://youtu.be/b".to_string()),
+                ..track("B", "b", None)
+            },
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::SimilarArtist,
+            std::slice::from_ref(&seed),
+            &[],
+            vec![first, second],
+            DEFAULT_MAX_DURATION,
+        );
+
+        // B: 0.5 + 0.5 + 0.5 consensus; A: 1.0; C: 1.0 (seen after A).
+        assert_eq!(titles(&ranked), vec!["B", "A", "C"]);
+    }
+
+    #[test]
+    fn test_rank_candidates_similar_artist_prefers_same_artist() {
+        let seed = track("Artist - Seed", "seed", None);
+        let batch = vec![
+            track("Someone Else - Song", "x", None),
+            track("Artist - Another Song", "y", None),
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::SimilarArtist,
+            std::slice::from_ref(&seed),
+            &[],
+            vec![batch],
+            DEFAULT_MAX_DURATION,
+        );
+
+        assert_eq!(titles(&ranked)[0], "Artist - Another Song");
+    }
+
+    #[test]
+    fn test_rank_candidates_same_genre_prefers_other_artists_with_shared_keywords() {
+        let seed = track("Artist - Chill Lofi Beats", "seed", None);
+        let batch = vec![
+            track("Artist - Another Song", "x", None),
+            track("Someone - Lofi Beats To Study", "z", None),
+            track("Other - Unrelated", "y", None),
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::SameGenre,
+            std::slice::from_ref(&seed),
+            &[],
+            vec![batch],
+            DEFAULT_MAX_DURATION,
+        );
+
+        assert_eq!(
+            titles(&ranked),
+            vec![
+                "Someone - Lofi Beats To Study",
+                "Artist - Another Song",
+                "Other - Unrelated"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_candidates_discovery_demotes_seed_artist() {
+        let seed = track("Artist - Seed", "seed", None);
+        let batch = vec![
+            track("Artist - Same Artist", "x", None),
+            track("Other - New Artist", "y", None),
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::Discovery,
+            std::slice::from_ref(&seed),
+            &[],
+            vec![batch],
+            DEFAULT_MAX_DURATION,
+        );
+
+        // The random jitter is smaller than the artist penalty, so the order is stable.
+        assert_eq!(
+            titles(&ranked),
+            vec!["Other - New Artist", "Artist - Same Artist"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recommend_ignores_failing_fetchers() {
+        let engine = RecommendationEngine::new(AutoplayMode::SimilarArtist)
+            .with_fetcher(MockFetcher {
+                result: Err("Simulated failure".to_string()),
+            })
+            .with_fetcher(MockFetcher {
+                result: Ok(vec![track("A", "a", None)]),
+            });
+
+        let seed = track("Seed", "seed", None);
+        let ranked = engine
+            .recommend(std::slice::from_ref(&seed), &[])
+            .await
+            .unwrap();
+
+        assert_eq!(titles(&ranked), vec!["A"]);
+    }
+
+    #[tokio::test]
+    async fn test_recommend_fails_when_all_fetchers_fail() {
+        let engine =
+            RecommendationEngine::new(AutoplayMode::SimilarArtist).with_fetcher(MockFetcher {
+                result: Err("Simulated failure".to_string()),
+            });
+
+        let seed = track("Seed", "seed", None);
+        let result = engine.recommend(std::slice::from_ref(&seed), &[]).await;
+
+        match result {
+            Err(MusicError::AudioSourceError(msg)) => assert_eq!(msg, "Simulated failure"),
+            other => panic!("Expected AudioSourceError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recommend_respects_max_duration() {
+        let mut engine = RecommendationEngine::new(AutoplayMode::SimilarArtist);
+        engine.max_duration = Duration::from_secs(60);
+        let engine = engine.with_fetcher(MockFetcher {
+            result: Ok(vec![
+                track("Long", "long", Some(61)),
+                track("Short", "short", Some(60)),
+            ]),
+        });
+
+        let seed = track("Seed", "seed", None);
+        let ranked = engine
+            .recommend(std::slice::from_ref(&seed), &[])
+            .await
+            .unwrap();
+
+        assert_eq!(titles(&ranked), vec!["Short"]);
+    }
+
+    #[tokio::test]
+    async fn test_recommend_combines_multiple_seeds() {
+        let engine = RecommendationEngine::new(AutoplayMode::SimilarArtist).with_fetcher(
+            PerSeedMockFetcher {
+                results: HashMap::from([
+                    (
+                        "first".to_string(),
+                        vec![track("Only First", "x", None), track("Shared", "s", None)],
+                    ),
+                    (
+                        "second".to_string(),
+                        vec![
+                            track("Only Second", "y", None),
+                            track("Shared", "s", None),
+                            // The other seed is never recommended.
+                            track("First Seed", "first", None),
+                        ],
+                    ),
+                ]),
+            },
+        );
+
+        let seeds = vec![
+            track("First Seed", "first", None),
+            track("Second Seed", "second", None),
+        ];
+        let ranked = engine.recommend(&seeds, &[]).await.unwrap();
+
+        // "Shared" is proposed for both seeds and wins through consensus.
+        assert_eq!(titles(&ranked), vec!["Shared", "Only First", "Only Second"]);
+    }
+
+    #[test]
+    fn test_rank_candidates_similar_artist_considers_every_seed() {
+        let seeds = vec![
+            track("First Artist - Seed", "first", None),
+            track("Second Artist - Seed", "second", None),
+        ];
+        let batch = vec![
+            track("Someone Else - Song", "x", None),
+            track("Second Artist - Another Song", "y", None),
+        ];
+
+        let ranked = rank_candidates(
+            AutoplayMode::SimilarArtist,
+            &seeds,
+            &[],
+            vec![batch],
+            DEFAULT_MAX_DURATION,
+        );
+
+        assert_eq!(titles(&ranked)[0], "Second Artist - Another Song");
+    }
+
+    #[tokio::test]
+    async fn test_recommend_fails_without_youtube_seed() {
+        let engine =
+            RecommendationEngine::new(AutoplayMode::SimilarArtist).with_fetcher(MockFetcher {
+                result: Ok(vec![track("A", "a", None)]),
+            });
+
+        let seed = TrackMetadata {
+            url: None,
+            ..track("Seed", "seed", None)
+        };
+        let result = engine.recommend(std::slice::from_ref(&seed), &[]).await;
+
+        assert!(matches!(result, Err(MusicError::AudioSourceError(_))));
+    }
+}
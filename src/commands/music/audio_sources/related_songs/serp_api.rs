@@ -1,7 +1,7 @@
 //! Implements the `RelatedSongsFetcher` trait using the SerpAPI (Google Search Results API)
 //! to find videos related to a given YouTube video.
 
-use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::commands::music::audio_sources::track_metadata::{AUTOPLAY_REQUESTER, TrackMetadata};
 use crate::commands::music::utils::music_manager::MusicError;
 use serenity::async_trait;
 use serpapi_search_rust::serp_api_search::SerpApiSearch;
@@ -142,7 +142,11 @@ impl<S: SerpApiSearcher + Send + Sync> RelatedSongsFetcher for SerpApiFetcher<S>
                         url: Some(link.to_string()),
                         duration,
                         thumbnail,
-                        requested_by: Some("Autoplay".into()),
+                        requested_by: Some(AUTOPLAY_REQUESTER.into()),
+                        requester_id: None,
+                        uploader: None,
+                        age_limit: None,
+                        autoplay: true,
                     });
 
                     // Stop after finding 5 related videos.
@@ -192,7 +196,11 @@ mod tests {
             url: url.map(String::from),
             duration: duration_secs.map(Duration::from_secs),
             thumbnail: thumbnail.map(String::from),
-            requested_by: Some("Autoplay".into()),
+            requested_by: Some(AUTOPLAY_REQUESTER.into()),
+            requester_id: None,
+            uploader: None,
+            age_limit: None,
+            autoplay: true,
         }
     }
 
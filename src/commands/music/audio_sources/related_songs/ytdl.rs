@@ -1,8 +1,9 @@
 //! Implements the `RelatedSongsFetcher` trait using `yt-dlp` command-line tool.
 //! This serves as a fallback if other methods (like SerpAPI) are unavailable or fail.
 
-use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::commands::music::audio_sources::track_metadata::{AUTOPLAY_REQUESTER, TrackMetadata};
 use crate::commands::music::audio_sources::youtube::YoutubeApi;
+use crate::commands::music::utils::autoplay_manager::AutoplayMode;
 use crate::commands::music::utils::music_manager::MusicError;
 use serenity::async_trait;
 use std::process::Command;
@@ -12,12 +13,47 @@ use super::{RelatedSongsFetcher, RelatedSongsResult};
 
 /// Implements `RelatedSongsFetcher` by using `yt-dlp`'s search functionality.
 /// It derives a search term from the original video's title.
-pub struct YtDlpFetcher;
+pub struct YtDlpFetcher {
+    /// The autoplay mode used to shape the search term.
+    mode: AutoplayMode,
+}
 
 impl YtDlpFetcher {
-    /// Creates a new `YtDlpFetcher`.
-    pub fn new() -> Self {
-        Self
+    /// Creates a new `YtDlpFetcher` whose search term is derived according to `mode`.
+    pub fn with_mode(mode: AutoplayMode) -> Self {
+        Self { mode }
+    }
+
+    /// Derives a YouTube search term from a video title.
+    ///
+    /// Titles of the form 'Artist - Song' are split so that the artist can be searched for
+    /// directly. Otherwise the first two words of the title stand in for the artist.
+    fn search_term(title: &str, mode: AutoplayMode) -> String {
+        // Attempt to find the artist part of the title.
+        let artist = if title.contains(" - ") {
+            // If title seems like 'Artist - Song', use the 'Artist' part.
+            title.split(" - ").next().map(str::trim).map(str::to_string)
+        } else {
+            // Otherwise, use the first few words, if there are enough of them.
+            let words: Vec<&str> = title.split_whitespace().collect();
+            (words.len() > 2).then(|| words[0..2].join(" "))
+        };
+
+        match (mode, artist) {
+            // Search for more songs by the same artist.
+            (AutoplayMode::SimilarArtist, Some(artist)) if title.contains(" - ") => {
+                artist + " music"
+            }
+            (AutoplayMode::SimilarArtist, Some(artist)) => artist,
+            // Search for songs that sound like this one.
+            (AutoplayMode::SameGenre, _) if !title.is_empty() => {
+                format!("songs like {}", title)
+            }
+            // Search for artists adjacent to this one.
+            (AutoplayMode::Discovery, Some(artist)) => format!("artists like {}", artist),
+            // Fall back to a generic search.
+            _ => "music".to_string(),
+        }
     }
 }
 
@@ -57,18 +93,7 @@ impl RelatedSongsFetcher for YtDlpFetcher {
         let title = metadata_json["title"].as_str().unwrap_or("").to_string();
 
         // Attempt to create a reasonable search term from the title.
-        let search_term = if title.contains(" - ") {
-            // If title seems like 'Artist - Song', search for 'Artist music'.
-            title.split(" - ").next().unwrap_or(&title).to_string() + " music"
-        } else {
-            // Otherwise, use the first few words or just 'music'.
-            let words: Vec<&str> = title.split_whitespace().collect();
-            if words.len() > 2 {
-                words[0..2].join(" ")
-            } else {
-                "music".to_string()
-            }
-        };
+        let search_term = Self::search_term(&title, self.mode);
 
         // Perform the YouTube search using yt-dlp.
         let search_output = Command::new("yt-dlp")
@@ -98,7 +123,7 @@ impl RelatedSongsFetcher for YtDlpFetcher {
                 // Extract the URL from the search result.
                 let video_url = video_json["webpage_url"].as_str().map(|s| s.to_string());
 
-                    // Check if the URL matches the original video or isn't a valid YouTube URL.
+                // Check if the URL matches the original video or isn't a valid YouTube URL.
                 if let Some(ref video_url) = video_url {
                     // Skip original video or non-video URLs (like channels)
                     if video_url == &orig_url || !YoutubeApi::is_youtube_url(video_url) {
@@ -116,13 +141,22 @@ impl RelatedSongsFetcher for YtDlpFetcher {
 
                 let thumbnail = video_json["thumbnail"].as_str().map(|s| s.to_string());
 
+                let uploader = video_json["uploader"]
+                    .as_str()
+                    .or_else(|| video_json["channel"].as_str())
+                    .map(|s| s.to_string());
+
                 // Create TrackMetadata and add to the list.
                 related_songs.push(TrackMetadata {
                     title,
                     url: video_url,
                     duration,
                     thumbnail,
-                    requested_by: Some("Autoplay".into()),
+                    requested_by: Some(AUTOPLAY_REQUESTER.into()),
+                    requester_id: None,
+                    uploader,
+                    age_limit: None,
+                    autoplay: true,
                 });
 
                 // Stop after collecting 5 related songs.
@@ -135,3 +169,48 @@ impl RelatedSongsFetcher for YtDlpFetcher {
         Ok(related_songs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_term_similar_artist() {
+        assert_eq!(
+            YtDlpFetcher::search_term("Daft Punk - One More Time", AutoplayMode::SimilarArtist),
+            "Daft Punk music"
+        );
+        assert_eq!(
+            YtDlpFetcher::search_term("Daft Punk One More Time", AutoplayMode::SimilarArtist),
+            "Daft Punk"
+        );
+        assert_eq!(
+            YtDlpFetcher::search_term("Intro", AutoplayMode::SimilarArtist),
+            "music"
+        );
+    }
+
+    #[test]
+    fn test_search_term_same_genre() {
+        assert_eq!(
+            YtDlpFetcher::search_term("Daft Punk - One More Time", AutoplayMode::SameGenre),
+            "songs like Daft Punk - One More Time"
+        );
+        assert_eq!(
+            YtDlpFetcher::search_term("", AutoplayMode::SameGenre),
+            "music"
+        );
+    }
+
+    #[test]
+    fn test_search_term_discovery() {
+        assert_eq!(
+            YtDlpFetcher::search_term("Daft Punk - One More Time", AutoplayMode::Discovery),
+            "artists like Daft Punk"
+        );
+        assert_eq!(
+            YtDlpFetcher::search_term("Intro", AutoplayMode::Discovery),
+            "music"
+        );
+    }
+}
@@ -125,10 +125,10 @@ impl SpotifyApi {
         let mut token_lock = SPOTIFY_TOKEN.lock().await;
 
         // If a valid token exists in the cache, clone and return it.
-        if let Some(token) = &*token_lock {
-            if !token.is_expired() {
-                return Ok(token.access_token.clone());
-            }
+        if let Some(token) = &*token_lock
+            && !token.is_expired()
+        {
+            return Ok(token.access_token.clone());
         }
 
         // No valid token in cache, need to request a new one.
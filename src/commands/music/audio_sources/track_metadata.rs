@@ -6,6 +6,7 @@ use crate::commands::music::audio_sources::youtube::YoutubeApi;
 use crate::commands::music::utils::music_manager::MusicError;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use serenity::model::id::UserId;
 use std::process::Output;
 use std::sync::{Arc, LazyLock};
 use std::time::Duration;
@@ -19,6 +20,9 @@ use super::spotify::SpotifyTrack;
 pub static AUDIO_CACHE: LazyLock<Arc<DashMap<Url, TrackMetadata>>> =
     LazyLock::new(|| Arc::new(DashMap::new()));
 
+/// The requester name given to tracks queued by autoplay.
+pub const AUTOPLAY_REQUESTER: &str = "Autoplay";
+
 /// Unified representation of metadata for a playable track.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TrackMetadata {
@@ -33,6 +37,18 @@ pub struct TrackMetadata {
     pub thumbnail: Option<String>,
     /// The name of the user who requested the track.
     pub requested_by: Option<String>,
+    /// The ID of the user who requested the track, used to enforce per-user queue limits.
+    #[serde(default)]
+    pub requester_id: Option<UserId>,
+    /// The name of the channel that uploaded the track, if known.
+    #[serde(default)]
+    pub uploader: Option<String>,
+    /// The minimum viewer age reported by the source (18 for age-restricted tracks), if known.
+    #[serde(default)]
+    pub age_limit: Option<u32>,
+    /// Whether the track was queued by autoplay rather than by a user.
+    #[serde(default)]
+    pub autoplay: bool,
 }
 
 impl Default for TrackMetadata {
@@ -43,6 +59,10 @@ impl Default for TrackMetadata {
             duration: None,
             thumbnail: None,
             requested_by: None,
+            requester_id: None,
+            uploader: None,
+            age_limit: None,
+            autoplay: false,
         }
     }
 }
@@ -66,6 +86,11 @@ impl TrackMetadata {
         Ok(metadata)
     }
 
+    /// Returns whether the track was queued by autoplay rather than by a user.
+    pub fn is_autoplay(&self) -> bool {
+        self.autoplay
+    }
+
     /// Helper method to set miscellaneous data, currently just the requestor's name.
     fn misc_data(&mut self, requested_by: String) {
         self.requested_by = Some(requested_by);
@@ -85,6 +110,13 @@ impl TryFrom<Output> for TrackMetadata {
                 MusicError::AudioSourceError(format!("Failed to parse video metadata: {}", e))
             })?;
 
+        Ok(Self::from(&metadata_json))
+    }
+}
+
+/// Converts a single `yt-dlp` JSON object into `TrackMetadata`, caching it by URL.
+impl From<&serde_json::Value> for TrackMetadata {
+    fn from(metadata_json: &serde_json::Value) -> Self {
         // Extract fields, providing defaults if missing.
         let title = metadata_json["title"]
             .as_str()
@@ -99,6 +131,15 @@ impl TryFrom<Output> for TrackMetadata {
 
         let url_str = metadata_json["webpage_url"].as_str().map(|s| s.to_string());
 
+        let uploader = metadata_json["uploader"]
+            .as_str()
+            .or_else(|| metadata_json["channel"].as_str())
+            .map(|s| s.to_string());
+
+        let age_limit = metadata_json["age_limit"]
+            .as_u64()
+            .and_then(|age| u32::try_from(age).ok());
+
         // Create metadata with extracted information
         let metadata = TrackMetadata {
             title,
@@ -106,6 +147,10 @@ impl TryFrom<Output> for TrackMetadata {
             duration,
             thumbnail,
             requested_by: None,
+            requester_id: None,
+            uploader,
+            age_limit,
+            autoplay: false,
         };
 
         // If a valid URL was extracted, attempt to cache the metadata.
@@ -117,7 +162,7 @@ impl TryFrom<Output> for TrackMetadata {
             }
         }
 
-        Ok(metadata)
+        metadata
     }
 }
 
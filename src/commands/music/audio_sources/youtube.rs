@@ -1,16 +1,8 @@
 //! Implements the `AudioApi` trait for fetching metadata from YouTube.
 //! Uses `yt-dlp` command-line tool for extracting information.
-//! Also includes logic for finding related songs.
+//! Related songs are handled by the `related_songs` recommendation engine.
 
-use super::related_songs::serp_api::{RealSerpApiSearcher, SerpApiFetcher};
-use std::sync::Arc;
-
-use crate::commands::music::{
-    audio_sources::related_songs::{
-        RelatedSongsFetcher, ytdl::YtDlpFetcher,
-    },
-    utils::music_manager::MusicError,
-};
+use crate::commands::music::utils::music_manager::MusicError;
 use regex::Regex;
 use serenity::async_trait;
 use tracing::info;
@@ -119,8 +111,35 @@ impl YoutubeApi {
         TrackMetadata::try_from(metadata_output)
     }
 
+    /// Fetches metadata for the first `count` YouTube search results for a given search term.
+    /// Uses `yt-dlp` with the `ytsearchN:` prefix, which prints one JSON object per result.
+    pub fn search(search_term: &str, count: usize) -> Result<Vec<TrackMetadata>, MusicError> {
+        info!("Searching YouTube for {} results: {}", count, search_term);
+        let search_param = format!("ytsearch{}:{}", count, search_term);
+
+        // Execute yt-dlp to get metadata for each search result.
+        let output = Command::new("yt-dlp")
+            .args([
+                "-j",            // Output as JSON
+                "--no-playlist", // Don't process playlists
+                "--",
+                &search_param,
+            ])
+            .output()
+            .map_err(|e| {
+                MusicError::AudioSourceError(format!("Failed to search YouTube: {}", e))
+            })?;
+
+        // Convert each line of output to TrackMetadata, skipping anything unparseable.
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .map(|json| TrackMetadata::from(&json))
+            .collect())
+    }
+
     /// Extracts the video ID from various YouTube URL formats using regex.
-    fn extract_video_id(url: &str) -> AudioSourceResult<String> {
+    pub(crate) fn extract_video_id(url: &str) -> AudioSourceResult<String> {
         // Try to match the URL against the regex.
         if let Some(captures) = YOUTUBE_REGEX.captures(url) {
             // Extract the 5th capture group (the video ID).
@@ -139,37 +158,6 @@ impl YoutubeApi {
             ))
         }
     }
-
-    /// Fetches a list of related songs for a given YouTube video URL.
-    ///
-    /// Tries to use the SerpAPI first if the `SERP_API_KEY` environment variable is set.
-    /// Falls back to using `yt-dlp` if SerpAPI is unavailable or returns no results.
-    pub async fn get_related_songs(url: &str) -> AudioSourceResult<Vec<TrackMetadata>> {
-        info!("Fetching related songs for URL: {}", url);
-
-        // Extract the video ID required by the fetchers.
-        let video_id = Self::extract_video_id(url)?;
-
-        // Check if SerpAPI key is available.
-        if let Ok(serp_api_key) = std::env::var("SERP_API_KEY") {
-            // Create the SerpAPI fetcher.
-            let real_searcher = Arc::new(RealSerpApiSearcher::new(serp_api_key));
-          
-            // Specify the generic type for SerpApiFetcher
-            let serp_fetcher: SerpApiFetcher<RealSerpApiSearcher> = SerpApiFetcher::new(real_searcher);
-            // Attempt to fetch related songs via SerpAPI.
-            let related_songs = serp_fetcher.fetch_related_songs(&video_id).await?;
-
-            // If SerpAPI returned results, use them.
-            if !related_songs.is_empty() {
-                return Ok(related_songs);
-            }
-        }
-
-        // If SerpAPI failed or is unavailable, use the yt-dlp fetcher.
-        let ytdlp_fetcher = YtDlpFetcher::new();
-        ytdlp_fetcher.fetch_related_songs(&video_id).await
-    }
 }
 
 #[cfg(test)]
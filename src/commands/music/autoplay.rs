@@ -1,39 +1,85 @@
 //! Defines the `/autoplay` command for managing the music autoplay feature.
 
 use super::*;
+use crate::commands::music::audio_sources::AUDIO_APIS;
 use crate::commands::music::utils::{
-    autoplay_manager::{is_autoplay_enabled, set_autoplay},
+    autoplay_manager::{
+        AutoplayMode, get_autoplay_mode, get_autoplay_seed_playlist, is_autoplay_enabled,
+        set_autoplay, set_autoplay_mode, set_autoplay_seed_playlist,
+    },
     embedded_messages,
     music_manager::MusicError,
 };
 
 /// Enables, disables, or toggles the music autoplay feature for the guild.
 ///
-/// When autoplay is enabled, the bot will automatically queue related songs
-/// when the current queue becomes empty. If the `enabled` argument is omitted,
-/// the command toggles the current autoplay state.
+/// When autoplay is enabled, the bot keeps a few related songs queued ahead, seeded
+/// from the last tracks of the session. If the `enabled` argument is omitted,
+/// the command toggles the current autoplay state, unless only other settings are given,
+/// in which case autoplay stays enabled.
+///
+/// The `mode` argument selects how related songs are picked: similar artist,
+/// same genre, or discovery of new artists. The `seed_playlist` argument adds tracks from
+/// a fixed playlist to the seed, and `clear_seed_playlist` removes it.
 #[poise::command(slash_command, category = "Music")]
 pub async fn autoplay(
     ctx: Context<'_>,
     #[description = "Enable or disable autoplay"] enabled: Option<bool>,
+    #[description = "How related songs are picked"] mode: Option<AutoplayMode>,
+    #[description = "Playlist URL used to steer recommendations"] seed_playlist: Option<String>,
+    #[description = "Stop using the seed playlist"] clear_seed_playlist: Option<bool>,
 ) -> CommandResult {
     // Ensure the command is used within a guild.
     let guild_id = ctx.guild_id().ok_or_else(|| {
         Box::new(MusicError::NotInGuild) as Box<dyn std::error::Error + Send + Sync>
     })?;
 
-    // Determine the desired state: use provided argument or toggle current state.
+    // Reject seed playlists that no audio source can resolve.
+    if let Some(url) = &seed_playlist
+        && !AUDIO_APIS.iter().any(|api| api.is_valid_url(url))
+    {
+        ctx.send(embedded_messages::generic_error(&format!(
+            "Unsupported seed playlist URL: {}",
+            url
+        )))
+        .await?;
+        return Ok(());
+    }
+
+    // Determine the desired state: use provided argument, keep it enabled when only
+    // changing other settings, or toggle current state.
+    let changes_settings =
+        mode.is_some() || seed_playlist.is_some() || clear_seed_playlist.is_some();
     let new_state = match enabled {
         Some(state) => state,
+        None if changes_settings => true,
         None => !is_autoplay_enabled(guild_id).await,
     };
 
     // Update the autoplay state using the autoplay manager.
     set_autoplay(guild_id, new_state).await;
 
+    // Update the autoplay mode if one was provided.
+    if let Some(mode) = mode {
+        set_autoplay_mode(guild_id, mode).await;
+    }
+    let current_mode = get_autoplay_mode(guild_id).await;
+
+    // Update or clear the seed playlist if requested.
+    if clear_seed_playlist == Some(true) {
+        set_autoplay_seed_playlist(guild_id, None).await;
+    } else if seed_playlist.is_some() {
+        set_autoplay_seed_playlist(guild_id, seed_playlist).await;
+    }
+    let current_seed_playlist = get_autoplay_seed_playlist(guild_id).await;
+
     // Send an embed confirming the new autoplay status.
-    ctx.send(embedded_messages::autoplay_status(new_state))
-        .await?;
+    ctx.send(embedded_messages::autoplay_status(
+        new_state,
+        current_mode,
+        current_seed_playlist.as_deref(),
+    ))
+    .await?;
 
     Ok(())
 }
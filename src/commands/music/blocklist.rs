@@ -0,0 +1,129 @@
+//! Defines the `/music blocklist` command group for managing the guild's music content filter.
+
+use super::*;
+use crate::commands::music::utils::{
+    content_filter::{
+        FilterKind, FilterList, FilterRule, add_filter_rule, get_content_filter,
+        remove_filter_rule, set_block_explicit,
+    },
+    embedded_messages,
+    music_manager::MusicError,
+};
+
+/// Commands for managing which tracks can be played in this server.
+///
+/// Requires the Manage Server permission, which is checked by the parent `/music` command.
+#[poise::command(
+    slash_command,
+    subcommands("add", "remove", "list", "explicit"),
+    category = "Music"
+)]
+pub async fn blocklist(_: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Adds an entry to the blocklist, or to the allowlist.
+///
+/// Title patterns are case-insensitive regular expressions. Once the allowlist has entries,
+/// only tracks matching one of them can be played.
+#[poise::command(slash_command)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "What to match"] kind: FilterKind,
+    #[description = "Title pattern, uploader name, video ID, or domain"] pattern: String,
+    #[description = "Add to the blocklist (default) or the allowlist"] list: Option<FilterList>,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let list = list.unwrap_or_default();
+
+    // Validate the entry and store it.
+    let reply = match FilterRule::new(list, kind, &pattern) {
+        Ok(rule) => match add_filter_rule(guild_id, &rule).await {
+            Ok(true) => embedded_messages::generic_success(
+                "Music",
+                &format!("🚫 Added {} `{}` to the {}", kind, rule.pattern, list),
+            ),
+            Ok(false) => embedded_messages::generic_error(&format!(
+                "{} `{}` is already on the {}",
+                kind, rule.pattern, list
+            )),
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        },
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes an entry from the blocklist, or from the allowlist.
+#[poise::command(slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "What the entry matches"] kind: FilterKind,
+    #[description = "The entry's pattern"] pattern: String,
+    #[description = "Remove from the blocklist (default) or the allowlist"] list: Option<
+        FilterList,
+    >,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let list = list.unwrap_or_default();
+
+    // Normalize the pattern the same way it was stored, then remove it.
+    let reply = match FilterRule::new(list, kind, &pattern) {
+        Ok(rule) => match remove_filter_rule(guild_id, &rule).await {
+            Ok(true) => embedded_messages::generic_success(
+                "Music",
+                &format!("✅ Removed {} `{}` from the {}", kind, rule.pattern, list),
+            ),
+            Ok(false) => embedded_messages::generic_error(&format!(
+                "{} `{}` is not on the {}",
+                kind, rule.pattern, list
+            )),
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        },
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Lists the blocklist and allowlist entries.
+#[poise::command(slash_command)]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let filter = get_content_filter(guild_id).await;
+    ctx.send(embedded_messages::content_filter_status(&filter))
+        .await?;
+
+    Ok(())
+}
+
+/// Blocks or allows age-restricted tracks.
+#[poise::command(slash_command)]
+async fn explicit(
+    ctx: Context<'_>,
+    #[description = "Block age-restricted tracks"] blocked: bool,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    set_block_explicit(guild_id, blocked).await;
+
+    let description = if blocked {
+        "🔞 Age-restricted tracks are now blocked"
+    } else {
+        "🔞 Age-restricted tracks are now allowed"
+    };
+    ctx.send(embedded_messages::generic_success("Music", description))
+        .await?;
+
+    Ok(())
+}
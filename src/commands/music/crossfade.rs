@@ -0,0 +1,41 @@
+//! Defines the `/crossfade` command for configuring transitions between tracks.
+
+use std::time::Duration;
+
+use super::*;
+use crate::commands::music::utils::{
+    crossfade::{MAX_CROSSFADE_SECS, set_crossfade},
+    embedded_messages,
+    music_manager::MusicError,
+};
+
+/// Sets how long consecutive tracks fade into each other, in seconds.
+///
+/// The outgoing track fades out while the next track in the queue fades in.
+/// A value of 0 disables crossfading; the next track is still pre-buffered for a
+/// gapless transition. Applies to tracks queued after the change.
+#[poise::command(slash_command, category = "Music")]
+pub async fn crossfade(
+    ctx: Context<'_>,
+    #[description = "Crossfade duration in seconds (0 to disable)"]
+    #[min = 0]
+    #[max = 12]
+    seconds: u64,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or_else(|| {
+        Box::new(MusicError::NotInGuild) as Box<dyn std::error::Error + Send + Sync>
+    })?;
+
+    // Clamp to the supported range, in case the command was invoked without Discord's validation.
+    let seconds = seconds.min(MAX_CROSSFADE_SECS);
+
+    // Store the new crossfade duration.
+    set_crossfade(guild_id, Duration::from_secs(seconds)).await;
+
+    // Send an embed confirming the new setting.
+    ctx.send(embedded_messages::crossfade_status(seconds))
+        .await?;
+
+    Ok(())
+}
@@ -3,10 +3,40 @@
 
 /// Submodule defining the `/autoplay` command.
 pub(crate) mod autoplay;
+/// Submodule defining the `/music blocklist` command group.
+pub(crate) mod blocklist;
+/// Submodule defining the `/crossfade` command.
+pub(crate) mod crossfade;
+/// Submodule defining the `/move_here` command.
+pub(crate) mod move_here;
+/// Submodule defining the `/nowplaying` command.
+pub(crate) mod nowplaying;
+/// Submodule defining the `/pause` command.
+pub(crate) mod pause;
 /// Submodule defining the `/play` command.
 pub(crate) mod play;
+/// Submodule defining the `/player` command.
+pub(crate) mod player;
+/// Submodule defining the `/queue` command group for exporting and importing the queue.
+pub(crate) mod queue;
 /// Submodule defining the `/remove` command.
 pub(crate) mod remove;
+/// Submodule defining the `/repeat` command.
+pub(crate) mod repeat;
+/// Submodule defining the `/resume` command.
+pub(crate) mod resume;
+/// Submodule defining the `/resume_session` command.
+pub(crate) mod resume_session;
+/// Submodule defining the `/music` command group for guild music settings.
+pub(crate) mod settings;
+/// Submodule defining the `/shuffle` command.
+pub(crate) mod shuffle;
+/// Submodule defining the `/skip` command.
+pub(crate) mod skip;
+/// Submodule defining the `/stop` command.
+pub(crate) mod stop;
+/// Submodule defining the `/toggle_queue` command.
+pub(crate) mod toggle_queue;
 
 /// Submodule containing logic for different audio sources (YouTube, Spotify, etc.).
 pub(crate) mod audio_sources;
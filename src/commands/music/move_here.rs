@@ -0,0 +1,52 @@
+//! Defines the `/move_here` command for bringing the bot to the caller's voice channel.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages,
+    music_manager::{MusicError, MusicManager},
+    player_controls,
+};
+
+/// Moves the bot to your voice channel, keeping the queue and current track.
+///
+/// The bot won't leave listeners behind: the move only succeeds if nobody else is listening in
+/// its current channel, unless you have the Move Members permission.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn move_here(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Members who can move others may take the bot away from its current listeners.
+    let force = can_move_members(ctx).await;
+
+    let reply =
+        match MusicManager::move_to_user(ctx.serenity_context(), guild_id, ctx.author().id, force)
+            .await
+        {
+            Ok(channel_id) => {
+                // Refresh the player so it reflects the new channel.
+                player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+                embedded_messages::generic_success(
+                    "Music",
+                    &format!("🎵 Moved to <#{}>", channel_id),
+                )
+            }
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Checks whether the command author has the Move Members permission in the guild.
+async fn can_move_members(ctx: Context<'_>) -> bool {
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    ctx.guild()
+        .is_some_and(|guild| guild.member_permissions(&member).move_members())
+}
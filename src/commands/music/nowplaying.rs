@@ -0,0 +1,28 @@
+//! Defines the `/nowplaying` command for showing the current track.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Shows the track that is currently playing, with its progress and requester.
+///
+/// Useful when the player message has scrolled out of view.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn nowplaying(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Look up the current track and its position.
+    let reply = match player_controls::now_playing(ctx.serenity_context(), guild_id).await {
+        Ok((metadata, position)) => embedded_messages::now_playing(&metadata, position),
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Defines the `/pause` command for pausing music playback.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Pauses the track that is currently playing.
+///
+/// Equivalent to pressing the play/pause button on the player while music is playing.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn pause(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the change using the same logic as the player buttons.
+    let reply =
+        match player_controls::pause(ctx.serenity_context(), guild_id, ctx.author().id).await {
+            Ok(()) => embedded_messages::generic_success("Music", "⏸️ Paused playback"),
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    // Keep the player message in sync with the change.
+    player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
@@ -36,13 +36,10 @@ pub async fn play(
     )
     .await
     {
-        Ok((metadata, number_of_tracks)) => {
+        Ok(outcome) => {
             // On success, send a confirmation message (e.g., 'Added X tracks to queue').
-            ctx.send(MusicManager::play_success_response(
-                metadata,
-                number_of_tracks,
-            ))
-            .await?;
+            ctx.send(MusicManager::play_success_response(outcome))
+                .await?;
         }
         Err(e) => {
             // On error, send a generic error message.
@@ -0,0 +1,36 @@
+//! Defines the `/player` command for re-posting the music player message.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages,
+    music_channel::player_channel,
+    music_manager::{MusicError, MusicManager},
+};
+
+/// Re-posts the music player at the bottom of the music channel, or of the current channel.
+///
+/// The current channel is used if no music channel is configured. The previous player message
+/// is deleted, and the new one is kept up to date from now on.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn player(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Move the player message to the music channel, or to this channel.
+    let channel_id = player_channel(guild_id, ctx.channel_id()).await;
+    let reply =
+        match MusicManager::repost_player(ctx.serenity_context(), guild_id, channel_id).await {
+            Ok(()) => embedded_messages::generic_success(
+                "Music",
+                &format!("🎵 Player moved to <#{}>", channel_id),
+            ),
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
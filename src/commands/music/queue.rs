@@ -0,0 +1,139 @@
+//! Defines the `/queue` command group for exporting the music queue to a file and importing it.
+
+use poise::serenity_prelude as serenity;
+
+use super::*;
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::commands::music::utils::{
+    embedded_messages, format_duration,
+    music_manager::{MusicError, MusicManager},
+    queue_io::{self, MAX_IMPORT_SIZE, QueueFormat},
+};
+
+/// Commands for saving the music queue to a file and loading it back.
+#[poise::command(slash_command, subcommands("export", "import"), category = "Music")]
+pub async fn queue(_: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Uploads the current queue as a JSON, M3U, or text file.
+///
+/// JSON keeps the full metadata of every track, M3U can be opened in most media players, and
+/// text lists one URL per line. Any of them can be loaded back with `/queue import`.
+#[poise::command(slash_command)]
+async fn export(
+    ctx: Context<'_>,
+    #[description = "File format (default: JSON)"] format: Option<QueueFormat>,
+) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let format = format.unwrap_or_default();
+
+    // Collect the metadata of every track in the queue, starting with the one playing.
+    let tracks: Vec<TrackMetadata> = MusicManager::get_queue(&guild_id)
+        .await
+        .map(|queue| {
+            queue
+                .current_queue()
+                .iter()
+                .map(|handle| handle.data::<TrackMetadata>().as_ref().clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    if tracks.is_empty() {
+        ctx.send(embedded_messages::queue_is_empty()).await?;
+        return Ok(());
+    }
+
+    let content = match queue_io::export(&tracks, format) {
+        Ok(content) => content,
+        Err(e) => {
+            ctx.send(embedded_messages::generic_error(&e.to_string()))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Upload the queue as an attachment.
+    let attachment = serenity::CreateAttachment::bytes(
+        content.into_bytes(),
+        format!("queue.{}", format.extension()),
+    );
+    let description = format!(
+        "📤 Exported {} tracks ({}) as {}",
+        tracks.len(),
+        format_duration(queue_io::total_duration(&tracks)),
+        format
+    );
+    ctx.send(embedded_messages::generic_success("Music", &description).attachment(attachment))
+        .await?;
+
+    Ok(())
+}
+
+/// Adds the tracks from a queue file (JSON, M3U, or text) to the queue.
+///
+/// Each entry is looked up like a `/play` request, so entries can be YouTube or Spotify URLs
+/// or search queries. Entries that can't be found are skipped.
+#[poise::command(slash_command)]
+async fn import(
+    ctx: Context<'_>,
+    #[description = "A queue file exported with /queue export, an M3U playlist, or a text file"]
+    file: serenity::Attachment,
+) -> CommandResult {
+    // Defer the response ephemerally, as looking up every entry might take time.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let reply = match read_entries(&file).await {
+        Ok(entries) => {
+            match MusicManager::process_import_request(
+                ctx.serenity_context(),
+                guild_id,
+                ctx.channel_id(),
+                ctx.author(),
+                entries,
+            )
+            .await
+            {
+                Ok(outcome) => MusicManager::play_success_response(outcome),
+                Err(e) => embedded_messages::generic_error(&e.to_string()),
+            }
+        }
+        Err(e) => embedded_messages::generic_error(&e),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Downloads a queue file and reads its entries.
+async fn read_entries(file: &serenity::Attachment) -> Result<Vec<String>, String> {
+    if file.size > MAX_IMPORT_SIZE {
+        return Err(format!(
+            "Queue files can be at most {} KB",
+            MAX_IMPORT_SIZE / 1024
+        ));
+    }
+
+    let bytes = file
+        .download()
+        .await
+        .map_err(|e| format!("Failed to download the file: {}", e))?;
+    let content =
+        String::from_utf8(bytes).map_err(|_| "Queue files must be text files".to_string())?;
+
+    let format = QueueFormat::detect(&file.filename, &content);
+    let entries = queue_io::parse_entries(&content, format).map_err(|e| e.to_string())?;
+    if entries.is_empty() {
+        return Err("The file doesn't list any tracks".to_string());
+    }
+
+    Ok(entries)
+}
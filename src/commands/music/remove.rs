@@ -29,6 +29,15 @@ pub async fn remove(
         Box::new(MusicError::NotInGuild) as Box<dyn std::error::Error + Send + Sync>
     })?;
 
+    // Only listeners in the bot's voice channel can change the queue.
+    if let Err(e) =
+        MusicManager::ensure_same_channel(ctx.serenity_context(), guild_id, ctx.author().id).await
+    {
+        ctx.send(embedded_messages::generic_error(&e.to_string()))
+            .await?;
+        return Ok(());
+    }
+
     // Get the current queue for the guild.
     let queue = MusicManager::get_queue(&guild_id).await;
 
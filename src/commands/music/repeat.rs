@@ -0,0 +1,39 @@
+//! Defines the `/repeat` command for looping the current track.
+
+use super::*;
+use crate::commands::music::utils::{
+    button_controls::RepeatState, embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Toggles looping of the current track.
+///
+/// Equivalent to pressing the repeat button on the player.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn repeat(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the change using the same logic as the player buttons.
+    let reply =
+        match player_controls::toggle_repeat(ctx.serenity_context(), guild_id, ctx.author().id)
+            .await
+        {
+            Ok(RepeatState::Track) => {
+                embedded_messages::generic_success("Music", "🔂 Repeating the current track")
+            }
+            Ok(RepeatState::Disabled) => {
+                embedded_messages::generic_success("Music", "➡️ Repeat disabled")
+            }
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    // Keep the player message in sync with the change.
+    player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
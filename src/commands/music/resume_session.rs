@@ -0,0 +1,51 @@
+//! Defines the `/resume_session` command for restoring the last stopped music session.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages,
+    music_manager::{MusicError, MusicManager},
+    saved_sessions::SESSION_RETENTION,
+};
+
+/// Restores the queue that was playing when the bot was last stopped, where it left off.
+///
+/// Sessions are saved whenever the bot is stopped or ejected, and can be resumed for 7 days.
+/// The interrupted track resumes from where it was, unless something else is already playing,
+/// in which case the saved tracks are queued after it.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn resume_session(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally, as joining and queueing might take time.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let reply = match MusicManager::restore_session(
+        ctx.serenity_context(),
+        guild_id,
+        ctx.channel_id(),
+        ctx.author(),
+    )
+    .await
+    {
+        Ok(outcome) => {
+            let mut description = format!(
+                "▶️ Resumed {} track(s), starting with **{}**",
+                outcome.number_of_tracks, outcome.first_track.title
+            );
+            for note in &outcome.notes {
+                description.push_str(&format!("\n⚠️ {}", note));
+            }
+            embedded_messages::generic_success("Music", &description)
+        }
+        Err(MusicError::NoSavedSession) => embedded_messages::generic_error(&format!(
+            "There is no session from the last {} days to resume",
+            SESSION_RETENTION.as_secs() / (24 * 60 * 60)
+        )),
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
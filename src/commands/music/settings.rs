@@ -0,0 +1,238 @@
+//! Defines the `/music` command group for configuring the music feature in a guild.
+
+use poise::serenity_prelude as serenity;
+
+use super::blocklist::blocklist;
+use super::*;
+use crate::commands::music::utils::{
+    announcements::{get_announcement_settings, set_announcement_settings},
+    embedded_messages,
+    fair_queue::{self as fair, is_fair_queue_enabled, set_fair_queue},
+    music_channel::{MAX_REANCHOR_AFTER, get_music_channel, set_music_channel},
+    music_manager::{MusicError, MusicManager},
+    player_controls::refresh_player,
+    queue_limits::{get_queue_limits, set_queue_limits},
+};
+use crate::utils::database::{MusicChannelSetting, QueueLimits};
+
+/// Commands for configuring the music feature in this server.
+///
+/// Requires the Manage Server permission.
+#[poise::command(
+    slash_command,
+    subcommands("channel", "limits", "fair_queue", "announcements", "blocklist"),
+    category = "Music",
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn music(_: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Configures the dedicated music channel.
+///
+/// The player message is posted in the music channel, wherever music is requested from.
+/// `reanchor_after` re-sends the player once that many messages have been posted below it
+/// (0 disables this). With `request_mode` enabled, any plain message typed in the music
+/// channel is queued as a play request and then deleted. `clear` removes all of these settings.
+/// Arguments that are omitted keep their current value.
+#[poise::command(slash_command)]
+async fn channel(
+    ctx: Context<'_>,
+    #[description = "Channel the player is posted in"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "Re-send the player after this many newer messages (0 to disable)"]
+    #[min = 0]
+    #[max = 50]
+    reanchor_after: Option<u32>,
+    #[description = "Treat plain messages in the music channel as play requests"]
+    request_mode: Option<bool>,
+    #[description = "Remove the music channel settings"] clear: Option<bool>,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Start from the current settings, or from scratch when clearing them.
+    let mut setting = if clear == Some(true) {
+        MusicChannelSetting::default()
+    } else {
+        get_music_channel(guild_id).await
+    };
+
+    // Apply the provided arguments.
+    if let Some(channel) = channel {
+        setting.channel_id = Some(channel.id);
+    }
+    if let Some(reanchor_after) = reanchor_after {
+        // Clamp to the supported range, in case the command was invoked without Discord's validation.
+        setting.reanchor_after = reanchor_after.min(MAX_REANCHOR_AFTER);
+    }
+    if let Some(request_mode) = request_mode {
+        setting.request_mode = request_mode;
+    }
+
+    // Request mode only makes sense with a channel to listen in.
+    if setting.request_mode && setting.channel_id.is_none() {
+        ctx.send(embedded_messages::generic_error(
+            "Request mode needs a music channel. Set one with the `channel` argument.",
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // Store the new settings.
+    set_music_channel(guild_id, setting).await;
+
+    // Send an embed confirming the new settings.
+    ctx.send(embedded_messages::music_channel_status(&setting))
+        .await?;
+
+    Ok(())
+}
+
+/// Configures limits on music requests.
+///
+/// Caps the number of queued tracks, overall and per user, the length of queued tracks, and
+/// the number of tracks imported from a playlist, and sets a cooldown between a user's requests.
+/// A value of 0 removes the limit. `reset` restores the default limits.
+/// Arguments that are omitted keep their current value.
+#[poise::command(slash_command)]
+async fn limits(
+    ctx: Context<'_>,
+    #[description = "Most tracks in the queue (0 for no limit)"]
+    #[min = 0]
+    #[max = 5000]
+    max_queue_length: Option<u32>,
+    #[description = "Most tracks each user can have queued (0 for no limit)"]
+    #[min = 0]
+    #[max = 5000]
+    max_tracks_per_user: Option<u32>,
+    #[description = "Longest track in minutes (0 for no limit)"]
+    #[min = 0]
+    #[max = 600]
+    max_track_minutes: Option<u32>,
+    #[description = "Most tracks imported from a playlist (0 for no limit)"]
+    #[min = 0]
+    #[max = 5000]
+    max_playlist_size: Option<u32>,
+    #[description = "Seconds each user waits between requests (0 to disable)"]
+    #[min = 0]
+    #[max = 3600]
+    cooldown_seconds: Option<u32>,
+    #[description = "Restore the default limits"] reset: Option<bool>,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Start from the current limits, or from the defaults when resetting them.
+    let mut limits = if reset == Some(true) {
+        QueueLimits::default()
+    } else {
+        get_queue_limits(guild_id).await
+    };
+
+    // Apply the provided arguments.
+    if let Some(max_queue_length) = max_queue_length {
+        limits.max_queue_length = max_queue_length;
+    }
+    if let Some(max_tracks_per_user) = max_tracks_per_user {
+        limits.max_tracks_per_user = max_tracks_per_user;
+    }
+    if let Some(max_track_minutes) = max_track_minutes {
+        limits.max_track_duration = max_track_minutes.saturating_mul(60);
+    }
+    if let Some(max_playlist_size) = max_playlist_size {
+        limits.max_playlist_size = max_playlist_size;
+    }
+    if let Some(cooldown_seconds) = cooldown_seconds {
+        limits.cooldown = cooldown_seconds;
+    }
+
+    // Store the new limits.
+    set_queue_limits(guild_id, limits).await;
+
+    // Send an embed listing the limits now in place.
+    ctx.send(embedded_messages::queue_limits_status(&limits))
+        .await?;
+
+    Ok(())
+}
+
+/// Enables, disables, or toggles the fair queue.
+///
+/// With the fair queue, upcoming tracks take turns by requester (round-robin) instead of
+/// playing in the order they were requested. If `enabled` is omitted, the setting is toggled.
+#[poise::command(slash_command)]
+async fn fair_queue(
+    ctx: Context<'_>,
+    #[description = "Interleave upcoming tracks by requester"] enabled: Option<bool>,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Determine the desired state: use provided argument or toggle current state.
+    let enabled = match enabled {
+        Some(enabled) => enabled,
+        None => !is_fair_queue_enabled(guild_id).await,
+    };
+    set_fair_queue(guild_id, enabled).await;
+
+    // Apply the new order to the current queue right away.
+    if enabled && let Some(queue) = MusicManager::get_queue(&guild_id).await {
+        queue.modify_queue(fair::reorder);
+    }
+    refresh_player(ctx.serenity_context(), guild_id).await;
+
+    // Send an embed confirming the new setting.
+    ctx.send(embedded_messages::fair_queue_status(enabled))
+        .await?;
+
+    Ok(())
+}
+
+/// Configures now-playing announcements.
+///
+/// `enabled` posts a short "Now playing" message in the player channel whenever a track starts,
+/// deleted after `delete_after` seconds (0 keeps it). `dm_requester` sends requesters a direct
+/// message when their track starts, and `voice_status` shows the playing track as the status of
+/// the voice channel. Arguments that are omitted keep their current value.
+#[poise::command(slash_command)]
+async fn announcements(
+    ctx: Context<'_>,
+    #[description = "Post a message when a track starts"] enabled: Option<bool>,
+    #[description = "Seconds before announcements are deleted (0 keeps them)"]
+    #[min = 0]
+    #[max = 86400]
+    delete_after: Option<u32>,
+    #[description = "Send requesters a direct message when their track starts"]
+    dm_requester: Option<bool>,
+    #[description = "Show the track as the voice channel status"] voice_status: Option<bool>,
+) -> CommandResult {
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the provided arguments to the current settings.
+    let mut settings = get_announcement_settings(guild_id).await;
+    if let Some(enabled) = enabled {
+        settings.announce = enabled;
+    }
+    if let Some(delete_after) = delete_after {
+        settings.delete_after = delete_after;
+    }
+    if let Some(dm_requester) = dm_requester {
+        settings.dm_requester = dm_requester;
+    }
+    if let Some(voice_status) = voice_status {
+        settings.voice_status = voice_status;
+    }
+
+    // Store the new settings.
+    set_announcement_settings(guild_id, settings).await;
+
+    // Send an embed listing the settings now in place.
+    ctx.send(embedded_messages::announcement_status(&settings))
+        .await?;
+
+    Ok(())
+}
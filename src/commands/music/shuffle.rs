@@ -0,0 +1,32 @@
+//! Defines the `/shuffle` command for shuffling the music queue.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Shuffles the upcoming tracks in the queue, keeping the current track playing.
+///
+/// Equivalent to pressing the shuffle button on the player.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn shuffle(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the change using the same logic as the player buttons.
+    let reply =
+        match player_controls::shuffle(ctx.serenity_context(), guild_id, ctx.author().id).await {
+            Ok(()) => embedded_messages::generic_success("Music", "🔀 Shuffled the queue"),
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    // Keep the player message in sync with the change.
+    player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
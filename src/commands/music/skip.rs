@@ -0,0 +1,34 @@
+//! Defines the `/skip` command for skipping the current track.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Skips the current track and moves on to the next one in the queue.
+///
+/// Equivalent to pressing the next button on the player.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn skip(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the change using the same logic as the player buttons.
+    let reply = match player_controls::skip(ctx.serenity_context(), guild_id, ctx.author().id).await
+    {
+        Ok(metadata) => {
+            embedded_messages::generic_success("Music", &format!("⏭️ Skipped: {}", metadata.title))
+        }
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    // Keep the player message in sync with the change.
+    player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
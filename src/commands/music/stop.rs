@@ -0,0 +1,31 @@
+//! Defines the `/stop` command for ending the music session.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Stops playback, clears the queue, and makes the bot leave the voice channel.
+///
+/// Equivalent to pressing the eject button on the player. The player message is removed.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn stop(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Tear down the session using the same logic as the eject button.
+    let reply = match player_controls::stop(ctx.serenity_context(), guild_id, ctx.author().id).await
+    {
+        Ok(()) => {
+            embedded_messages::generic_success("Music", "⏹️ Stopped playback and left the channel")
+        }
+        Err(e) => embedded_messages::generic_error(&e.to_string()),
+    };
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
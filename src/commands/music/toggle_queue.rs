@@ -0,0 +1,39 @@
+//! Defines the `/toggle_queue` command for showing or hiding the queue in the player.
+
+use super::*;
+use crate::commands::music::utils::{
+    embedded_messages, music_manager::MusicError, player_controls,
+};
+
+/// Shows or hides the list of upcoming tracks in the player message.
+///
+/// Equivalent to pressing the queue button on the player.
+#[poise::command(slash_command, prefix_command, category = "Music")]
+pub async fn toggle_queue(ctx: Context<'_>) -> CommandResult {
+    // Defer the response ephemerally.
+    ctx.defer_ephemeral().await?;
+
+    // Ensure the command is used within a guild.
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    // Apply the change using the same logic as the player buttons.
+    let reply =
+        match player_controls::toggle_queue_view(ctx.serenity_context(), guild_id, ctx.author().id)
+            .await
+        {
+            Ok(true) => {
+                embedded_messages::generic_success("Music", "📜 Showing the queue in the player")
+            }
+            Ok(false) => {
+                embedded_messages::generic_success("Music", "📜 Hiding the queue in the player")
+            }
+            Err(e) => embedded_messages::generic_error(&e.to_string()),
+        };
+
+    // Keep the player message in sync with the change.
+    player_controls::refresh_player(ctx.serenity_context(), guild_id).await;
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
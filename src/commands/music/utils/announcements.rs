@@ -0,0 +1,238 @@
+//! Now-playing announcements.
+//! When a track starts, the guild can have the bot post a short "Now playing" message in the
+//! player channel (optionally deleted after a while), send the requester a direct message, and
+//! show the track in the status of the bot's voice channel.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+use serde::Serialize;
+use serenity::{ChannelId, CreateAllowedMentions, CreateMessage, GuildId};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::utils::database::{self, AnnouncementSettings};
+
+use super::music_manager::MusicManager;
+
+/// Discord's limit on the length of a voice channel status.
+const MAX_VOICE_STATUS_LENGTH: usize = 500;
+
+/// In-memory cache mapping GuildId to its announcement settings.
+static ANNOUNCEMENT_SETTINGS: LazyLock<Mutex<HashMap<GuildId, AnnouncementSettings>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Sets the announcement settings for a guild, updating both the cache and the database.
+pub async fn set_announcement_settings(guild_id: GuildId, settings: AnnouncementSettings) {
+    // Update the cache.
+    ANNOUNCEMENT_SETTINGS
+        .lock()
+        .await
+        .insert(guild_id, settings);
+
+    // Attempt to save the settings to the database, logging any errors.
+    if let Err(e) = database::set_announcement_settings(guild_id, &settings) {
+        eprintln!("Failed to save announcement settings to database: {}", e);
+    }
+}
+
+/// Gets the announcement settings for a guild, checking the cache before the database.
+pub async fn get_announcement_settings(guild_id: GuildId) -> AnnouncementSettings {
+    let mut settings = ANNOUNCEMENT_SETTINGS.lock().await;
+    *settings
+        .entry(guild_id)
+        .or_insert_with(|| database::get_announcement_settings(guild_id))
+}
+
+/// Announces a track that just started, as configured for the guild.
+/// `voice_channel` is the channel the bot is playing in, if known.
+pub async fn announce_track(
+    http: &Arc<serenity::Http>,
+    guild_id: GuildId,
+    voice_channel: Option<ChannelId>,
+    metadata: &TrackMetadata,
+) {
+    let settings = get_announcement_settings(guild_id).await;
+
+    // Post the announcement in the player channel.
+    if settings.announce
+        && let Some(channel_id) = MusicManager::get_channel_id(guild_id).await
+    {
+        post_announcement(http, channel_id, metadata, settings.delete_after).await;
+    }
+
+    // Let the requester know their track is on. Autoplay tracks have no requester.
+    if settings.dm_requester
+        && let Some(user_id) = metadata.requester_id
+    {
+        let message =
+            CreateMessage::new().content(direct_message(metadata, guild_id, voice_channel));
+        if let Err(e) = user_id.direct_message(http, message).await {
+            warn!("Failed to send now playing message to {}: {}", user_id, e);
+        }
+    }
+
+    // Show the track in the voice channel status.
+    if settings.voice_status
+        && let Some(channel_id) = voice_channel
+    {
+        set_voice_status(http, channel_id, &voice_status(metadata)).await;
+    }
+}
+
+/// Clears the voice channel status set for the playing track, if the guild uses it.
+pub async fn clear_voice_status(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    voice_channel: Option<ChannelId>,
+) {
+    if let Some(channel_id) = voice_channel
+        && get_announcement_settings(guild_id).await.voice_status
+    {
+        set_voice_status(http, channel_id, "").await;
+    }
+}
+
+/// Posts the "Now playing" message, deleting it after `delete_after` seconds if set.
+async fn post_announcement(
+    http: &Arc<serenity::Http>,
+    channel_id: ChannelId,
+    metadata: &TrackMetadata,
+    delete_after: u32,
+) {
+    // Mention the requester without pinging them.
+    let message = CreateMessage::new()
+        .content(announcement(metadata))
+        .allowed_mentions(CreateAllowedMentions::new());
+    let message = match channel_id.send_message(http, message).await {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("Failed to post now playing announcement: {}", e);
+            return;
+        }
+    };
+
+    if delete_after > 0 {
+        let http = http.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(delete_after.into())).await;
+            let _ = message.delete(&http).await;
+        });
+    }
+}
+
+/// Sets the status of a voice channel, or clears it if `status` is empty.
+async fn set_voice_status(http: &serenity::Http, channel_id: ChannelId, status: &str) {
+    #[derive(Serialize)]
+    struct VoiceStatus<'a> {
+        status: &'a str,
+    }
+
+    if let Err(e) = http
+        .edit_voice_status(channel_id, &VoiceStatus { status }, None)
+        .await
+    {
+        warn!(
+            "Failed to set status of voice channel {}: {}",
+            channel_id, e
+        );
+    }
+}
+
+/// Returns who requested a track: a mention of the user, or their name if the ID is unknown.
+fn requester(metadata: &TrackMetadata) -> Option<String> {
+    match (metadata.requester_id, &metadata.requested_by) {
+        (Some(user_id), _) => Some(format!("<@{}>", user_id)),
+        (None, Some(name)) => Some(name.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Builds the announcement posted in the player channel.
+fn announcement(metadata: &TrackMetadata) -> String {
+    let title = match &metadata.url {
+        Some(url) => format!("[{}](<{}>)", metadata.title, url),
+        None => metadata.title.clone(),
+    };
+    match requester(metadata) {
+        Some(requester) => format!("🎶 Now playing {} requested by {}", title, requester),
+        None => format!("🎶 Now playing {}", title),
+    }
+}
+
+/// Builds the direct message sent to the requester of a track, linking to the voice channel
+/// it plays in if known.
+fn direct_message(
+    metadata: &TrackMetadata,
+    guild_id: GuildId,
+    voice_channel: Option<ChannelId>,
+) -> String {
+    let mut message = format!("🎶 Your track **{}** is now playing", metadata.title);
+    if let Some(channel_id) = voice_channel {
+        message.push_str(&format!(
+            " in https://discord.com/channels/{}/{}",
+            guild_id, channel_id
+        ));
+    }
+    if let Some(url) = &metadata.url {
+        message.push_str(&format!("\n{}", url));
+    }
+    message
+}
+
+/// Builds the voice channel status for a track, truncated to Discord's limit.
+fn voice_status(metadata: &TrackMetadata) -> String {
+    let status = format!("🎶 {}", metadata.title);
+    match status.char_indices().nth(MAX_VOICE_STATUS_LENGTH) {
+        Some((index, _)) => status[..index].to_string(),
+        None => status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serenity::model::id::UserId;
+
+    fn track() -> TrackMetadata {
+        TrackMetadata {
+            title: "Song".to_string(),
+            url: Some("https://www.youtube.com/watch?v=aaaaaaaaaaa".to_string()),
+            requested_by: Some("Alice".to_string()),
+            requester_id: Some(UserId::new(42)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_announcement_mentions_requester() {
+        assert_eq!(
+            announcement(&track()),
+            "🎶 Now playing [Song](<https://www.youtube.com/watch?v=aaaaaaaaaaa>) requested by <@42>"
+        );
+    }
+
+    #[test]
+    fn test_announcement_without_requester() {
+        let metadata = TrackMetadata {
+            title: "Song".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(announcement(&metadata), "🎶 Now playing Song");
+    }
+
+    #[test]
+    fn test_voice_status_truncated() {
+        let metadata = TrackMetadata {
+            title: "é".repeat(MAX_VOICE_STATUS_LENGTH),
+            ..Default::default()
+        };
+        assert_eq!(
+            voice_status(&metadata).chars().count(),
+            MAX_VOICE_STATUS_LENGTH
+        );
+    }
+}
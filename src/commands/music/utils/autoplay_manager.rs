@@ -1,18 +1,81 @@
-//! Manages the autoplay state for guilds.
+//! Manages the autoplay state for guilds, including the selected mode and seed playlist.
 //! Uses an in-memory cache (`HashMap`) and persists settings to a database.
 //! Provides a globally accessible, thread-safe manager instance.
 
 use serenity::model::id::GuildId;
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use std::sync::{Arc, LazyLock};
 use tokio::sync::Mutex;
+use tracing::{info, warn};
 
+use crate::commands::music::audio_sources::AUDIO_APIS;
+use crate::commands::music::audio_sources::track_metadata::{AUTOPLAY_REQUESTER, TrackMetadata};
+use crate::commands::music::utils::music_manager::MusicError;
 use crate::utils::database;
 
+/// The strategy autoplay uses to pick the next song.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AutoplayMode {
+    /// Prefer songs by the same artist as the seed track.
+    #[default]
+    #[name = "Similar artist"]
+    SimilarArtist,
+    /// Prefer songs that sound alike, but from other artists.
+    #[name = "Same genre"]
+    SameGenre,
+    /// Prefer less obvious picks and avoid the seed track's artist.
+    #[name = "Discovery"]
+    Discovery,
+}
+
+impl AutoplayMode {
+    /// Returns the identifier used to persist the mode in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutoplayMode::SimilarArtist => "similar_artist",
+            AutoplayMode::SameGenre => "same_genre",
+            AutoplayMode::Discovery => "discovery",
+        }
+    }
+}
+
+impl fmt::Display for AutoplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AutoplayMode::SimilarArtist => "Similar artist",
+            AutoplayMode::SameGenre => "Same genre",
+            AutoplayMode::Discovery => "Discovery",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for AutoplayMode {
+    type Err = String;
+
+    /// Parses the database identifier produced by `AutoplayMode::as_str`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "similar_artist" => Ok(AutoplayMode::SimilarArtist),
+            "same_genre" => Ok(AutoplayMode::SameGenre),
+            "discovery" => Ok(AutoplayMode::Discovery),
+            other => Err(format!("Unknown autoplay mode: {}", other)),
+        }
+    }
+}
+
 /// Manages autoplay settings for multiple guilds.
 pub struct AutoplayManager {
     /// In-memory cache mapping GuildId to its autoplay status (true = enabled).
     autoplay_settings: HashMap<GuildId, bool>,
+    /// In-memory cache mapping GuildId to its selected autoplay mode.
+    autoplay_modes: HashMap<GuildId, AutoplayMode>,
+    /// In-memory cache mapping GuildId to its seed playlist URL (`None` if not set).
+    seed_playlists: HashMap<GuildId, Option<String>>,
+    /// In-memory cache mapping a seed playlist URL to the tracks it resolved to.
+    seed_playlist_tracks: HashMap<String, Vec<TrackMetadata>>,
 }
 
 impl AutoplayManager {
@@ -20,6 +83,9 @@ impl AutoplayManager {
     pub fn new() -> Self {
         Self {
             autoplay_settings: HashMap::new(),
+            autoplay_modes: HashMap::new(),
+            seed_playlists: HashMap::new(),
+            seed_playlist_tracks: HashMap::new(),
         }
     }
 
@@ -53,6 +119,73 @@ impl AutoplayManager {
 
         enabled
     }
+
+    /// Sets the autoplay mode for a specific guild.
+    /// Updates both the in-memory cache and the persistent database setting.
+    pub fn set_mode(&mut self, guild_id: GuildId, mode: AutoplayMode) {
+        // Update the cache.
+        self.autoplay_modes.insert(guild_id, mode);
+
+        // Attempt to save the setting to the database, logging any errors.
+        if let Err(e) = database::set_autoplay_mode_setting(guild_id, mode.as_str()) {
+            eprintln!("Failed to save autoplay mode to database: {}", e);
+        }
+    }
+
+    /// Gets the autoplay mode for a specific guild.
+    ///
+    /// Checks the in-memory cache first, then the database.
+    /// Defaults to `AutoplayMode::SimilarArtist` if no mode has been stored.
+    pub fn get_mode(&mut self, guild_id: GuildId) -> AutoplayMode {
+        // Check cache first.
+        if let Some(&mode) = self.autoplay_modes.get(&guild_id) {
+            return mode;
+        }
+
+        // Not in cache, fetch from database and parse it.
+        let mode = database::get_autoplay_mode_setting(guild_id)
+            .and_then(|mode| mode.parse().ok())
+            .unwrap_or_default();
+
+        // Update cache with the value fetched from the database.
+        self.autoplay_modes.insert(guild_id, mode);
+
+        mode
+    }
+
+    /// Sets or clears the seed playlist for a specific guild.
+    /// Updates both the in-memory cache and the persistent database setting.
+    pub fn set_seed_playlist(&mut self, guild_id: GuildId, url: Option<String>) {
+        // Attempt to save the setting to the database, logging any errors.
+        let result = match &url {
+            Some(url) => database::set_autoplay_seed_playlist(guild_id, url),
+            None => database::clear_autoplay_seed_playlist(guild_id),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to save autoplay seed playlist to database: {}", e);
+        }
+
+        // Update the cache.
+        self.seed_playlists.insert(guild_id, url);
+    }
+
+    /// Gets the seed playlist URL for a specific guild, if any.
+    ///
+    /// Checks the in-memory cache first, then the database.
+    pub fn get_seed_playlist(&mut self, guild_id: GuildId) -> Option<String> {
+        // Check cache first.
+        if let Some(url) = self.seed_playlists.get(&guild_id) {
+            return url.clone();
+        }
+
+        // Not in cache, fetch from database.
+        let url = database::get_autoplay_seed_playlist(guild_id);
+
+        // Update cache with the value fetched from the database.
+        self.seed_playlists.insert(guild_id, url.clone());
+
+        url
+    }
 }
 
 /// Global, thread-safe instance of the `AutoplayManager`.
@@ -76,6 +209,94 @@ pub async fn is_autoplay_enabled(guild_id: GuildId) -> bool {
     manager.is_autoplay_enabled(guild_id)
 }
 
+/// Asynchronously sets the autoplay mode for a guild using the global manager.
+pub async fn set_autoplay_mode(guild_id: GuildId, mode: AutoplayMode) {
+    // Lock the global manager.
+    let mut manager = AUTOPLAY_MANAGER.lock().await;
+    // Call the manager's method.
+    manager.set_mode(guild_id, mode);
+}
+
+/// Asynchronously gets the autoplay mode for a guild using the global manager.
+pub async fn get_autoplay_mode(guild_id: GuildId) -> AutoplayMode {
+    // Lock the global manager.
+    let mut manager = AUTOPLAY_MANAGER.lock().await;
+    // Call the manager's method.
+    manager.get_mode(guild_id)
+}
+
+/// Asynchronously sets or clears the seed playlist for a guild using the global manager.
+pub async fn set_autoplay_seed_playlist(guild_id: GuildId, url: Option<String>) {
+    // Lock the global manager.
+    let mut manager = AUTOPLAY_MANAGER.lock().await;
+    // Call the manager's method.
+    manager.set_seed_playlist(guild_id, url);
+}
+
+/// Asynchronously gets the seed playlist URL for a guild using the global manager.
+pub async fn get_autoplay_seed_playlist(guild_id: GuildId) -> Option<String> {
+    // Lock the global manager.
+    let mut manager = AUTOPLAY_MANAGER.lock().await;
+    // Call the manager's method.
+    manager.get_seed_playlist(guild_id)
+}
+
+/// Resolves a seed playlist URL into track metadata using the first `AudioApi` that accepts it.
+pub async fn resolve_seed_playlist(url: &str) -> Result<Vec<TrackMetadata>, MusicError> {
+    for api_handler in AUDIO_APIS.iter() {
+        if api_handler.is_valid_url(url) {
+            return api_handler
+                .get_metadata(url, AUTOPLAY_REQUESTER.to_string())
+                .await;
+        }
+    }
+    Err(MusicError::AudioSourceError(format!(
+        "Unable to resolve URL to valid provider: {}",
+        url
+    )))
+}
+
+/// Asynchronously gets the tracks of a guild's seed playlist, if one is set.
+///
+/// Resolved playlists are cached by URL, so the (slow) resolution only happens once.
+/// Returns an empty list if no playlist is set or if it cannot be resolved.
+pub async fn get_seed_playlist_tracks(guild_id: GuildId) -> Vec<TrackMetadata> {
+    // Look up the playlist URL and any cached tracks for it.
+    let (url, cached) = {
+        let mut manager = AUTOPLAY_MANAGER.lock().await;
+        let url = manager.get_seed_playlist(guild_id);
+        let cached = url
+            .as_ref()
+            .and_then(|url| manager.seed_playlist_tracks.get(url).cloned());
+        (url, cached)
+    };
+
+    let Some(url) = url else {
+        return Vec::new();
+    };
+    if let Some(tracks) = cached {
+        return tracks;
+    }
+
+    // Resolve the playlist without holding the lock.
+    match resolve_seed_playlist(&url).await {
+        Ok(tracks) => {
+            info!(
+                "Resolved autoplay seed playlist for guild {} to {} tracks",
+                guild_id,
+                tracks.len()
+            );
+            let mut manager = AUTOPLAY_MANAGER.lock().await;
+            manager.seed_playlist_tracks.insert(url, tracks.clone());
+            tracks
+        }
+        Err(e) => {
+            warn!("Failed to resolve autoplay seed playlist {}: {}", url, e);
+            Vec::new()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,6 +304,29 @@ mod tests {
     #[test]
     fn test_autoplay_manager_new() {
         let manager = AutoplayManager::new();
-        assert!(manager.autoplay_settings.is_empty(), "Autoplay settings should be empty on creation");
+        assert!(
+            manager.autoplay_settings.is_empty(),
+            "Autoplay settings should be empty on creation"
+        );
+        assert!(
+            manager.autoplay_modes.is_empty(),
+            "Autoplay modes should be empty on creation"
+        );
+        assert!(
+            manager.seed_playlists.is_empty(),
+            "Seed playlists should be empty on creation"
+        );
+    }
+
+    #[test]
+    fn test_autoplay_mode_round_trip() {
+        for mode in [
+            AutoplayMode::SimilarArtist,
+            AutoplayMode::SameGenre,
+            AutoplayMode::Discovery,
+        ] {
+            assert_eq!(mode.as_str().parse::<AutoplayMode>(), Ok(mode));
+        }
+        assert!("not_a_mode".parse::<AutoplayMode>().is_err());
     }
 }
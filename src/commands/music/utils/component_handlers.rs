@@ -1,28 +1,25 @@
 //! Handles component interactions (button clicks) for the music player controls.
-//! Routes interactions based on custom IDs to specific handler functions.
+//! Routes interactions based on custom IDs to specific handler functions, which share their
+//! playback logic with the equivalent commands through `player_controls`.
 
 use ::serenity::all::{
     ComponentInteraction, CreateInteractionResponseFollowup, CreateQuickModal, GuildId,
 };
 use poise::serenity_prelude::{self as serenity, Context};
 use serenity::{InputTextStyle, builder::CreateInputText};
-use songbird::tracks::PlayMode;
 use std::time::Duration;
-use tokio::time::sleep;
-use tracing::{debug, error, info};
+use tracing::{error, info};
 
-use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
-
-use super::{button_controls::RepeatState, embedded_messages, music_manager::MusicManager};
-use tracing::warn;
+use super::{embedded_messages, music_manager::MusicManager, player_controls};
 
 /// A specialized `Result` type for button interaction handlers.
 type ButtonInteractionResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
 /// The main entry point for handling music-related component interactions.
 ///
-/// It defers the interaction, checks if the bot is in a voice channel (except for search),
-/// and routes the interaction to the appropriate handler based on the `custom_id`.
+/// It defers the interaction (except for search, which opens a modal) and routes it to the
+/// appropriate handler based on the `custom_id`. The handlers check that the user is in the
+/// bot's voice channel through `player_controls`.
 pub async fn handle_interaction(
     ctx: &Context,
     interaction: &mut ComponentInteraction,
@@ -34,12 +31,6 @@ pub async fn handle_interaction(
     if interaction.data.custom_id != "music_search" {
         // Acknowledge the interaction quickly.
         interaction.defer(ctx).await?;
-
-        // Check if the bot is connected to a voice channel in this guild.
-        if (MusicManager::get_call(ctx, guild_id).await).is_err() {
-            // Send an error message if not connected.
-            return error_followup(ctx, interaction, "I'm not in a voice channel.").await;
-        }
     }
 
     // Match the custom ID to the corresponding handler function.
@@ -68,26 +59,11 @@ async fn handle_play_pause(
     interaction: &mut ComponentInteraction,
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
-    // Get the handle for the currently playing track.
-    let current_track_opt = MusicManager::get_current_track(&guild_id).await;
-
-    if let Some(track) = current_track_opt {
-        // Get the current playback state (playing or paused).
-        let track_info = track.get_info().await?;
-        let is_playing = track_info.playing == PlayMode::Play;
-
-        // Toggle the state.
-        if is_playing {
-            track.pause()?;
-        } else {
-            track.play()?;
-        }
-
+    match player_controls::toggle_pause(ctx, guild_id, interaction.user.id).await {
         // Update the original message with new buttons/embed.
-        update_player_message(ctx, interaction).await
-    } else {
+        Ok(_) => update_player_message(ctx, interaction).await,
         // Send error if no track is playing.
-        error_followup(ctx, interaction, "No track is currently playing.").await
+        Err(e) => error_followup(ctx, interaction, &e.to_string()).await,
     }
 }
 
@@ -98,86 +74,27 @@ async fn handle_music_eject(
     interaction: &mut ComponentInteraction,
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
-    // Clone HTTP client for potential direct message deletion.
-    let http = ctx.http.clone(); // Get http client reference
-
-    // Stop playback and clear the queue.
-    if let Some(queue) = MusicManager::get_queue(&guild_id).await {
-        queue.stop();
-    }
-
-    // Attempt to leave the voice channel.
-    if let Err(e) = MusicManager::leave_channel(ctx, guild_id).await {
-        warn!("Failed to leave voice channel via button stop: {}", e);
-        // Don't return error, proceed to delete message if possible
-    }
-
-    // Try to delete the original player message.
-    if let (Some(channel_id), Some(message_id)) = (
-        MusicManager::get_channel_id(guild_id).await,
-        MusicManager::get_message_id(guild_id).await,
-    ) {
-        // Check if the interaction message is the one we want to delete
-        if interaction.message.id == message_id {
-            // Try deleting via the interaction response first.
-            if let Err(e) = interaction.delete_response(&ctx.http).await {
-                warn!(
-                    "Failed to delete player message via interaction response: {}",
-                    e
-                );
-                // If interaction deletion fails, try direct deletion.
-                if let Err(e_del) = http.delete_message(channel_id, message_id, None).await {
-                    warn!(
-                        "Failed to delete player message directly after interaction failure: {}",
-                        e_del
-                    );
-                }
-            }
-        } else {
-            // If interaction isn't on the player message, delete the stored message ID directly.
-            if let Err(e) = http.delete_message(channel_id, message_id, None).await {
-                warn!(
-                    "Failed to delete player message {} in channel {}: {}",
-                    message_id, channel_id, e
-                );
-            }
-        }
-    } else {
-        warn!(
-            "Could not find channel/message ID to delete for guild {}",
-            guild_id
-        );
+    // The player message is deleted along with the rest of the guild's music state.
+    if let Err(e) = player_controls::stop(ctx, guild_id, interaction.user.id).await {
+        return error_followup(ctx, interaction, &e.to_string()).await;
     }
 
-    // Clean up any remaining guild-specific data in the MusicManager.
-    MusicManager::drop_all(&guild_id).await;
-
     Ok(())
 }
 
 /// Handles the next track (skip) button interaction.
-/// Stops the current track, allowing the `SongEndNotifier` to play the next one.
+/// Stops the current track, allowing the queue to play the next one.
 /// Updates the player message.
 async fn handle_next(
     ctx: &Context,
     interaction: &mut ComponentInteraction,
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
-    // Get the handle for the currently playing track.
-    let current_track_opt = MusicManager::get_current_track(&guild_id).await;
-
-    if let Some(track) = current_track_opt {
-        // Stop the track. The event handler will trigger the next song.
-        track.stop()?;
-
-        // Short delay to allow Songbird event handlers to potentially update state.
-        sleep(Duration::from_millis(100)).await;
-
+    match player_controls::skip(ctx, guild_id, interaction.user.id).await {
         // Update the original message.
-        update_player_message(ctx, interaction).await
-    } else {
+        Ok(_) => update_player_message(ctx, interaction).await,
         // Send error if no track is playing.
-        error_followup(ctx, interaction, "No track is currently playing to skip.").await
+        Err(e) => error_followup(ctx, interaction, &e.to_string()).await,
     }
 }
 
@@ -189,7 +106,9 @@ async fn handle_queue_toggle(
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
     // Flip the boolean state for showing the queue.
-    MusicManager::toggle_queue_view(guild_id).await;
+    if let Err(e) = player_controls::toggle_queue_view(ctx, guild_id, interaction.user.id).await {
+        return error_followup(ctx, interaction, &e.to_string()).await;
+    }
 
     // Update the original message.
     update_player_message(ctx, interaction).await
@@ -245,8 +164,8 @@ async fn handle_search(
         .await
         {
             // On success, send an ephemeral followup with the result.
-            Ok((metadata, number_of_tracks)) => {
-                let response = MusicManager::play_success_response(metadata, number_of_tracks);
+            Ok(outcome) => {
+                let response = MusicManager::play_success_response(outcome);
 
                 interaction
                     .create_followup(
@@ -283,37 +202,11 @@ async fn handle_repeat(
     interaction: &mut ComponentInteraction,
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
-    // Get the handle for the currently playing track.
-    let current_track_opt = MusicManager::get_current_track(&guild_id).await;
-
-    if let Some(track) = current_track_opt {
-        // Get the current repeat state.
-        let current_state = MusicManager::get_repeat_state(guild_id).await;
-        // Determine the new state and enable/disable looping on the track handle.
-        let new_state = match current_state {
-            RepeatState::Disabled => {
-                debug!("Looping track '{}'", track.data::<TrackMetadata>().title);
-                track.enable_loop()?;
-                RepeatState::Track
-            }
-            RepeatState::Track => {
-                debug!(
-                    "Disabling loop for track '{}'",
-                    track.data::<TrackMetadata>().title
-                );
-                track.disable_loop()?;
-                RepeatState::Disabled
-            }
-        };
-
-        // Store the new repeat state in the manager.
-        MusicManager::set_repeat_state(guild_id, new_state).await;
-
+    match player_controls::toggle_repeat(ctx, guild_id, interaction.user.id).await {
         // Update the original message.
-        update_player_message(ctx, interaction).await
-    } else {
+        Ok(_) => update_player_message(ctx, interaction).await,
         // Send error if no track is playing.
-        error_followup(ctx, interaction, "No track is currently playing.").await
+        Err(e) => error_followup(ctx, interaction, &e.to_string()).await,
     }
 }
 
@@ -324,11 +217,11 @@ async fn handle_shuffle(
     interaction: &mut ComponentInteraction,
     guild_id: GuildId,
 ) -> ButtonInteractionResult {
-    // Shuffle the queue via the MusicManager.
-    MusicManager::shuffle_queue(&guild_id).await;
-
-    // Update the original message.
-    update_player_message(ctx, interaction).await
+    match player_controls::shuffle(ctx, guild_id, interaction.user.id).await {
+        // Update the original message.
+        Ok(()) => update_player_message(ctx, interaction).await,
+        Err(e) => error_followup(ctx, interaction, &e.to_string()).await,
+    }
 }
 
 /// Helper function to update the original music player message after an interaction.
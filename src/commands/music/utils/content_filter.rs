@@ -0,0 +1,518 @@
+//! Per-guild content filtering for music.
+//! Guilds can block or exclusively allow tracks by title pattern, uploader, video ID, or domain,
+//! and can refuse age-restricted tracks. Filters apply to requested tracks and to autoplay.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+use regex::{Regex, RegexBuilder};
+use serenity::model::id::GuildId;
+use tokio::sync::Mutex;
+use tracing::warn;
+use url::Url;
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::commands::music::audio_sources::youtube::YoutubeApi;
+use crate::utils::database::{self, MusicFilterEntry};
+
+use super::music_manager::{MusicError, MusicResult};
+use super::queue_limits::LimitedTracks;
+
+/// The `age_limit` reported by yt-dlp from which a track counts as explicit.
+const EXPLICIT_AGE_LIMIT: u32 = 18;
+
+/// Whether a filter rule blocks matching tracks or allows only matching tracks.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum FilterList {
+    /// Matching tracks can't be played.
+    #[default]
+    #[name = "Blocklist"]
+    Block,
+    /// Only matching tracks can be played.
+    #[name = "Allowlist"]
+    Allow,
+}
+
+impl FilterList {
+    /// Returns the identifier used to persist the list in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterList::Block => "block",
+            FilterList::Allow => "allow",
+        }
+    }
+}
+
+impl fmt::Display for FilterList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FilterList::Block => "Blocklist",
+            FilterList::Allow => "Allowlist",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for FilterList {
+    type Err = String;
+
+    /// Parses the database identifier produced by `FilterList::as_str`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(FilterList::Block),
+            "allow" => Ok(FilterList::Allow),
+            other => Err(format!("Unknown filter list: {}", other)),
+        }
+    }
+}
+
+/// The track property a filter rule matches against.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum FilterKind {
+    /// A case-insensitive regular expression matched against the track title.
+    #[name = "Title pattern"]
+    Title,
+    /// The name of the channel that uploaded the track, ignoring case.
+    #[name = "Uploader"]
+    Uploader,
+    /// A YouTube video ID.
+    #[name = "Video ID"]
+    VideoId,
+    /// A domain, matching its subdomains too.
+    #[name = "Domain"]
+    Domain,
+}
+
+impl FilterKind {
+    /// Returns the identifier used to persist the kind in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterKind::Title => "title",
+            FilterKind::Uploader => "uploader",
+            FilterKind::VideoId => "video_id",
+            FilterKind::Domain => "domain",
+        }
+    }
+}
+
+impl fmt::Display for FilterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FilterKind::Title => "Title pattern",
+            FilterKind::Uploader => "Uploader",
+            FilterKind::VideoId => "Video ID",
+            FilterKind::Domain => "Domain",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    /// Parses the database identifier produced by `FilterKind::as_str`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(FilterKind::Title),
+            "uploader" => Ok(FilterKind::Uploader),
+            "video_id" => Ok(FilterKind::VideoId),
+            "domain" => Ok(FilterKind::Domain),
+            other => Err(format!("Unknown filter kind: {}", other)),
+        }
+    }
+}
+
+/// A single blocklist or allowlist entry.
+#[derive(Clone, Debug)]
+pub struct FilterRule {
+    /// The list the rule belongs to.
+    pub list: FilterList,
+    /// The track property the rule matches against.
+    pub kind: FilterKind,
+    /// The pattern as entered by the admin.
+    pub pattern: String,
+    /// The compiled pattern, for title rules.
+    regex: Option<Regex>,
+}
+
+impl FilterRule {
+    /// Creates a rule, normalizing the pattern and compiling it if it is a title pattern.
+    pub fn new(list: FilterList, kind: FilterKind, pattern: &str) -> MusicResult<Self> {
+        let pattern = match kind {
+            FilterKind::Title | FilterKind::VideoId => pattern.trim().to_string(),
+            FilterKind::Uploader | FilterKind::Domain => pattern.trim().to_lowercase(),
+        };
+        if pattern.is_empty() {
+            return Err(MusicError::InvalidFilter(
+                "The pattern is empty".to_string(),
+            ));
+        }
+
+        let regex = match kind {
+            FilterKind::Title => Some(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| MusicError::InvalidFilter(e.to_string()))?,
+            ),
+            _ => None,
+        };
+
+        Ok(Self {
+            list,
+            kind,
+            pattern,
+            regex,
+        })
+    }
+
+    /// Returns `true` if the rule matches the track.
+    fn matches(&self, track: &TrackMetadata) -> bool {
+        match self.kind {
+            FilterKind::Title => self
+                .regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(&track.title)),
+            FilterKind::Uploader => track
+                .uploader
+                .as_deref()
+                .is_some_and(|uploader| uploader.to_lowercase() == self.pattern),
+            FilterKind::VideoId => track
+                .url
+                .as_deref()
+                .and_then(|url| YoutubeApi::extract_video_id(url).ok())
+                .is_some_and(|id| id == self.pattern),
+            FilterKind::Domain => track
+                .url
+                .as_deref()
+                .and_then(|url| Url::parse(url).ok())
+                .and_then(|url| url.host_str().map(str::to_lowercase))
+                .is_some_and(|host| {
+                    host == self.pattern || host.ends_with(&format!(".{}", self.pattern))
+                }),
+        }
+    }
+}
+
+/// A guild's content filter.
+#[derive(Clone, Debug, Default)]
+pub struct ContentFilter {
+    /// The guild's blocklist and allowlist entries.
+    pub rules: Vec<FilterRule>,
+    /// Whether age-restricted tracks are refused.
+    pub block_explicit: bool,
+}
+
+impl ContentFilter {
+    /// Checks a track against the filter, returning why it is refused, if it is.
+    ///
+    /// A track is refused if it matches a blocklist entry, if the allowlist has entries and the
+    /// track matches none of them, or if it is age-restricted and explicit tracks are blocked.
+    pub fn check(&self, track: &TrackMetadata) -> Result<(), String> {
+        if self.block_explicit
+            && track
+                .age_limit
+                .is_some_and(|age_limit| age_limit >= EXPLICIT_AGE_LIMIT)
+        {
+            return Err("age-restricted content is blocked".to_string());
+        }
+
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.list == FilterList::Block && rule.matches(track))
+        {
+            return Err(format!(
+                "blocked by {} `{}`",
+                rule.kind.to_string().to_lowercase(),
+                rule.pattern
+            ));
+        }
+
+        let mut allow_rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.list == FilterList::Allow)
+            .peekable();
+        if allow_rules.peek().is_some() && !allow_rules.any(|rule| rule.matches(track)) {
+            return Err("not on the allowlist".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether checking a track needs its uploader or age restriction, which related
+    /// song lookups for autoplay leave out.
+    pub fn needs_details(&self) -> bool {
+        self.block_explicit
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.kind == FilterKind::Uploader)
+    }
+
+    /// Checks a track picked by autoplay against the filter.
+    ///
+    /// Unlike requested tracks, tracks whose age restriction is unknown are refused while
+    /// explicit tracks are blocked, as nobody chose them.
+    pub fn check_autoplay(&self, track: &TrackMetadata) -> Result<(), String> {
+        if self.block_explicit && track.age_limit.is_none() {
+            return Err("its age restriction is unknown".to_string());
+        }
+        self.check(track)
+    }
+
+    /// Removes refused tracks from a request.
+    ///
+    /// Fails with `ContentBlocked` if every track is refused, and otherwise notes how many
+    /// tracks were left out.
+    pub fn apply(&self, mut tracks: Vec<TrackMetadata>) -> MusicResult<LimitedTracks> {
+        let requested = tracks.len();
+        let mut first_reason = None;
+        tracks.retain(|track| match self.check(track) {
+            Ok(()) => true,
+            Err(reason) => {
+                first_reason.get_or_insert(format!("{} ({})", track.title, reason));
+                false
+            }
+        });
+
+        let mut notes = Vec::new();
+        if let Some(reason) = first_reason {
+            if tracks.is_empty() {
+                return Err(MusicError::ContentBlocked(reason));
+            }
+            notes.push(format!(
+                "Skipped {} tracks blocked on this server",
+                requested - tracks.len()
+            ));
+        }
+
+        Ok(LimitedTracks { tracks, notes })
+    }
+}
+
+/// In-memory cache mapping GuildId to its content filter.
+static CONTENT_FILTERS: LazyLock<Mutex<HashMap<GuildId, Arc<ContentFilter>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Loads a guild's content filter from the database, skipping entries that no longer parse.
+fn load_content_filter(guild_id: GuildId) -> ContentFilter {
+    let rules = database::get_music_filters(guild_id)
+        .into_iter()
+        .filter_map(|entry| {
+            let rule = entry
+                .list
+                .parse()
+                .and_then(|list| entry.kind.parse().map(|kind| (list, kind)))
+                .and_then(|(list, kind)| {
+                    FilterRule::new(list, kind, &entry.pattern).map_err(|e| e.to_string())
+                });
+            rule.inspect_err(|e| warn!("Ignoring stored music filter: {}", e))
+                .ok()
+        })
+        .collect();
+
+    ContentFilter {
+        rules,
+        block_explicit: database::get_explicit_filter_setting(guild_id),
+    }
+}
+
+/// Gets the content filter for a guild, checking the cache before the database.
+pub async fn get_content_filter(guild_id: GuildId) -> Arc<ContentFilter> {
+    let mut filters = CONTENT_FILTERS.lock().await;
+    filters
+        .entry(guild_id)
+        .or_insert_with(|| Arc::new(load_content_filter(guild_id)))
+        .clone()
+}
+
+/// Reloads a guild's content filter from the database after it changed.
+async fn reload_content_filter(guild_id: GuildId) {
+    CONTENT_FILTERS
+        .lock()
+        .await
+        .insert(guild_id, Arc::new(load_content_filter(guild_id)));
+}
+
+/// Adds a rule to a guild's blocklist or allowlist.
+/// Returns `false` if the rule already existed.
+pub async fn add_filter_rule(guild_id: GuildId, rule: &FilterRule) -> MusicResult<bool> {
+    let entry = MusicFilterEntry {
+        list: rule.list.as_str().to_string(),
+        kind: rule.kind.as_str().to_string(),
+        pattern: rule.pattern.clone(),
+    };
+    let added = database::add_music_filter(guild_id, &entry)
+        .map_err(|e| MusicError::ConfigError(e.to_string()))?;
+    reload_content_filter(guild_id).await;
+    Ok(added)
+}
+
+/// Removes a rule from a guild's blocklist or allowlist.
+/// Returns `false` if there was no such rule.
+pub async fn remove_filter_rule(guild_id: GuildId, rule: &FilterRule) -> MusicResult<bool> {
+    let entry = MusicFilterEntry {
+        list: rule.list.as_str().to_string(),
+        kind: rule.kind.as_str().to_string(),
+        pattern: rule.pattern.clone(),
+    };
+    let removed = database::remove_music_filter(guild_id, &entry)
+        .map_err(|e| MusicError::ConfigError(e.to_string()))?;
+    reload_content_filter(guild_id).await;
+    Ok(removed)
+}
+
+/// Enables or disables the explicit content filter for a guild.
+pub async fn set_block_explicit(guild_id: GuildId, enabled: bool) {
+    // Attempt to save the setting to the database, logging any errors.
+    if let Err(e) = database::set_explicit_filter_setting(guild_id, enabled) {
+        eprintln!("Failed to save explicit filter setting to database: {}", e);
+    }
+    reload_content_filter(guild_id).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(
+        title: &str,
+        url: &str,
+        uploader: Option<&str>,
+        age_limit: Option<u32>,
+    ) -> TrackMetadata {
+        TrackMetadata {
+            title: title.to_string(),
+            url: Some(url.to_string()),
+            uploader: uploader.map(String::from),
+            age_limit,
+            ..Default::default()
+        }
+    }
+
+    fn filter(rules: &[(FilterList, FilterKind, &str)]) -> ContentFilter {
+        ContentFilter {
+            rules: rules
+                .iter()
+                .map(|(list, kind, pattern)| FilterRule::new(*list, *kind, pattern).unwrap())
+                .collect(),
+            block_explicit: false,
+        }
+    }
+
+    #[test]
+    fn test_filter_list_and_kind_round_trip() {
+        for list in [FilterList::Block, FilterList::Allow] {
+            assert_eq!(list.as_str().parse::<FilterList>(), Ok(list));
+        }
+        for kind in [
+            FilterKind::Title,
+            FilterKind::Uploader,
+            FilterKind::VideoId,
+            FilterKind::Domain,
+        ] {
+            assert_eq!(kind.as_str().parse::<FilterKind>(), Ok(kind));
+        }
+        assert!("other".parse::<FilterKind>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(matches!(
+            FilterRule::new(FilterList::Block, FilterKind::Title, "(unclosed"),
+            Err(MusicError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            FilterRule::new(FilterList::Block, FilterKind::Uploader, "  "),
+            Err(MusicError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_blocklist_rules() {
+        let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        let song = track("Never Gonna Give You Up", url, Some("Rick Astley"), Some(0));
+
+        assert!(filter(&[]).check(&song).is_ok());
+        for (kind, pattern) in [
+            (FilterKind::Title, "never gonna"),
+            (FilterKind::Uploader, "RICK ASTLEY"),
+            (FilterKind::VideoId, "dQw4w9WgXcQ"),
+            (FilterKind::Domain, "youtube.com"),
+        ] {
+            assert!(
+                filter(&[(FilterList::Block, kind, pattern)])
+                    .check(&song)
+                    .is_err(),
+                "{} `{}` should block the track",
+                kind,
+                pattern
+            );
+        }
+
+        // Domains match whole labels only.
+        assert!(
+            filter(&[(FilterList::Block, FilterKind::Domain, "tube.com")])
+                .check(&song)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_allowlist_rules() {
+        let allowed = track("A", "https://youtu.be/aaaaaaaaaaa", Some("Label"), None);
+        let other = track("B", "https://youtu.be/bbbbbbbbbbb", Some("Someone"), None);
+        let filter = filter(&[(FilterList::Allow, FilterKind::Uploader, "label")]);
+
+        assert!(filter.check(&allowed).is_ok());
+        assert!(filter.check(&other).is_err());
+    }
+
+    #[test]
+    fn test_explicit_filter() {
+        let explicit = track("A", "https://youtu.be/aaaaaaaaaaa", None, Some(18));
+        let unknown = track("B", "https://youtu.be/bbbbbbbbbbb", None, None);
+        let mut filter = filter(&[]);
+        assert!(filter.check(&explicit).is_ok());
+
+        filter.block_explicit = true;
+        assert!(filter.check(&explicit).is_err());
+        assert!(filter.check(&unknown).is_ok());
+    }
+
+    #[test]
+    fn test_explicit_filter_refuses_unknown_autoplay_tracks() {
+        let explicit = track("A", "https://youtu.be/aaaaaaaaaaa", None, Some(18));
+        let unknown = track("B", "https://youtu.be/bbbbbbbbbbb", None, None);
+        let safe = track("C", "https://youtu.be/ccccccccccc", None, Some(0));
+        let mut filter = filter(&[]);
+        assert!(!filter.needs_details());
+        assert!(filter.check_autoplay(&unknown).is_ok());
+
+        filter.block_explicit = true;
+        assert!(filter.needs_details());
+        assert!(filter.check_autoplay(&explicit).is_err());
+        assert!(filter.check_autoplay(&unknown).is_err());
+        assert!(filter.check_autoplay(&safe).is_ok());
+    }
+
+    #[test]
+    fn test_apply_skips_blocked_tracks() {
+        let filter = filter(&[(FilterList::Block, FilterKind::Title, "^bad")]);
+        let good = track("Good", "https://youtu.be/aaaaaaaaaaa", None, None);
+        let bad = track("Bad", "https://youtu.be/bbbbbbbbbbb", None, None);
+
+        let result = filter.apply(vec![good.clone(), bad.clone()]).unwrap();
+        assert_eq!(result.tracks, vec![good]);
+        assert_eq!(result.notes.len(), 1);
+
+        assert!(matches!(
+            filter.apply(vec![bad]),
+            Err(MusicError::ContentBlocked(_))
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+//! Gapless and crossfaded transitions between queued tracks.
+//! Computes when the next track should be pre-buffered, stores the per-guild crossfade
+//! duration, and ramps the volumes of the outgoing and incoming tracks.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+use serenity::async_trait;
+use serenity::model::id::GuildId;
+use songbird::tracks::TrackHandle;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::utils::database;
+
+use super::button_controls::RepeatState;
+use super::music_manager::MusicManager;
+
+/// The longest crossfade a guild can configure.
+pub const MAX_CROSSFADE_SECS: u64 = 12;
+
+/// How long before the end of a track the next track starts buffering.
+/// Covers the longest crossfade, so the incoming track is always ready when the fade starts.
+const PRELOAD_LEAD: Duration = Duration::from_secs(MAX_CROSSFADE_SECS + 8);
+
+/// The number of volume steps used for a crossfade.
+const FADE_STEPS: u32 = 20;
+
+/// In-memory cache mapping GuildId to its crossfade duration.
+static CROSSFADE_SETTINGS: LazyLock<Mutex<HashMap<GuildId, Duration>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Sets the crossfade duration for a guild, updating both the cache and the database.
+/// A zero duration disables crossfading.
+pub async fn set_crossfade(guild_id: GuildId, duration: Duration) {
+    // Update the cache.
+    CROSSFADE_SETTINGS.lock().await.insert(guild_id, duration);
+
+    // Attempt to save the setting to the database, logging any errors.
+    if let Err(e) = database::set_crossfade_setting(guild_id, duration.as_secs()) {
+        eprintln!("Failed to save crossfade setting to database: {}", e);
+    }
+}
+
+/// Gets the crossfade duration for a guild, checking the cache before the database.
+pub async fn get_crossfade(guild_id: GuildId) -> Duration {
+    let mut settings = CROSSFADE_SETTINGS.lock().await;
+    *settings
+        .entry(guild_id)
+        .or_insert_with(|| Duration::from_secs(database::get_crossfade_setting(guild_id)))
+}
+
+/// Returns the position in a track at which the next queued track should start buffering,
+/// or `None` if the track's duration is unknown.
+pub fn preload_time(track_duration: Option<Duration>) -> Option<Duration> {
+    track_duration.map(|duration| duration.saturating_sub(PRELOAD_LEAD))
+}
+
+/// Returns the position in a track at which the crossfade into the next track should start.
+///
+/// Returns `None` if crossfading is disabled, if the track's duration is unknown, or if the
+/// track is too short to fade over (less than twice the crossfade duration).
+pub fn crossfade_start(track_duration: Option<Duration>, crossfade: Duration) -> Option<Duration> {
+    let duration = track_duration?;
+    if crossfade.is_zero() || duration < crossfade * 2 {
+        return None;
+    }
+    Some(duration - crossfade)
+}
+
+/// Returns the volumes of the outgoing and incoming tracks at a given step of the fade.
+fn fade_volumes(step: u32, steps: u32) -> (f32, f32) {
+    let progress = (step.min(steps) as f32) / (steps.max(1) as f32);
+    (1.0 - progress, progress)
+}
+
+/// A Songbird event handler that fires shortly before a track ends and fades it into the next
+/// queued track.
+pub struct CrossfadeNotifier {
+    /// The ID of the guild where the event occurred.
+    pub guild_id: GuildId,
+    /// A handle to the Songbird voice call.
+    pub call: Arc<serenity::prelude::Mutex<songbird::Call>>,
+}
+
+#[async_trait]
+impl songbird::EventHandler for CrossfadeNotifier {
+    /// Starts the crossfade if the track is still at the head of the queue and another
+    /// track follows it.
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        let songbird::EventContext::Track([(_track_state, track_handle)]) = ctx else {
+            return None;
+        };
+
+        // A repeated track never ends, so there is nothing to fade into.
+        if MusicManager::get_repeat_state(self.guild_id).await == RepeatState::Track {
+            return None;
+        }
+
+        // The crossfade may have been disabled since the track was queued.
+        let crossfade = get_crossfade(self.guild_id).await;
+        if crossfade.is_zero() {
+            return None;
+        }
+
+        // Only fade if this track is the one playing and another one follows.
+        let queue = self.call.lock().await.queue().current_queue();
+        let is_current = queue
+            .first()
+            .is_some_and(|current| current.uuid() == track_handle.uuid());
+        let next = queue.get(1).filter(|_| is_current).cloned()?;
+
+        info!(
+            "Crossfading into the next track for guild {}",
+            self.guild_id
+        );
+        tokio::spawn(fade((*track_handle).clone(), next, crossfade));
+
+        None
+    }
+}
+
+/// Fades `outgoing` out and `incoming` in over `duration`, then stops `outgoing` so that the
+/// queue moves on to `incoming`, which is already playing.
+async fn fade(outgoing: TrackHandle, incoming: TrackHandle, duration: Duration) {
+    // Start the incoming track silently.
+    if let Err(e) = incoming.set_volume(0.0).and_then(|_| incoming.play()) {
+        warn!("Failed to start the incoming track for crossfade: {}", e);
+        return;
+    }
+
+    let interval = duration / FADE_STEPS;
+    for step in 1..=FADE_STEPS {
+        tokio::time::sleep(interval).await;
+        let (outgoing_volume, incoming_volume) = fade_volumes(step, FADE_STEPS);
+
+        // The outgoing track may have been skipped or ended early; keep fading in regardless.
+        if let Err(e) = outgoing.set_volume(outgoing_volume) {
+            debug!("Outgoing track unavailable during crossfade: {}", e);
+        }
+        if let Err(e) = incoming.set_volume(incoming_volume) {
+            warn!("Incoming track unavailable during crossfade: {}", e);
+            return;
+        }
+    }
+
+    // Hand over to the incoming track.
+    let _ = outgoing.stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preload_time() {
+        assert_eq!(preload_time(None), None);
+        assert_eq!(
+            preload_time(Some(Duration::from_secs(200))),
+            Some(Duration::from_secs(200) - PRELOAD_LEAD)
+        );
+        // Short tracks start buffering the next one right away.
+        assert_eq!(
+            preload_time(Some(Duration::from_secs(5))),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_crossfade_start() {
+        let crossfade = Duration::from_secs(5);
+        assert_eq!(crossfade_start(None, crossfade), None);
+        assert_eq!(
+            crossfade_start(Some(Duration::from_secs(200)), Duration::ZERO),
+            None
+        );
+        assert_eq!(
+            crossfade_start(Some(Duration::from_secs(9)), crossfade),
+            None
+        );
+        assert_eq!(
+            crossfade_start(Some(Duration::from_secs(200)), crossfade),
+            Some(Duration::from_secs(195))
+        );
+    }
+
+    #[test]
+    fn test_fade_volumes() {
+        assert_eq!(fade_volumes(0, 4), (1.0, 0.0));
+        assert_eq!(fade_volumes(2, 4), (0.5, 0.5));
+        assert_eq!(fade_volumes(4, 4), (0.0, 1.0));
+        // Steps past the end stay at the final volumes.
+        assert_eq!(fade_volumes(6, 4), (0.0, 1.0));
+    }
+}
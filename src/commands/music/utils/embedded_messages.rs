@@ -8,12 +8,15 @@ use songbird::tracks::{PlayMode, TrackQueue};
 use std::{sync::Arc, time::Duration};
 
 use crate::commands::music::{
-    audio_sources::track_metadata::TrackMetadata,
+    audio_sources::track_metadata::{AUTOPLAY_REQUESTER, TrackMetadata},
     utils::{button_controls, format_duration, music_manager::MusicError},
 };
+use crate::utils::database::{AnnouncementSettings, MusicChannelSetting, QueueLimits};
 
 use super::{
+    autoplay_manager::AutoplayMode,
     button_controls::{ButtonData, RepeatState},
+    content_filter::{ContentFilter, FilterList},
     music_manager::MusicManager,
 };
 
@@ -25,6 +28,8 @@ pub struct PlayerMessageData {
     pub show_queue: bool,
     /// The current repeat state.
     pub repeat_state: RepeatState,
+    /// Whether upcoming tracks are interleaved by requester.
+    pub fair_queue: bool,
 }
 
 /// Generates a simple text-based progress bar string.
@@ -63,6 +68,19 @@ fn parse_metadata(metadata: &TrackMetadata) -> (String, String, String) {
     (title, url, duration_str)
 }
 
+/// Returns who requested a track, as shown in the player: "Autoplay" for tracks queued by
+/// autoplay, the requester's name otherwise.
+fn requester_label(metadata: &TrackMetadata) -> String {
+    if metadata.is_autoplay() {
+        format!("🔄 {}", AUTOPLAY_REQUESTER)
+    } else {
+        metadata
+            .requested_by
+            .clone()
+            .unwrap_or_else(|| "Unknown".to_string())
+    }
+}
+
 /// Asynchronously generates the main music player `CreateReply` (embed + components).
 /// Fetches current state (queue, track info, repeat state, etc.) from `MusicManager`
 /// and constructs the appropriate embed and buttons.
@@ -110,6 +128,8 @@ pub async fn music_player_message(guild_id: GuildId) -> Result<CreateReply, Musi
                 let (title, url, _) = parse_metadata(&metadata);
                 // Start description with 'Now Playing'.
                 let mut description = format!("**Now Playing:** [{}]({})\n", title, url);
+                // Show who requested the track.
+                description.push_str(&format!("Requested by: {}\n", requester_label(&metadata)));
 
                 // Calculate and format progress bar and time.
                 let duration = metadata.duration.unwrap_or(Duration::from_secs(0));
@@ -156,7 +176,11 @@ pub async fn music_player_message(guild_id: GuildId) -> Result<CreateReply, Musi
                 // Add detailed upcoming tracks if toggled and queue has items.
                 if show_queue && queue.len() > 1 {
                     // Again, > 1 rather than !is_empty() to ignore head of queue
-                    description.push_str("\n**Upcoming Tracks:**\n");
+                    if data.fair_queue {
+                        description.push_str("\n**Upcoming Tracks (fair queue):**\n");
+                    } else {
+                        description.push_str("\n**Upcoming Tracks:**\n");
+                    }
                     // Iterate through the next 10 tracks in the queue.
                     for (index, track) in queue
                         .current_queue()
@@ -174,10 +198,12 @@ pub async fn music_player_message(guild_id: GuildId) -> Result<CreateReply, Musi
                             metadata.title,
                             metadata.url.as_deref().unwrap_or("#")
                         ));
-                            // Add duration if available.
+                        // Add duration if available.
                         if let Some(dur) = metadata.duration {
                             description.push_str(&format!(" `{}`", format_duration(dur)));
                         }
+                        // Add the requester.
+                        description.push_str(&format!(" - {}", requester_label(&metadata)));
                         description.push('\n');
                     }
                     // Indicate if there are more tracks beyond the displayed 10.
@@ -230,8 +256,47 @@ pub async fn music_player_message(guild_id: GuildId) -> Result<CreateReply, Musi
 
 // --- Simple Ephemeral Messages ---
 
-/// Creates an ephemeral reply indicating the autoplay status.
-pub fn autoplay_status(enabled: bool) -> CreateReply {
+/// Creates an ephemeral reply describing the track currently playing.
+pub fn now_playing(metadata: &TrackMetadata, position: Duration) -> CreateReply {
+    let (title, url, duration_str) = parse_metadata(metadata);
+
+    // Show progress if the duration is known.
+    let progress = match metadata.duration {
+        Some(duration) => format!(
+            "{} `{}/{}`",
+            format_progress_bar(position, duration),
+            format_duration(position),
+            duration_str
+        ),
+        None => format!("`{}`", format_duration(position)),
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title("🎵 Now Playing")
+        .description(format!(
+            "[{}]({})\n{}\nRequested by: {}",
+            title,
+            url,
+            progress,
+            requester_label(metadata)
+        ))
+        .color(0x00ff00); // Green color
+
+    // Add thumbnail if available
+    if let Some(thumbnail) = &metadata.thumbnail {
+        embed = embed.thumbnail(thumbnail);
+    }
+
+    CreateReply::default().embed(embed).ephemeral(true)
+}
+
+/// Creates an ephemeral reply indicating the autoplay status, the selected mode,
+/// and the seed playlist, if any.
+pub fn autoplay_status(
+    enabled: bool,
+    mode: AutoplayMode,
+    seed_playlist: Option<&str>,
+) -> CreateReply {
     CreateReply::default()
         .embed(
             CreateEmbed::new()
@@ -241,15 +306,217 @@ pub fn autoplay_status(enabled: bool) -> CreateReply {
                     "⏹️ Autoplay Disabled"
                 })
                 .description(if enabled {
-                    "I will automatically play related songs when the queue is empty"
+                    let mut description = format!(
+                        "I will keep related songs queued based on this session\nMode: **{}**",
+                        mode
+                    );
+                    if let Some(url) = seed_playlist {
+                        description.push_str(&format!("\nSeed playlist: {}", url));
+                    }
+                    description
                 } else {
-                    "I will stop playing when the queue is empty"
+                    "I will stop playing when the queue is empty".to_string()
                 })
                 .color(if enabled { 0x00ff00 } else { 0xff0000 }), // Green/Red
         )
         .ephemeral(true)
 }
 
+/// Creates an ephemeral reply indicating the crossfade duration.
+pub fn crossfade_status(seconds: u64) -> CreateReply {
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title(if seconds > 0 {
+                    "🎚️ Crossfade Enabled"
+                } else {
+                    "🎚️ Crossfade Disabled"
+                })
+                .description(if seconds > 0 {
+                    format!(
+                        "Tracks queued from now on will fade into each other over {} seconds",
+                        seconds
+                    )
+                } else {
+                    "Tracks will play back to back without fading".to_string()
+                })
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
+/// Creates an ephemeral reply describing the guild's music channel settings.
+pub fn music_channel_status(setting: &MusicChannelSetting) -> CreateReply {
+    let description = match setting.channel_id {
+        Some(channel_id) => {
+            let reanchor = if setting.reanchor_after > 0 {
+                format!("re-sent after {} newer messages", setting.reanchor_after)
+            } else {
+                "never re-sent".to_string()
+            };
+            let requests = if setting.request_mode {
+                "Messages typed in the channel are queued as play requests"
+            } else {
+                "Request mode is disabled"
+            };
+            format!(
+                "The player is posted in <#{}> and {}\n{}",
+                channel_id, reanchor, requests
+            )
+        }
+        None => format!(
+            "No music channel is set; the player is posted where music is requested{}",
+            if setting.reanchor_after > 0 {
+                format!(
+                    " and re-sent after {} newer messages",
+                    setting.reanchor_after
+                )
+            } else {
+                String::new()
+            }
+        ),
+    };
+
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title("📺 Music Channel")
+                .description(description)
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
+/// Creates an ephemeral reply listing the guild's queue limits.
+pub fn queue_limits_status(limits: &QueueLimits) -> CreateReply {
+    // Describes a limit, treating 0 as no limit.
+    let limit = |value: u32, unit: &str| {
+        if value > 0 {
+            format!("{} {}", value, unit)
+        } else {
+            "No limit".to_string()
+        }
+    };
+    let max_duration = if limits.max_track_duration > 0 {
+        format_duration(Duration::from_secs(limits.max_track_duration.into()))
+    } else {
+        "No limit".to_string()
+    };
+
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title("🚦 Queue Limits")
+                .field(
+                    "Queue length",
+                    limit(limits.max_queue_length, "tracks"),
+                    true,
+                )
+                .field(
+                    "Tracks per user",
+                    limit(limits.max_tracks_per_user, "tracks"),
+                    true,
+                )
+                .field("Track duration", max_duration, true)
+                .field(
+                    "Playlist import",
+                    limit(limits.max_playlist_size, "tracks"),
+                    true,
+                )
+                .field("Cooldown", limit(limits.cooldown, "seconds"), true)
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
+/// Creates an ephemeral reply listing the guild's now-playing announcement settings.
+pub fn announcement_status(settings: &AnnouncementSettings) -> CreateReply {
+    // Describes a setting that is either on or off.
+    let toggle = |enabled: bool| if enabled { "On" } else { "Off" };
+    let delete_after = if settings.delete_after > 0 {
+        format_duration(Duration::from_secs(settings.delete_after.into()))
+    } else {
+        "Never".to_string()
+    };
+
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title("📣 Now Playing Announcements")
+                .field("Announcements", toggle(settings.announce), true)
+                .field("Deleted after", delete_after, true)
+                .field("Requester DMs", toggle(settings.dm_requester), true)
+                .field("Voice channel status", toggle(settings.voice_status), true)
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
+/// Creates an ephemeral reply indicating whether the fair queue is enabled.
+pub fn fair_queue_status(enabled: bool) -> CreateReply {
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title(if enabled {
+                    "⚖️ Fair Queue Enabled"
+                } else {
+                    "⚖️ Fair Queue Disabled"
+                })
+                .description(if enabled {
+                    "Upcoming tracks take turns by requester"
+                } else {
+                    "Upcoming tracks play in the order they were requested"
+                })
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
+/// Creates an ephemeral reply listing the guild's content filter.
+pub fn content_filter_status(filter: &ContentFilter) -> CreateReply {
+    // Lists the entries of one list, one per line.
+    let entries = |list: FilterList| {
+        let lines: Vec<String> = filter
+            .rules
+            .iter()
+            .filter(|rule| rule.list == list)
+            .map(|rule| format!("{}: `{}`", rule.kind, rule.pattern))
+            .collect();
+        if lines.is_empty() {
+            "No entries".to_string()
+        } else {
+            lines.join("\n")
+        }
+    };
+
+    CreateReply::default()
+        .embed(
+            CreateEmbed::new()
+                .title("🚫 Music Filters")
+                .field(
+                    FilterList::Block.to_string(),
+                    entries(FilterList::Block),
+                    false,
+                )
+                .field(
+                    FilterList::Allow.to_string(),
+                    entries(FilterList::Allow),
+                    false,
+                )
+                .field(
+                    "Age-restricted tracks",
+                    if filter.block_explicit {
+                        "Blocked"
+                    } else {
+                        "Allowed"
+                    },
+                    false,
+                )
+                .color(0x00ff00), // Green color
+        )
+        .ephemeral(true)
+}
+
 /// Creates a generic ephemeral success reply.
 pub fn generic_success(title: &str, description: &str) -> CreateReply {
     CreateReply::default()
@@ -274,6 +541,31 @@ pub fn generic_error(description: &str) -> CreateReply {
         .ephemeral(true)
 }
 
+/// Creates a message reporting a track that couldn't be played, and the replacement queued
+/// in its place, if any.
+pub fn track_failed(
+    metadata: &TrackMetadata,
+    reason: &str,
+    replacement: Option<&TrackMetadata>,
+) -> CreateReply {
+    let (title, url, _) = parse_metadata(metadata);
+    let mut description = format!("Couldn't play [{}]({}): {}", title, url, reason);
+    match replacement {
+        Some(replacement) => {
+            let (title, url, _) = parse_metadata(replacement);
+            description.push_str(&format!("\nQueued [{}]({}) instead.", title, url));
+        }
+        None => description.push_str("\nSkipping to the next track."),
+    }
+
+    CreateReply::default().embed(
+        CreateEmbed::new()
+            .title("⚠️ Track Unavailable")
+            .description(description)
+            .color(0xffa500),
+    )
+}
+
 /// Creates an ephemeral error reply indicating the queue is empty.
 pub fn queue_is_empty() -> CreateReply {
     CreateReply::default().embed(
@@ -1,22 +1,48 @@
 //! Contains Songbird event handlers specific to the music functionality,
-//! primarily for handling track endings and triggering autoplay.
+//! primarily for handling track endings and errors, keeping the queue topped up with autoplay
+//! tracks, keeping the player message in sync with the playing track, and announcing tracks as
+//! they start.
 
 use std::sync::Arc;
 
-use crate::commands::music::audio_sources::{track_metadata::TrackMetadata, youtube::YoutubeApi};
+use crate::commands::music::audio_sources::{
+    AudioApi,
+    related_songs::recommendation::RecommendationEngine,
+    track_metadata::{AUTOPLAY_REQUESTER, TrackMetadata},
+    youtube::YoutubeApi,
+};
 use poise::serenity_prelude as serenity;
-use serenity::async_trait;
-use tracing::{error, info, warn};
+use rand::seq::IndexedRandom;
+use serenity::{CreateMessage, async_trait};
+use songbird::tracks::PlayMode;
+use tracing::{debug, error, info, warn};
 
 use super::{
+    announcements,
     autoplay_manager::{self},
+    content_filter, embedded_messages,
     music_manager::MusicManager,
 };
 
+/// The number of most recent session tracks used to seed autoplay.
+const AUTOPLAY_SEED_TRACKS: usize = 5;
+
+/// The number of tracks sampled from the guild's seed playlist, if any, to seed autoplay.
+const AUTOPLAY_PLAYLIST_SEEDS: usize = 2;
+
+/// The number of tracks autoplay keeps in the queue (including the one playing),
+/// so the next track is ready before the current one ends.
+const AUTOPLAY_QUEUE_AHEAD: usize = 3;
+
+/// The number of search results considered when looking for a replacement for a failed track.
+const RETRY_SEARCH_RESULTS: usize = 3;
+
 /// A Songbird event handler that triggers when a track finishes playing.
-/// It checks if the queue is empty and if autoplay is enabled, then attempts
-/// to find and queue a related song.
+/// It records the track as played and, if autoplay is enabled, tops up the queue
+/// with related songs.
 pub struct SongEndNotifier {
+    /// The HTTP client used to report tracks autoplay queues that fail to play.
+    pub http: Arc<serenity::Http>,
     /// The ID of the guild where the event occurred.
     pub guild_id: serenity::GuildId,
     /// A handle to the Songbird voice call.
@@ -31,13 +57,32 @@ impl songbird::EventHandler for SongEndNotifier {
     /// This implementation specifically listens for `EventContext::Track` events.
     async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
         // Check if the event is a track ending event.
-        if let songbird::EventContext::Track([(_track_state, _track_handle)]) = ctx {
-            // Get the queue for the guild.
-            if let Some(queue) = MusicManager::get_queue(&self.guild_id).await {
-                // If the queue is now empty, handle the empty queue logic (check autoplay).
-                if queue.is_empty() {
-                    self.handle_empty_queue().await;
-                }
+        if let songbird::EventContext::Track([(_track_state, track_handle)]) = ctx {
+            // Remember the finished track so autoplay doesn't recommend it again.
+            MusicManager::record_played_track(self.guild_id, self.track_metadata.clone()).await;
+
+            // Keep the queue topped up, ignoring the finished track which may still be
+            // at the head of the queue while the event fires.
+            if let Err(e) =
+                fill_autoplay_queue(&self.http, self.guild_id, &self.call, Some(track_handle)).await
+            {
+                // Log any errors during autoplay attempt.
+                error!("Autoplay failed: {}", e);
+            }
+
+            // Clear the voice channel status once nothing follows the finished track.
+            let (remaining, voice_channel) = {
+                let call = self.call.lock().await;
+                let remaining = call
+                    .queue()
+                    .current_queue()
+                    .iter()
+                    .filter(|queued| queued.uuid() != track_handle.uuid())
+                    .count();
+                (remaining, call.current_channel().map(voice_channel_id))
+            };
+            if remaining == 0 {
+                announcements::clear_voice_status(&self.http, self.guild_id, voice_channel).await;
             }
         }
         // Indicate that this handler doesn't need to handle further events for this track.
@@ -45,52 +90,334 @@ impl songbird::EventHandler for SongEndNotifier {
     }
 }
 
-impl SongEndNotifier {
-    /// Handles the logic when the queue becomes empty after a track ends.
-    /// Checks if autoplay is enabled for the guild and attempts it if so.
-    async fn handle_empty_queue(&self) {
-        // Check if autoplay is enabled via the AutoplayManager.
-        if autoplay_manager::is_autoplay_enabled(self.guild_id).await {
-            // Try to find and queue a related song.
-            if let Err(e) = self.attempt_autoplay().await {
-                // Log any errors during autoplay attempt.
-                error!("Autoplay failed: {}", e);
+/// A Songbird event handler that triggers when a track fails to play (e.g. the video is
+/// region-locked, deleted, or age-gated).
+///
+/// It records the failure, drops the track from the queue, tries once to replace it with
+/// another search result for the same title, and reports what happened in the player channel.
+/// If the failed track was playing, Songbird moves on to the next track by itself.
+pub struct TrackErrorNotifier {
+    /// The HTTP client used to post the error message.
+    pub http: Arc<serenity::Http>,
+    /// The ID of the guild where the event occurred.
+    pub guild_id: serenity::GuildId,
+    /// A handle to the Songbird voice call.
+    pub call: Arc<serenity::prelude::Mutex<songbird::Call>>,
+    /// Metadata of the track that failed.
+    pub track_metadata: TrackMetadata,
+}
+
+#[async_trait]
+impl songbird::EventHandler for TrackErrorNotifier {
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        let songbird::EventContext::Track([(track_state, track_handle)]) = ctx else {
+            return None;
+        };
+
+        let reason = match &track_state.playing {
+            PlayMode::Errored(e) => e.to_string(),
+            _ => "Unknown error".to_string(),
+        };
+        warn!(
+            "Track '{}' failed to play in guild {}: {}",
+            self.track_metadata.title, self.guild_id, reason
+        );
+
+        // Remember the failure, so neither retries nor autoplay pick the track again.
+        let retry = match &self.track_metadata.url {
+            Some(url) => MusicManager::record_failed_track(self.guild_id, url).await,
+            None => false,
+        };
+
+        // Drop the track from the queue, unless it is at the head where Songbird removes it
+        // itself and starts the next track. Remember where it was to put a replacement there.
+        let position = {
+            let call = self.call.lock().await;
+            call.queue().modify_queue(|queue| {
+                let position = queue
+                    .iter()
+                    .position(|queued| queued.uuid() == track_handle.uuid());
+                if let Some(index) = position.filter(|&index| index > 0) {
+                    queue.remove(index);
+                }
+                position.unwrap_or(0).max(1)
+            })
+        };
+
+        // Try once to replace the track with another upload of the same song.
+        let replacement = if retry {
+            self.queue_replacement(position).await
+        } else {
+            None
+        };
+
+        // Let the channel know what happened.
+        if let Some(channel_id) = MusicManager::get_channel_id(self.guild_id).await {
+            let reply = embedded_messages::track_failed(
+                &self.track_metadata,
+                &reason,
+                replacement.as_ref(),
+            );
+            let message = CreateMessage::new().embeds(reply.embeds);
+            if let Err(e) = channel_id.send_message(&self.http, message).await {
+                warn!("Failed to report track error: {}", e);
             }
         }
+
+        None
     }
+}
 
-    /// Attempts to find and queue a related song based on the finished track's metadata.
-    /// Uses `YoutubeApi::get_related_songs` and adds the first valid result to the queue.
-    async fn attempt_autoplay(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Check if the finished track has a URL (needed to find related songs).
-        if let Some(url) = &self.track_metadata.url {
-            // Fetch related songs using the YouTube API implementation.
-            let related_songs = YoutubeApi::get_related_songs(url).await?;
-
-            // Iterate through the fetched related songs.
-            for metadata in related_songs {
-                // Check if the related song has a URL.
-                if let Some(song_url) = &metadata.url {
-                    // Basic validation: ensure it looks like a YouTube URL.
-                    if !YoutubeApi::is_youtube_url(song_url) {
-                        warn!("Skipping non-YouTube URL from related songs: {}", song_url);
-                        continue;
-                    }
-
-                    // Add the valid related song's metadata to the queue.
-                    let mut call = self.call.lock().await;
-                    MusicManager::add_to_queue(&mut call, metadata.clone()).await;
-                    info!(
-                        "Added related song '{}' to queue for guild {}",
-                        metadata.title, self.guild_id
-                    );
-
-                    // Only add the first valid related song found.
-                    break;
-                }
+impl TrackErrorNotifier {
+    /// Searches for another playable upload of the failed track and queues it at `position`.
+    /// Returns the metadata of the replacement, if one was queued.
+    async fn queue_replacement(&self, position: usize) -> Option<TrackMetadata> {
+        let failed = &self.track_metadata;
+        let results = match YoutubeApi::search(&failed.title, RETRY_SEARCH_RESULTS) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Failed to search for a replacement track: {}", e);
+                return None;
+            }
+        };
+
+        // Pick the first result that hasn't failed and is allowed by the content filter.
+        let filter = content_filter::get_content_filter(self.guild_id).await;
+        let failed_urls = MusicManager::get_failed_tracks(self.guild_id).await;
+        let mut replacement = results.into_iter().find(|track| {
+            track
+                .url
+                .as_ref()
+                .is_some_and(|url| !failed_urls.contains(url))
+                && filter.check(track).is_ok()
+        })?;
+        replacement.requested_by = failed.requested_by.clone();
+        replacement.requester_id = failed.requester_id;
+        replacement.autoplay = failed.autoplay;
+
+        if let Some(url) = &replacement.url {
+            MusicManager::record_retry_track(self.guild_id, url).await;
+        }
+        info!(
+            "Replacing failed track '{}' with '{}' in guild {}",
+            failed.title, replacement.title, self.guild_id
+        );
+
+        // Queue the replacement and move it to where the failed track was.
+        MusicManager::add_to_queue(&self.http, self.guild_id, &self.call, replacement.clone())
+            .await;
+        let call = self.call.lock().await;
+        call.queue().modify_queue(|queue| {
+            if position < queue.len()
+                && let Some(queued) = queue.pop_back()
+            {
+                queue.insert(position, queued);
             }
+        });
+
+        Some(replacement)
+    }
+}
+
+/// A Songbird event handler that triggers when a track starts playing.
+/// It announces the track as configured for the guild: in the player channel, to the
+/// requester, and in the voice channel status.
+///
+/// The handler removes itself after the first announcement, so resuming the track after a
+/// pause doesn't announce it again.
+pub struct TrackStartNotifier {
+    /// The HTTP client used to post the announcements.
+    pub http: Arc<serenity::Http>,
+    /// The ID of the guild where the event occurred.
+    pub guild_id: serenity::GuildId,
+    /// A handle to the Songbird voice call.
+    pub call: Arc<serenity::prelude::Mutex<songbird::Call>>,
+    /// Metadata of the track that started.
+    pub track_metadata: TrackMetadata,
+}
+
+#[async_trait]
+impl songbird::EventHandler for TrackStartNotifier {
+    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        let voice_channel = self
+            .call
+            .lock()
+            .await
+            .current_channel()
+            .map(voice_channel_id);
+        announcements::announce_track(
+            &self.http,
+            self.guild_id,
+            voice_channel,
+            &self.track_metadata,
+        )
+        .await;
+        Some(songbird::Event::Cancel)
+    }
+}
+
+/// Converts the ID of the channel a Songbird call is connected to into a Serenity `ChannelId`.
+fn voice_channel_id(channel_id: songbird::id::ChannelId) -> serenity::ChannelId {
+    serenity::ChannelId::new(channel_id.0.get())
+}
+
+/// A Songbird event handler that asks for the player message to be re-rendered when a track
+/// changes state (starts, pauses, ends, or fails).
+pub struct PlayerUpdateNotifier {
+    /// The ID of the guild whose player should be updated.
+    pub guild_id: serenity::GuildId,
+}
+
+#[async_trait]
+impl songbird::EventHandler for PlayerUpdateNotifier {
+    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        MusicManager::request_player_update(self.guild_id).await;
+        None
+    }
+}
+
+/// Tops up a guild's queue with autoplay tracks until it holds `AUTOPLAY_QUEUE_AHEAD` tracks.
+///
+/// Autoplay is seeded from the last `AUTOPLAY_SEED_TRACKS` tracks of the session (played and
+/// queued), plus a few tracks sampled from the guild's seed playlist if one is set.
+/// `finished` is the handle of a track that has just ended and should no longer count as queued.
+///
+/// Does nothing if autoplay is disabled or already running for the guild.
+/// Returns the number of tracks added to the queue.
+pub async fn fill_autoplay_queue(
+    http: &Arc<serenity::Http>,
+    guild_id: serenity::GuildId,
+    call: &Arc<serenity::prelude::Mutex<songbird::Call>>,
+    finished: Option<&songbird::tracks::TrackHandle>,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    // Check if autoplay is enabled via the AutoplayManager.
+    if !autoplay_manager::is_autoplay_enabled(guild_id).await {
+        return Ok(0);
+    }
+
+    // Avoid queueing the same recommendations twice when several events race.
+    if !MusicManager::begin_autoplay(guild_id).await {
+        debug!("Autoplay already in progress for guild {}", guild_id);
+        return Ok(0);
+    }
+    let result = queue_recommendations(http, guild_id, call, finished).await;
+    MusicManager::finish_autoplay(guild_id).await;
+
+    result
+}
+
+/// Fetches recommendations for the current session and queues as many as needed.
+async fn queue_recommendations(
+    http: &Arc<serenity::Http>,
+    guild_id: serenity::GuildId,
+    call: &Arc<serenity::prelude::Mutex<songbird::Call>>,
+    finished: Option<&songbird::tracks::TrackHandle>,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    // Collect the metadata of the tracks still in the queue.
+    let queued: Vec<TrackMetadata> = {
+        let call = call.lock().await;
+        call.queue()
+            .current_queue()
+            .iter()
+            .filter(|handle| finished.is_none_or(|f| f.uuid() != handle.uuid()))
+            .map(|handle| handle.data::<TrackMetadata>().as_ref().clone())
+            .collect()
+    };
+
+    // Nothing to do if the queue is already long enough.
+    let needed = AUTOPLAY_QUEUE_AHEAD.saturating_sub(queued.len());
+    if needed == 0 {
+        return Ok(0);
+    }
+
+    // The session is everything played so far followed by everything still queued.
+    let recent = MusicManager::get_recent_tracks(guild_id).await;
+    let session: Vec<TrackMetadata> = recent.iter().chain(&queued).cloned().collect();
+
+    // Seed from the end of the session, plus a sample of the seed playlist.
+    let mut seeds: Vec<TrackMetadata> = session
+        .iter()
+        .skip(session.len().saturating_sub(AUTOPLAY_SEED_TRACKS))
+        .cloned()
+        .collect();
+    let playlist = autoplay_manager::get_seed_playlist_tracks(guild_id).await;
+    seeds.extend(
+        playlist
+            .sample(&mut rand::rng(), AUTOPLAY_PLAYLIST_SEEDS)
+            .cloned(),
+    );
+
+    if seeds.is_empty() {
+        debug!("No autoplay seeds available for guild {}", guild_id);
+        return Ok(0);
+    }
+
+    // Build the engine for the guild's preferred mode and rank related songs,
+    // excluding everything already played or queued this session.
+    let mode = autoplay_manager::get_autoplay_mode(guild_id).await;
+    let engine = RecommendationEngine::for_mode(mode);
+    let mut recommendations = engine.recommend(&seeds, &session).await?;
+
+    // Leave out anything that has already failed to play this session.
+    let failed = MusicManager::get_failed_tracks(guild_id).await;
+    recommendations.retain(|track| track.url.as_ref().is_none_or(|url| !failed.contains(url)));
+
+    // Take the best recommendations the guild's content filter allows, looking up the details
+    // it needs only until enough are found.
+    let filter = content_filter::get_content_filter(guild_id).await;
+    let mut accepted = Vec::new();
+    for mut track in recommendations {
+        if accepted.len() >= needed {
+            break;
         }
+        if filter.needs_details() {
+            resolve_details(&mut track).await;
+        }
+        match filter.check_autoplay(&track) {
+            Ok(()) => accepted.push(track),
+            Err(reason) => debug!("Autoplay skipped '{}': {}", track.title, reason),
+        }
+    }
 
-        Ok(())
+    if accepted.is_empty() {
+        warn!("No suitable related songs found for guild {}", guild_id);
+        return Ok(0);
+    }
+
+    // Queue the accepted recommendations, labelled as autoplayed.
+    let mut added = 0;
+    for mut metadata in accepted {
+        metadata.requested_by = Some(AUTOPLAY_REQUESTER.to_string());
+        metadata.autoplay = true;
+        let title = metadata.title.clone();
+        MusicManager::add_to_queue(http, guild_id, call, metadata).await;
+        info!(
+            "Added related song '{}' to queue for guild {} ({} mode)",
+            title, guild_id, mode
+        );
+        added += 1;
+    }
+
+    Ok(added)
+}
+
+/// Fills in the uploader and age restriction of a related song from its full YouTube metadata.
+/// The details stay unknown if the track isn't on YouTube or the lookup fails.
+async fn resolve_details(track: &mut TrackMetadata) {
+    let Some(url) = track
+        .url
+        .clone()
+        .filter(|url| YoutubeApi::is_youtube_url(url))
+    else {
+        return;
+    };
+    match YoutubeApi.get_metadata(&url, String::new()).await {
+        Ok(resolved) => {
+            if let Some(full) = resolved.into_iter().next() {
+                track.uploader = full.uploader.or(track.uploader.take());
+                track.age_limit = full.age_limit;
+            }
+        }
+        Err(e) => warn!("Failed to look up details of related song {}: {}", url, e),
     }
 }
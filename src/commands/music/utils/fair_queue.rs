@@ -0,0 +1,147 @@
+//! Optional fair queue ordering.
+//! When enabled for a guild, upcoming tracks are interleaved by requester (round-robin), so a
+//! single user queueing an album can't hold up everyone else's requests.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, LazyLock};
+
+use serenity::model::id::{GuildId, UserId};
+use songbird::tracks::Queued;
+use tokio::sync::Mutex;
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::utils::database;
+
+/// In-memory cache mapping GuildId to whether fair queue ordering is enabled.
+static FAIR_QUEUE_SETTINGS: LazyLock<Mutex<HashMap<GuildId, bool>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Enables or disables fair queue ordering for a guild, updating both the cache and the database.
+pub async fn set_fair_queue(guild_id: GuildId, enabled: bool) {
+    // Update the cache.
+    FAIR_QUEUE_SETTINGS.lock().await.insert(guild_id, enabled);
+
+    // Attempt to save the setting to the database, logging any errors.
+    if let Err(e) = database::set_fair_queue_setting(guild_id, enabled) {
+        eprintln!("Failed to save fair queue setting to database: {}", e);
+    }
+}
+
+/// Checks if fair queue ordering is enabled for a guild, checking the cache before the database.
+pub async fn is_fair_queue_enabled(guild_id: GuildId) -> bool {
+    let mut settings = FAIR_QUEUE_SETTINGS.lock().await;
+    *settings
+        .entry(guild_id)
+        .or_insert_with(|| database::get_fair_queue_setting(guild_id))
+}
+
+/// Re-orders the upcoming tracks of a queue round-robin by requester.
+///
+/// The head of the queue is the track currently playing and is never moved. Autoplay tracks
+/// keep their order after all user requests.
+pub fn reorder(queue: &mut VecDeque<Queued>) {
+    let Some(current) = queue.pop_front() else {
+        return;
+    };
+
+    // Describe each upcoming track by its requester and whether it was autoplayed.
+    let mut upcoming: Vec<Option<Queued>> = queue.drain(..).map(Some).collect();
+    let entries: Vec<(Option<UserId>, bool)> = upcoming
+        .iter()
+        .flatten()
+        .map(|queued| {
+            let metadata: Arc<TrackMetadata> = queued.data();
+            (metadata.requester_id, metadata.is_autoplay())
+        })
+        .collect();
+    let current_requester = current.data::<TrackMetadata>().requester_id;
+
+    // Put the tracks back in fair order behind the current one.
+    queue.push_back(current);
+    for index in fair_order(Some(current_requester), &entries) {
+        if let Some(queued) = upcoming[index].take() {
+            queue.push_back(queued);
+        }
+    }
+}
+
+/// Computes a round-robin order for upcoming tracks, given each track's requester and whether
+/// it was autoplayed. Returns the indices of the tracks in their new order.
+///
+/// Requesters take turns in the order of their first upcoming track, except that the requester
+/// of the current track goes last. Each requester's own tracks keep their relative order, and
+/// autoplay tracks follow all user requests.
+fn fair_order<K: PartialEq + Copy>(current: Option<K>, entries: &[(K, bool)]) -> Vec<usize> {
+    // Group user requests by requester, in order of first appearance.
+    let mut groups: Vec<(K, VecDeque<usize>)> = Vec::new();
+    let mut autoplay = Vec::new();
+    for (index, &(requester, is_autoplay)) in entries.iter().enumerate() {
+        if is_autoplay {
+            autoplay.push(index);
+        } else if let Some((_, group)) = groups.iter_mut().find(|(key, _)| *key == requester) {
+            group.push_back(index);
+        } else {
+            groups.push((requester, VecDeque::from([index])));
+        }
+    }
+
+    // The requester of the track playing now has just had their turn.
+    if let Some(position) = groups.iter().position(|(key, _)| Some(*key) == current) {
+        let group = groups.remove(position);
+        groups.push(group);
+    }
+
+    // Take one track from each requester in turn.
+    let mut order = Vec::with_capacity(entries.len());
+    while order.len() < entries.len() - autoplay.len() {
+        for (_, group) in groups.iter_mut() {
+            if let Some(index) = group.pop_front() {
+                order.push(index);
+            }
+        }
+    }
+    order.extend(autoplay);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fair_order_interleaves_requesters() {
+        // A queued an album, then B and C requested a track each.
+        let entries = [
+            ('a', false),
+            ('a', false),
+            ('a', false),
+            ('b', false),
+            ('c', false),
+        ];
+        assert_eq!(fair_order(None, &entries), vec![0, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_fair_order_current_requester_goes_last() {
+        let entries = [('a', false), ('b', false), ('a', false)];
+        assert_eq!(fair_order(Some('a'), &entries), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_fair_order_is_stable() {
+        // An already fair queue keeps its order.
+        let entries = [('b', false), ('c', false), ('a', false), ('b', false)];
+        assert_eq!(fair_order(Some('a'), &entries), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_fair_order_keeps_autoplay_last() {
+        let entries = [('x', true), ('a', false), ('x', true), ('b', false)];
+        assert_eq!(fair_order(None, &entries), vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn test_fair_order_empty() {
+        assert!(fair_order::<char>(None, &[]).is_empty());
+    }
+}
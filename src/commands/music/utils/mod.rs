@@ -2,18 +2,36 @@
 
 use std::time::Duration;
 
+/// Now-playing announcements, requester messages, and voice channel status.
+pub(crate) mod announcements;
 /// Manages the autoplay state for guilds.
 pub(crate) mod autoplay_manager;
 /// Defines the button components used for music controls.
 pub(crate) mod button_controls;
 /// Handles interactions with music control components (buttons).
 pub(crate) mod component_handlers;
+/// Per-guild blocklists, allowlists, and explicit content filtering.
+pub(crate) mod content_filter;
+/// Pre-buffering and crossfading between queued tracks.
+pub(crate) mod crossfade;
 /// Provides functions to create standardized embed messages for music commands.
 pub(crate) mod embedded_messages;
 /// Contains event handlers specific to the music feature (e.g., Songbird events).
 pub(crate) mod event_handlers;
+/// Optional round-robin ordering of the queue by requester.
+pub(crate) mod fair_queue;
+/// The guild's dedicated music channel, player re-anchoring, and request mode.
+pub(crate) mod music_channel;
 /// The core manager for music playback, handling queues, voice connections, and Songbird integration.
 pub(crate) mod music_manager;
+/// Playback controls shared by the player buttons and the equivalent commands.
+pub(crate) mod player_controls;
+/// Exporting the queue to JSON, M3U, or text files, and reading them back.
+pub(crate) mod queue_io;
+/// Per-guild queue limits and request cooldowns.
+pub(crate) mod queue_limits;
+/// Saving the queue when the bot leaves, and resuming it later.
+pub(crate) mod saved_sessions;
 
 /// Formats a `std::time::Duration` into a human-readable string.
 ///
@@ -0,0 +1,194 @@
+//! Per-guild dedicated music channel.
+//! Stores which channel the player message lives in, whether the player is re-sent once chat
+//! buries it, and whether plain messages typed in the channel are treated as play requests.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{GetMessages, Message};
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::utils::database::{self, MusicChannelSetting};
+
+use super::music_manager::MusicManager;
+
+/// The largest number of messages that can be configured before the player is re-sent.
+/// Kept below Discord's limit of 100 messages per fetch.
+pub const MAX_REANCHOR_AFTER: u32 = 50;
+
+/// How long error replies to play requests stay in the request channel.
+const REQUEST_ERROR_LIFETIME: Duration = Duration::from_secs(10);
+
+/// In-memory cache mapping GuildId to its music channel settings.
+static MUSIC_CHANNELS: LazyLock<Mutex<HashMap<GuildId, MusicChannelSetting>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Sets the music channel settings for a guild, updating both the cache and the database.
+pub async fn set_music_channel(guild_id: GuildId, setting: MusicChannelSetting) {
+    // Update the cache.
+    MUSIC_CHANNELS.lock().await.insert(guild_id, setting);
+
+    // Attempt to save the settings to the database, logging any errors.
+    if let Err(e) = database::set_music_channel_setting(guild_id, &setting) {
+        eprintln!("Failed to save music channel settings to database: {}", e);
+    }
+}
+
+/// Gets the music channel settings for a guild, checking the cache before the database.
+pub async fn get_music_channel(guild_id: GuildId) -> MusicChannelSetting {
+    let mut settings = MUSIC_CHANNELS.lock().await;
+    *settings
+        .entry(guild_id)
+        .or_insert_with(|| database::get_music_channel_setting(guild_id))
+}
+
+/// Returns the channel the player message should be posted in: the guild's music channel if
+/// one is configured, otherwise `fallback` (usually the channel the command was used in).
+pub async fn player_channel(guild_id: GuildId, fallback: ChannelId) -> ChannelId {
+    get_music_channel(guild_id)
+        .await
+        .channel_id
+        .unwrap_or(fallback)
+}
+
+/// Returns `true` if `channel_id` is the guild's music channel and request mode is enabled.
+pub async fn is_request_channel(guild_id: GuildId, channel_id: ChannelId) -> bool {
+    let setting = get_music_channel(guild_id).await;
+    setting.request_mode && setting.channel_id == Some(channel_id)
+}
+
+/// Returns `true` if the player should be re-sent, given the number of messages posted below it
+/// and the configured threshold (0 disables re-anchoring).
+fn should_reanchor(newer_messages: usize, reanchor_after: u32) -> bool {
+    reanchor_after > 0 && newer_messages > reanchor_after as usize
+}
+
+/// Checks whether the player message has been buried under more messages than the guild allows.
+///
+/// Only fetches the channel history when re-anchoring is enabled. Fetch errors are treated as
+/// "not buried", so the player is simply edited in place.
+pub async fn is_buried(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> bool {
+    let reanchor_after = get_music_channel(guild_id)
+        .await
+        .reanchor_after
+        .min(MAX_REANCHOR_AFTER);
+    if reanchor_after == 0 {
+        return false;
+    }
+
+    // Fetch one more message than the threshold, which is enough to know it was exceeded.
+    let request = GetMessages::new()
+        .after(message_id)
+        .limit(reanchor_after as u8 + 1);
+    match channel_id.messages(http, request).await {
+        Ok(messages) => should_reanchor(messages.len(), reanchor_after),
+        Err(e) => {
+            debug!(
+                "Failed to fetch messages after the player in channel {}: {}",
+                channel_id, e
+            );
+            false
+        }
+    }
+}
+
+/// Treats a plain message typed in a request channel as a play request.
+///
+/// Messages from bots, empty messages, prefix commands, and messages outside of a request
+/// channel are ignored. The request message is deleted afterwards to keep the channel tidy,
+/// and errors are reported with a reply that deletes itself after a few seconds.
+pub async fn handle_request_message(ctx: &Context, message: &Message) {
+    let Some(guild_id) = message.guild_id else {
+        return;
+    };
+    let query = message.content.trim();
+    if message.author.bot || query.is_empty() || crate::has_command_prefix(&message.content) {
+        return;
+    }
+    if !is_request_channel(guild_id, message.channel_id).await {
+        return;
+    }
+
+    debug!(
+        "Treating message in request channel {} as a play request: {}",
+        message.channel_id, query
+    );
+
+    // Queue the request. The player message itself confirms successful requests.
+    if let Err(e) = MusicManager::process_play_request(
+        ctx,
+        guild_id,
+        message.channel_id,
+        &message.author,
+        query.to_string(),
+    )
+    .await
+    {
+        report_request_error(ctx, message, &e.to_string()).await;
+    }
+
+    // Remove the request to keep the channel focused on the player.
+    if let Err(e) = message.delete(&ctx.http).await {
+        warn!(
+            "Failed to delete play request message in channel {}: {}",
+            message.channel_id, e
+        );
+    }
+}
+
+/// Replies to a failed play request, then deletes the reply after a short delay.
+async fn report_request_error(ctx: &Context, message: &Message, error: &str) {
+    let reply = match message
+        .reply(&ctx.http, format!("❌ {}: {}", message.author.name, error))
+        .await
+    {
+        Ok(reply) => reply,
+        Err(e) => {
+            warn!("Failed to report play request error: {}", e);
+            return;
+        }
+    };
+
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(REQUEST_ERROR_LIFETIME).await;
+        let _ = reply.delete(&http).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_reanchor_disabled() {
+        assert!(!should_reanchor(0, 0));
+        assert!(!should_reanchor(100, 0));
+    }
+
+    #[test]
+    fn test_should_reanchor_threshold() {
+        assert!(!should_reanchor(0, 5));
+        assert!(!should_reanchor(5, 5));
+        assert!(should_reanchor(6, 5));
+    }
+
+    #[test]
+    fn test_request_messages_skip_prefix_commands() {
+        assert!(crate::has_command_prefix("~skip"));
+        assert!(crate::has_command_prefix("rusty skip"));
+        assert!(crate::has_command_prefix("Rusty, pause"));
+        assert!(!crate::has_command_prefix("never gonna give you up"));
+        assert!(!crate::has_command_prefix("songs like rusty cage"));
+    }
+}
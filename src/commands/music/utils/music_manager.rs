@@ -1,30 +1,44 @@
 //! The core manager for music playback functionality.
 //! Handles voice connections, queues, track metadata, state management (repeat, queue view),
 //! message updates, and interaction with the Songbird library.
-//! Provides a globally accessible, thread-safe instance.
+//! State is kept per guild in a concurrent map, so guilds never contend for a shared lock.
 
 use ::serenity::all::{CreateMessage, EditMessage, MessageId, User, UserId};
+use dashmap::DashMap;
+use futures::FutureExt;
 use poise::{CreateReply, serenity_prelude as serenity};
 use rand::seq::SliceRandom;
 use serenity::client::Context;
 use serenity::model::id::{ChannelId, GuildId};
 use serenity::prelude::Mutex as SerenityMutex;
 use songbird::input::YoutubeDl;
-use songbird::tracks::{Track, TrackHandle, TrackQueue};
-use songbird::{Call, Songbird};
-use std::collections::HashMap;
+use songbird::tracks::{PlayMode, Track, TrackHandle, TrackQueue};
+use songbird::{Call, Event, Songbird, TrackEvent};
+use std::collections::{HashSet, VecDeque};
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 
 use crate::Error;
 use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
 use crate::commands::music::audio_sources::youtube::YoutubeApi;
 use crate::commands::music::audio_sources::{AUDIO_APIS, AudioSource};
+use crate::utils::database::QueueLimits;
 
 use super::button_controls::RepeatState;
+use super::content_filter;
+use super::crossfade::{self, CrossfadeNotifier};
 use super::embedded_messages::{self, PlayerMessageData};
+use super::event_handlers::{
+    self, PlayerUpdateNotifier, SongEndNotifier, TrackErrorNotifier, TrackStartNotifier,
+};
+use super::fair_queue;
+use super::format_duration;
+use super::music_channel;
+use super::queue_limits::{self, LimitedTracks};
+use super::saved_sessions;
 
 use crate::HTTP_CLIENT;
 use tracing::{debug, error, info, warn};
@@ -67,54 +81,155 @@ pub enum MusicError {
     /// Expected a queue to exist, but none was found for the guild.
     #[error("No queue")]
     NoQueue,
+
+    /// The operation requires a track to be playing, but none is.
+    #[error("No track is currently playing")]
+    NothingPlaying,
+
+    /// The queue already holds as many tracks as the guild allows.
+    #[error("The queue is full (limit of {0} tracks)")]
+    QueueFull(usize),
+
+    /// The user already has as many tracks queued as the guild allows.
+    #[error("You already have {0} tracks in the queue, the most each user can queue")]
+    UserTrackLimit(usize),
+
+    /// The requested tracks are all longer than the guild allows.
+    #[error("Tracks longer than {} can't be queued", format_duration(*.0))]
+    TrackTooLong(Duration),
+
+    /// The user has to wait before making another request.
+    #[error("Please wait {} more seconds before requesting again", .0.as_secs().max(1))]
+    RequestCooldown(Duration),
+
+    /// The requested track is refused by the guild's content filter.
+    #[error("This track can't be played on this server: {0}")]
+    ContentBlocked(String),
+
+    /// A content filter rule could not be created.
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
+    /// The bot is playing for other listeners in another voice channel.
+    #[error("I'm already playing music for others in <#{0}>. Join that channel to listen along")]
+    SessionInProgress(ChannelId),
+
+    /// The user has to be in the bot's voice channel to control playback.
+    #[error("You need to be in <#{0}> to control the music")]
+    NotInBotChannel(ChannelId),
+
+    /// There is no recent session to resume for the guild.
+    #[error("There is no saved session to resume")]
+    NoSavedSession,
 }
 
 /// A specialized `Result` type for music operations.
 pub type MusicResult<T> = Result<T, MusicError>;
 
-/// Central struct managing the state of music playback across guilds.
-#[derive(Default)]
-pub struct MusicManager {
-    /// Maps GuildId to the corresponding Songbird `TrackQueue`.
-    queues: HashMap<GuildId, TrackQueue>,
-    /// Maps GuildId to the `ChannelId` where the player message is located.
-    channel_ids: HashMap<GuildId, ChannelId>,
-    /// Maps GuildId to the `MessageId` of the active player message.
-    message_ids: HashMap<GuildId, MessageId>,
-    /// Maps GuildId to whether the detailed queue view is shown (true) or hidden (false).
-    show_queue: HashMap<GuildId, bool>,
-    /// Maps GuildId to the handle of the task responsible for periodically updating the player message.
-    update_tasks: HashMap<GuildId, JoinHandle<()>>,
-    /// Maps GuildId to the current repeat state (`Disabled` or `Track`).
-    repeat_state: HashMap<GuildId, RepeatState>,
+/// The result of a successful play request.
+#[derive(Debug)]
+pub struct PlayOutcome {
+    /// The first track that was queued.
+    pub first_track: TrackMetadata,
+    /// The number of tracks that were queued.
+    pub number_of_tracks: usize,
+    /// Explanations for any requested tracks that were left out due to the guild's filters
+    /// and limits.
+    pub notes: Vec<String>,
+}
+
+/// The music playback state of a single guild.
+pub struct GuildMusicState {
+    /// The Songbird `TrackQueue` of the guild's call.
+    queue: Option<TrackQueue>,
+    /// The `ChannelId` where the player message is located.
+    channel_id: Option<ChannelId>,
+    /// The `MessageId` of the active player message.
+    message_id: Option<MessageId>,
+    /// Whether the detailed queue view is shown (true) or hidden (false).
+    show_queue: bool,
+    /// The handle of the task responsible for periodically updating the player message.
+    update_task: Option<JoinHandle<()>>,
+    /// The current repeat state (`Disabled` or `Track`).
+    repeat_state: RepeatState,
+    /// The most recently played tracks, newest last.
+    recent_tracks: VecDeque<TrackMetadata>,
+    /// Whether autoplay is currently fetching recommendations.
+    autoplay_in_progress: bool,
+    /// Wakes the update task when the player message should be re-rendered.
+    player_update: Arc<Notify>,
+    /// URLs of tracks that failed to play this session.
+    failed_tracks: HashSet<String>,
+    /// URLs of tracks queued to replace a failed track, which aren't retried again.
+    retry_tracks: HashSet<String>,
 }
 
+impl Default for GuildMusicState {
+    fn default() -> Self {
+        Self {
+            queue: None,
+            channel_id: None,
+            message_id: None,
+            show_queue: true,
+            update_task: None,
+            repeat_state: RepeatState::Disabled,
+            recent_tracks: VecDeque::new(),
+            autoplay_in_progress: false,
+            player_update: Arc::new(Notify::new()),
+            failed_tracks: HashSet::new(),
+            retry_tracks: HashSet::new(),
+        }
+    }
+}
+
+/// Namespace for the music playback operations shared by the music commands.
+pub struct MusicManager;
+
+/// The number of recently played tracks remembered per guild, used to keep autoplay from looping.
+const RECENT_TRACKS_LIMIT: usize = 50;
+
+/// How often the player message is re-rendered to advance the progress bar while a track plays.
+const PROGRESS_TICK: Duration = Duration::from_secs(15);
 
-/// Global, thread-safe instance of the `MusicManager`.
-/// Lazily initialized and wrapped in `Arc<Mutex>` for safe concurrent access.
-static MUSIC_MANAGER: LazyLock<Arc<Mutex<MusicManager>>> =
-    LazyLock::new(|| Arc::new(Mutex::new(MusicManager::default())));
+/// How often the player message is re-rendered while nothing is playing (e.g. paused).
+const IDLE_TICK: Duration = Duration::from_secs(60);
 
-/// Provides read-only access to the global `MusicManager` within a closure.
-pub async fn with_manager<F, R>(f: F) -> R
+/// How long the update task waits after being woken, so bursts of events cause a single edit.
+const UPDATE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// The first delay after Discord rate-limits a player message update. Doubles on each
+/// consecutive rate limit, up to `MAX_RATE_LIMIT_BACKOFF`.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// The longest delay between player message updates while being rate-limited.
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Music playback state, keyed by guild.
+/// Each guild's entry is locked independently, so guilds never contend with each other.
+static GUILD_STATES: LazyLock<DashMap<GuildId, GuildMusicState>> = LazyLock::new(DashMap::new);
+
+/// Provides read-only access to a guild's music state within a closure.
+/// Guilds without any state are seen with the default state.
+///
+/// The closure is synchronous, so the state can never be held across an `.await` (such as
+/// locking a Songbird `Call`). It must not access the state of another guild.
+pub fn with_guild_state<F, R>(guild_id: GuildId, f: F) -> R
 where
-    F: FnOnce(&MusicManager) -> R,
+    F: FnOnce(&GuildMusicState) -> R,
 {
-    // Lock the mutex to get access.
-    let manager = MUSIC_MANAGER.lock().await;
-    // Execute the closure with the manager reference.
-    f(&manager)
+    match GUILD_STATES.get(&guild_id) {
+        Some(state) => f(&state),
+        None => f(&GuildMusicState::default()),
+    }
 }
 
-/// Provides mutable access to the global `MusicManager` within a closure.
-pub async fn with_manager_mut<F, R>(f: F) -> R
+/// Provides mutable access to a guild's music state within a closure, creating the default
+/// state if the guild has none yet. The same restrictions as `with_guild_state` apply.
+pub fn with_guild_state_mut<F, R>(guild_id: GuildId, f: F) -> R
 where
-    F: FnOnce(&mut MusicManager) -> R,
+    F: FnOnce(&mut GuildMusicState) -> R,
 {
-    // Lock the mutex to get mutable access.
-    let mut manager = MUSIC_MANAGER.lock().await;
-    // Execute the closure with the mutable manager reference.
-    f(&mut manager)
+    f(&mut GUILD_STATES.entry(guild_id).or_default())
 }
 
 impl MusicManager {
@@ -137,70 +252,138 @@ impl MusicManager {
 
     /// Retrieves a clone of the `TrackQueue` for a given guild, if it exists in the manager.
     pub async fn get_queue(guild_id: &GuildId) -> Option<TrackQueue> {
-        with_manager(|m| m.queues.get(guild_id).cloned()).await
+        with_guild_state(*guild_id, |state| state.queue.clone())
     }
 
     /// Stores or updates the `TrackQueue` for a given guild in the manager.
     pub async fn store_queue(guild_id: GuildId, queue: TrackQueue) {
-        with_manager_mut(|m| m.queues.insert(guild_id, queue)).await;
+        with_guild_state_mut(guild_id, |state| state.queue = Some(queue));
     }
 
     /// Retrieves the `MessageId` of the player message for a given guild, if stored.
     pub async fn get_message_id(guild_id: GuildId) -> Option<MessageId> {
-        with_manager(|m| m.message_ids.get(&guild_id).copied()).await
+        with_guild_state(guild_id, |state| state.message_id)
     }
 
     /// Stores or updates the `MessageId` of the player message for a given guild.
     pub async fn store_message_id(guild_id: GuildId, message_id: MessageId) {
-        with_manager_mut(|m| m.message_ids.insert(guild_id, message_id)).await;
+        with_guild_state_mut(guild_id, |state| state.message_id = Some(message_id));
     }
 
     /// Retrieves the `ChannelId` where the player message is located for a given guild, if stored.
     pub async fn get_channel_id(guild_id: GuildId) -> Option<ChannelId> {
-        with_manager(|m| m.channel_ids.get(&guild_id).copied()).await
+        with_guild_state(guild_id, |state| state.channel_id)
     }
 
     /// Stores or updates the `ChannelId` of the player message for a given guild.
     pub async fn store_channel_id(guild_id: GuildId, channel_id: ChannelId) {
-        with_manager_mut(|m| m.channel_ids.insert(guild_id, channel_id)).await;
+        with_guild_state_mut(guild_id, |state| state.channel_id = Some(channel_id));
     }
 
     /// Stores the `JoinHandle` for the player message update task for a given guild.
     pub async fn store_update_task(guild_id: GuildId, task: JoinHandle<()>) {
-        with_manager_mut(|m| m.update_tasks.insert(guild_id, task)).await;
+        with_guild_state_mut(guild_id, |state| state.update_task = Some(task));
     }
 
     /// Removes and returns the `JoinHandle` for the update task for a given guild, if it exists.
     pub async fn drop_update_task(guild_id: &GuildId) -> Option<JoinHandle<()>> {
-        with_manager_mut(|m| m.update_tasks.remove(guild_id)).await
+        GUILD_STATES
+            .get_mut(guild_id)
+            .and_then(|mut state| state.update_task.take())
     }
 
     /// Retrieves the current `RepeatState` for a given guild, defaulting to `Disabled` if not set.
     pub async fn get_repeat_state(guild_id: GuildId) -> RepeatState {
-        with_manager(|m| {
-            m.repeat_state
-                .get(&guild_id)
-                .cloned()
-                .unwrap_or(RepeatState::Disabled)
-        })
-        .await
+        with_guild_state(guild_id, |state| state.repeat_state)
     }
 
     /// Sets the `RepeatState` for a given guild.
     pub async fn set_repeat_state(guild_id: GuildId, state: RepeatState) {
-        with_manager_mut(|m| m.repeat_state.insert(guild_id, state)).await;
+        with_guild_state_mut(guild_id, |guild_state| guild_state.repeat_state = state);
     }
 
-    /// Removes all stored state (queue, message ID, channel ID, repeat state) for a given guild.
+    /// Resets the session state (queue, message ID, channel ID, repeat state, recent tracks)
+    /// for a given guild and stops its update task. The queue view preference is left alone.
     /// Typically called when the bot leaves a voice channel or stops playback.
     pub async fn drop_all(guild_id: &GuildId) {
-        with_manager_mut(|m| {
-            m.queues.remove(guild_id);
-            m.message_ids.remove(guild_id);
-            m.channel_ids.remove(guild_id);
-            m.repeat_state.remove(guild_id);
+        Self::stop_update_task(*guild_id).await;
+        with_guild_state_mut(*guild_id, |state| {
+            state.queue = None;
+            state.message_id = None;
+            state.channel_id = None;
+            state.repeat_state = RepeatState::Disabled;
+            state.recent_tracks.clear();
+            state.autoplay_in_progress = false;
+            state.failed_tracks.clear();
+            state.retry_tracks.clear();
+        });
+    }
+
+    /// Records a track as played for a given guild, forgetting the oldest track once
+    /// `RECENT_TRACKS_LIMIT` is reached.
+    pub async fn record_played_track(guild_id: GuildId, metadata: TrackMetadata) {
+        with_guild_state_mut(guild_id, |state| {
+            if state.recent_tracks.len() >= RECENT_TRACKS_LIMIT {
+                state.recent_tracks.pop_front();
+            }
+            state.recent_tracks.push_back(metadata);
+        });
+    }
+
+    /// Retrieves the recently played tracks for a given guild, oldest first.
+    pub async fn get_recent_tracks(guild_id: GuildId) -> Vec<TrackMetadata> {
+        with_guild_state(guild_id, |state| {
+            state.recent_tracks.iter().cloned().collect()
+        })
+    }
+
+    /// Marks autoplay as in progress for a given guild.
+    /// Returns `false` if autoplay was already in progress, in which case the caller should back off.
+    pub async fn begin_autoplay(guild_id: GuildId) -> bool {
+        with_guild_state_mut(guild_id, |state| {
+            !std::mem::replace(&mut state.autoplay_in_progress, true)
+        })
+    }
+
+    /// Marks autoplay as no longer in progress for a given guild.
+    pub async fn finish_autoplay(guild_id: GuildId) {
+        with_guild_state_mut(guild_id, |state| state.autoplay_in_progress = false);
+    }
+
+    /// Records that a track failed to play for a given guild.
+    /// Returns `false` if the track was itself a replacement for a failed track, in which case
+    /// it shouldn't be retried again.
+    pub async fn record_failed_track(guild_id: GuildId, url: &str) -> bool {
+        with_guild_state_mut(guild_id, |state| {
+            state.failed_tracks.insert(url.to_string());
+            !state.retry_tracks.contains(url)
+        })
+    }
+
+    /// Retrieves the URLs of the tracks that failed to play in a given guild this session.
+    pub async fn get_failed_tracks(guild_id: GuildId) -> HashSet<String> {
+        with_guild_state(guild_id, |state| state.failed_tracks.clone())
+    }
+
+    /// Records that a track was queued to replace a failed track for a given guild.
+    pub async fn record_retry_track(guild_id: GuildId, url: &str) {
+        with_guild_state_mut(guild_id, |state| state.retry_tracks.insert(url.to_string()));
+    }
+
+    /// Asks the update task to re-render the player message soon. Requests made in quick
+    /// succession are coalesced into a single edit.
+    /// Returns `false` if no update task is running for the guild.
+    pub async fn request_player_update(guild_id: GuildId) -> bool {
+        with_guild_state(guild_id, |state| {
+            let running = state
+                .update_task
+                .as_ref()
+                .is_some_and(|task| !task.is_finished());
+            if running {
+                state.player_update.notify_one();
+            }
+            running
         })
-        .await;
     }
 
     /// Convenience method to get the `TrackHandle` of the currently playing track for a guild.
@@ -236,24 +419,18 @@ impl MusicManager {
 
     /// Fetches the necessary data required to build the player message embed.
     pub async fn get_player_message_data(guild_id: &GuildId) -> PlayerMessageData {
-        // Access the manager to get queue, show_queue, and repeat_state.
-        let (queue, show_queue, repeat_state) = with_manager(|m| {
-            (
-                m.queues.get(guild_id).cloned(),
-                m.show_queue.get(guild_id).copied().unwrap_or(true),
-                m.repeat_state
-                    .get(guild_id)
-                    .copied()
-                    .unwrap_or(RepeatState::Disabled),
-            )
-        })
-        .await;
+        // Read the guild's queue, show_queue, and repeat_state.
+        let (queue, show_queue, repeat_state) = with_guild_state(*guild_id, |state| {
+            (state.queue.clone(), state.show_queue, state.repeat_state)
+        });
+        let fair_queue = fair_queue::is_fair_queue_enabled(*guild_id).await;
 
         // Construct the data struct.
         PlayerMessageData {
             queue,
             show_queue,
             repeat_state,
+            fair_queue,
         }
     }
 
@@ -261,7 +438,8 @@ impl MusicManager {
     ///
     /// Checks if a message ID is stored for the guild. If yes, attempts to edit it.
     /// If editing fails (e.g., message deleted) or no ID is stored, sends a new message
-    /// and stores the new ID.
+    /// and stores the new ID. If the guild re-anchors its player and the message has been
+    /// buried under chat, it is deleted and sent again at the bottom of the channel.
     pub async fn send_or_update_message(
         http: Arc<serenity::Http>,
         guild_id: GuildId,
@@ -273,6 +451,16 @@ impl MusicManager {
 
         // Check if an existing message ID is stored.
         let message_id = match Self::get_message_id(guild_id).await {
+            Some(message_id)
+                if music_channel::is_buried(&http, guild_id, channel_id, message_id).await =>
+            {
+                // Re-send the player so it stays visible below the chat.
+                debug!("Player message is buried under chat, re-sending it.");
+                if let Err(e) = channel_id.delete_message(&http, message_id).await {
+                    warn!("Failed to delete buried player message: {}", e);
+                }
+                Self::send_and_store_new_message(http, guild_id, channel_id, reply).await?
+            }
             Some(message_id) => {
                 // Attempt to edit the existing message.
                 debug!("Found existing message ID, attempting to update.");
@@ -284,8 +472,12 @@ impl MusicManager {
                     .edit_message(http.clone(), message_id, message)
                     .await;
 
-                // If editing failed, send a new message instead.
-                if result.is_err() {
+                // If editing failed, send a new message instead, unless Discord is just asking
+                // us to slow down.
+                if let Err(e) = result {
+                    if is_rate_limited(&e) {
+                        return Err(e.into());
+                    }
                     debug!("Failed to update existing message, sending new one.");
                     Self::send_and_store_new_message(http, guild_id, channel_id, reply).await?
                 } else {
@@ -303,56 +495,54 @@ impl MusicManager {
         Ok(message_id)
     }
 
-    /// Toggles the `show_queue` state for a given guild.
-    pub async fn toggle_queue_view(guild_id: GuildId) {
-        with_manager_mut(|m| {
-        // Get mutable access to the show_queue map, insert default (true) if not present.
-            let current_state = m.show_queue.entry(guild_id).or_insert(true);
+    /// Toggles the `show_queue` state for a given guild and returns the new state.
+    pub async fn toggle_queue_view(guild_id: GuildId) -> bool {
+        let show_queue = with_guild_state_mut(guild_id, |state| {
             // Flip the boolean value.
-            *current_state = !*current_state;
-            info!(
-                "Toggled queue view for guild {}: {}",
-                guild_id, *current_state
-            );
-        })
-        .await;
+            state.show_queue = !state.show_queue;
+            state.show_queue
+        });
+        info!("Toggled queue view for guild {}: {}", guild_id, show_queue);
+        show_queue
     }
 
     /// Shuffles the track queue for a given guild, keeping the currently playing track (if any) at the front.
     pub async fn shuffle_queue(guild_id: &GuildId) {
-        with_manager_mut(|m| {
-            if let Some(queue) = m.queues.get(guild_id) {
-                if queue.len() <= 1 {
-                    return;
-                }
-
-                let mut rng = rand::rng();
+        let Some(queue) = Self::get_queue(guild_id).await else {
+            return;
+        };
+        if queue.len() <= 1 {
+            return;
+        }
 
-                queue.modify_queue(|q| {
-                    // Keep the first track (currently playing)
-                    let current = q.pop_front();
+        let mut rng = rand::rng();
 
-                    // Convert remaining queue to Vec for shuffling
-                    let mut remaining: Vec<_> = q.drain(..).collect();
-                    remaining.shuffle(&mut rng);
+        queue.modify_queue(|q| {
+            // Keep the first track (currently playing)
+            let current = q.pop_front();
 
-                    // Put back the current track
-                    if let Some(current_track) = current {
-                        q.push_back(current_track);
-                    }
+            // Convert remaining queue to Vec for shuffling
+            let mut remaining: Vec<_> = q.drain(..).collect();
+            remaining.shuffle(&mut rng);
 
-                    // Add shuffled tracks back
-                    q.extend(remaining);
-                });
+            // Put back the current track
+            if let Some(current_track) = current {
+                q.push_back(current_track);
             }
-        })
-        .await;
+
+            // Add shuffled tracks back
+            q.extend(remaining);
+        });
     }
 
-    /// Spawns and manages a background task that periodically updates the player message.
+    /// Spawns and manages a background task that keeps the player message up to date.
     /// Stops any existing update task for the guild before starting a new one.
-    /// The task runs every 5 seconds and stops automatically if the queue becomes empty
-    /// or the bot leaves the voice channel.
+    ///
+    /// The message is re-rendered when woken by `request_player_update` (track events, queue
+    /// changes, commands), and otherwise every `PROGRESS_TICK` while a track plays or every
+    /// `IDLE_TICK` while paused. Wake-ups are debounced so bursts cause a single edit, and
+    /// updates back off while Discord is rate-limiting them. The task stops automatically if the
+    /// queue becomes empty or the bot leaves the voice channel.
     async fn start_update_task(
         ctx: &Context,
         http: Arc<serenity::Http>,
@@ -363,30 +553,58 @@ impl MusicManager {
 
         // Clone context for use in the async task.
         let ctx = Arc::new(ctx.clone());
+        let notify = with_guild_state_mut(guild_id, |state| state.player_update.clone());
 
         info!("Starting update task for guild {}", guild_id);
 
         // Spawn the asynchronous task.
         let task = tokio::spawn(async move {
-            // Loop indefinitely until a break condition is met.
+            let mut backoff = None;
+
+            // Loop until the session ends.
             loop {
                 debug!("Attempting to send/update message for guild {}", guild_id);
-
-                // Create a new scope for the lock to ensure it's released after use
                 let message_result =
                     Self::send_or_update_message(http.clone(), guild_id, channel_id).await;
 
-                // Log success or warning on failure.
-                match message_result {
-                    Ok(_) => info!("Successfully updated player message for guild {}", guild_id),
+                // Log the outcome, backing off if Discord is rate-limiting us.
+                backoff = match message_result {
+                    Ok(_) => {
+                        debug!("Successfully updated player message for guild {}", guild_id);
+                        None
+                    }
+                    Err(e) if is_rate_limited(e.as_ref()) => Some(next_backoff(backoff)),
                     Err(e) => {
                         warn!(
                             "Error updating music player message for guild {}: {}",
                             guild_id, e
                         );
+                        None
                     }
+                };
+                if let Some(delay) = backoff {
+                    warn!(
+                        "Player message updates rate-limited for guild {}, backing off for {:?}",
+                        guild_id, delay
+                    );
+                    tokio::time::sleep(delay).await;
                 }
 
+                // Wait for something to change, or for the progress bar to need advancing.
+                let tick = progress_tick(Self::is_playing(guild_id).await);
+                tokio::select! {
+                    _ = notify.notified() => {
+                        debug!("Player update requested for guild {}", guild_id);
+                    }
+                    _ = tokio::time::sleep(tick) => {
+                        debug!("Player progress tick for guild {}", guild_id);
+                    }
+                }
+
+                // Let bursts of events settle, then drop the wake-ups they caused.
+                tokio::time::sleep(UPDATE_DEBOUNCE).await;
+                let _ = notify.notified().now_or_never();
+
                 // Check if the task should continue running.
                 let should_continue = match Self::get_call(&ctx, guild_id).await {
                     // Continue if a call handler exists and its queue is not empty.
@@ -401,11 +619,6 @@ impl MusicManager {
                     }
                 };
 
-                debug!(
-                    "Update task continue check for guild {}: {}",
-                    guild_id, should_continue
-                );
-
                 // If the loop should not continue, break out.
                 if !should_continue {
                     info!(
@@ -414,10 +627,6 @@ impl MusicManager {
                     );
                     break;
                 }
-
-                debug!("Update task sleeping for 5s for guild {}", guild_id);
-                // Wait before the next update attempt.
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
             }
             info!("Update task loop finished for guild {}", guild_id);
         });
@@ -426,6 +635,55 @@ impl MusicManager {
         Self::store_update_task(guild_id, task).await;
     }
 
+    /// Checks whether the current track of a guild is playing (not paused or stopped).
+    async fn is_playing(guild_id: GuildId) -> bool {
+        let Some(track) = Self::get_current_track(&guild_id).await else {
+            return false;
+        };
+        track
+            .get_info()
+            .await
+            .is_ok_and(|info| info.playing == PlayMode::Play)
+    }
+
+    /// Deletes the current player message, if any, and posts a new one at the bottom of
+    /// `channel_id`, which becomes the channel the player is kept up to date in.
+    pub async fn repost_player(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> MusicResult<()> {
+        // Make sure there is something to show.
+        Self::get_call(ctx, guild_id).await?;
+        Self::get_queue(&guild_id)
+            .await
+            .ok_or(MusicError::NoQueue)?;
+
+        // Stop updating the old message before deleting it.
+        Self::stop_update_task(guild_id).await;
+
+        // Delete the old player message, if any.
+        let old_message = with_guild_state_mut(guild_id, |state| {
+            (state.channel_id.take(), state.message_id.take())
+        });
+        if let (Some(old_channel_id), Some(old_message_id)) = old_message
+            && let Err(e) = ctx
+                .http
+                .delete_message(old_channel_id, old_message_id, None)
+                .await
+        {
+            warn!(
+                "Failed to delete old player message {} in channel {}: {}",
+                old_message_id, old_channel_id, e
+            );
+        }
+
+        // Without a stored message ID, the update task sends a fresh message.
+        Self::start_update_task(ctx, ctx.http.clone(), guild_id, channel_id).await;
+
+        Ok(())
+    }
+
     /// Stops the background player message update task for a guild, if it's running.
     async fn stop_update_task(guild_id: GuildId) {
         // Remove the task handle from the manager.
@@ -484,18 +742,25 @@ impl MusicManager {
 
     /// High-level function to process a `/play` request.
     ///
-    /// 1. Ensures the bot is joined to the user's voice channel.
-    /// 2. Converts the user's input (URL or search query) into `TrackMetadata`.
-    /// 3. Adds the fetched track(s) to the guild's queue.
-    /// 4. Starts the player message update task.
-    /// 5. Returns metadata of the first added track and the total number added.
+    /// 1. Checks the user's request cooldown.
+    /// 2. Ensures the bot is joined to the user's voice channel.
+    /// 3. Converts the user's input (URL or search query) into `TrackMetadata`, leaving out
+    ///    tracks refused by the guild's content filter.
+    /// 4. Trims the tracks to the guild's queue limits.
+    /// 5. Adds the remaining track(s) to the guild's queue.
+    /// 6. Starts the player message update task, in the guild's music channel if one is
+    ///    configured, otherwise in `channel_id`.
+    /// 7. Returns the first added track, the number of tracks added, and notes on any left out.
     pub async fn process_play_request(
         ctx: &Context,
         guild_id: GuildId,
         channel_id: ChannelId,
         user: &User,
         input: String,
-    ) -> Result<(TrackMetadata, usize), MusicError> {
+    ) -> Result<PlayOutcome, MusicError> {
+        let limits = queue_limits::get_queue_limits(guild_id).await;
+        queue_limits::check_cooldown(guild_id, user.id, &limits).await?;
+
         // Get songbird manager
         let manager = songbird::get(ctx)
             .await
@@ -504,50 +769,366 @@ impl MusicManager {
 
         Self::try_join_voice(ctx, &manager, guild_id, user.id).await?;
 
-        let inputs = Self::query_to_youtube_inputs(&input, user.name.clone()).await?;
-        let number_of_tracks = inputs.len();
-        let first_track = inputs[0].clone();
+        let inputs = Self::query_to_youtube_inputs(guild_id, &input, user.name.clone()).await?;
+        if inputs.tracks.is_empty() {
+            return Err(MusicError::AudioSourceError(format!(
+                "No tracks found for: {}",
+                input
+            )));
+        }
+
+        Self::queue_for_user(ctx, guild_id, channel_id, user, &limits, inputs).await
+    }
+
+    /// Queues the entries of an imported queue file for a user.
+    ///
+    /// Each entry (a URL or search query) is resolved like a `/play` request. Entries that can't
+    /// be found or are refused by the guild's content filter are skipped, and the guild's limits
+    /// apply to the import as a whole.
+    pub async fn process_import_request(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user: &User,
+        mut entries: Vec<String>,
+    ) -> MusicResult<PlayOutcome> {
+        let limits = queue_limits::get_queue_limits(guild_id).await;
+        queue_limits::check_cooldown(guild_id, user.id, &limits).await?;
+
+        let manager = Self::get_songbird(ctx).await?;
+        Self::try_join_voice(ctx, &manager, guild_id, user.id).await?;
+
+        // Don't look up more entries than can be imported.
+        let mut notes = Vec::new();
+        let max_playlist = limits.max_playlist_size as usize;
+        if max_playlist > 0 && entries.len() > max_playlist {
+            entries.truncate(max_playlist);
+            notes.push(format!(
+                "Only the first {} entries of the file were imported",
+                max_playlist
+            ));
+        }
 
-        if let Some(handler_lock) = manager.get(guild_id) {
-            let queue = {
-                let mut handler = handler_lock.lock().await;
-                for metadata in inputs.into_iter() {
-                    Self::add_to_queue(&mut handler, metadata).await;
+        // Resolve each entry through the audio sources, skipping the ones that fail.
+        let mut tracks = Vec::new();
+        let mut skipped = 0;
+        for entry in &entries {
+            match Self::query_to_youtube_inputs(guild_id, entry, user.name.clone()).await {
+                Ok(mut resolved) => tracks.append(&mut resolved.tracks),
+                Err(e) => {
+                    debug!("Skipping imported entry '{}': {}", entry, e);
+                    skipped += 1;
                 }
-                handler.queue().clone()
-            };
-            Self::store_queue(guild_id, queue).await;
+            }
+        }
+        if skipped > 0 {
+            notes.push(format!(
+                "Skipped {} entries that couldn't be found or aren't allowed",
+                skipped
+            ));
+        }
+        if tracks.is_empty() {
+            return Err(MusicError::AudioSourceError(
+                "None of the entries in the file could be found".to_string(),
+            ));
         }
 
-        Self::start_update_task(ctx, ctx.http.clone(), guild_id, channel_id).await;
+        let inputs = LimitedTracks { tracks, notes };
+        Self::queue_for_user(ctx, guild_id, channel_id, user, &limits, inputs).await
+    }
+
+    /// Queues tracks requested by a user, within the guild's queue limits.
+    /// The bot must already be in a voice channel.
+    async fn queue_for_user(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user: &User,
+        limits: &QueueLimits,
+        mut inputs: LimitedTracks,
+    ) -> MusicResult<PlayOutcome> {
+        for metadata in inputs.tracks.iter_mut() {
+            metadata.requester_id = Some(user.id);
+        }
+
+        let handler_lock = Self::get_call(ctx, guild_id).await?;
+        let LimitedTracks { tracks, notes } =
+            Self::limit_for_user(&handler_lock, user.id, limits, inputs).await?;
+        let Some(first_track) = tracks.first().cloned() else {
+            return Err(MusicError::AudioSourceError("No tracks found".to_string()));
+        };
+        let number_of_tracks = tracks.len();
+
+        Self::enqueue_tracks(ctx, guild_id, channel_id, &handler_lock, tracks).await;
+        queue_limits::record_request(guild_id, user.id).await;
 
-        Ok((first_track, number_of_tracks))
+        Ok(PlayOutcome {
+            first_track,
+            number_of_tracks,
+            notes,
+        })
+    }
+
+    /// Trims the tracks a user adds to what the guild's queue limits allow, given the tracks
+    /// already queued. The notes of `inputs` are kept ahead of the ones about the limits.
+    async fn limit_for_user(
+        handler_lock: &Arc<SerenityMutex<Call>>,
+        user_id: UserId,
+        limits: &QueueLimits,
+        inputs: LimitedTracks,
+    ) -> MusicResult<LimitedTracks> {
+        // Count the tracks users have queued so far; pending autoplay tracks don't count.
+        let queued = handler_lock.lock().await.queue().current_queue();
+        let (queued, queued_by_user) = queued
+            .iter()
+            .map(|handle| handle.data::<TrackMetadata>())
+            .filter(|metadata| !metadata.is_autoplay())
+            .fold((0, 0), |(all, by_user), metadata| {
+                let is_user = metadata.requester_id == Some(user_id);
+                (all + 1, by_user + usize::from(is_user))
+            });
+        let mut limited =
+            queue_limits::apply_limits(limits, inputs.tracks, queued, queued_by_user)?;
+        let mut notes = inputs.notes;
+        notes.append(&mut limited.notes);
+        Ok(LimitedTracks {
+            tracks: limited.tracks,
+            notes,
+        })
     }
 
-    pub fn play_success_response(metadata: TrackMetadata, number_of_tracks: usize) -> CreateReply {
-        let reply_content = if number_of_tracks > 1 {
-            format!("✅ Added playlist: with {} tracks", number_of_tracks)
+    /// Restores the guild's last saved session: joins the user's voice channel, queues the saved
+    /// tracks, and seeks the first one to where it was interrupted if nothing else is playing.
+    /// The restore counts as a request by the user, so the guild's queue limits and request
+    /// cooldown apply to it.
+    pub async fn restore_session(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        user: &User,
+    ) -> MusicResult<PlayOutcome> {
+        let limits = queue_limits::get_queue_limits(guild_id).await;
+        queue_limits::check_cooldown(guild_id, user.id, &limits).await?;
+        let session = saved_sessions::load_session(guild_id)?;
+        let interrupted = session.tracks[0].clone();
+
+        // The guild's filters may have changed since the session was saved.
+        let filter = content_filter::get_content_filter(guild_id).await;
+        let allowed = filter.apply(session.tracks)?;
+
+        let manager = Self::get_songbird(ctx).await?;
+        Self::try_join_voice(ctx, &manager, guild_id, user.id).await?;
+        let handler_lock = manager.get(guild_id).ok_or(MusicError::NotConnected)?;
+        let LimitedTracks { tracks, notes } =
+            Self::limit_for_user(&handler_lock, user.id, &limits, allowed).await?;
+        let first_track = tracks[0].clone();
+        let number_of_tracks = tracks.len();
+
+        // Only pick up where we left off if the interrupted track is the one that plays next.
+        let was_idle = first_track == interrupted && handler_lock.lock().await.queue().is_empty();
+        let first_handle =
+            Self::enqueue_tracks(ctx, guild_id, channel_id, &handler_lock, tracks).await;
+        if was_idle
+            && !session.position.is_zero()
+            && let Some(handle) = first_handle
+            && let Err(e) = handle.seek_async(session.position).await
+        {
+            warn!("Failed to seek restored track: {}", e);
+        }
+        queue_limits::record_request(guild_id, user.id).await;
+        saved_sessions::forget_session(guild_id);
+
+        Ok(PlayOutcome {
+            first_track,
+            number_of_tracks,
+            notes,
+        })
+    }
+
+    /// Adds tracks to the guild's queue, then tops it up with autoplay tracks and keeps the
+    /// player message up to date. Returns the handle of the first track queued.
+    async fn enqueue_tracks(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        handler_lock: &Arc<SerenityMutex<Call>>,
+        tracks: Vec<TrackMetadata>,
+    ) -> Option<TrackHandle> {
+        let mut first_handle = None;
+        for metadata in tracks.into_iter() {
+            let handle = Self::add_to_queue(&ctx.http, guild_id, handler_lock, metadata).await;
+            first_handle = first_handle.or(handle);
+        }
+        let queue = handler_lock.lock().await.queue().clone();
+        Self::store_queue(guild_id, queue).await;
+
+        // Top up the queue with autoplay tracks in the background, so that the next
+        // track is ready before the queue runs dry.
+        let call = handler_lock.clone();
+        let http = ctx.http.clone();
+        tokio::spawn(async move {
+            if let Err(e) = event_handlers::fill_autoplay_queue(&http, guild_id, &call, None).await
+            {
+                error!("Autoplay failed: {}", e);
+            }
+        });
+
+        let player_channel = music_channel::player_channel(guild_id, channel_id).await;
+        Self::start_update_task(ctx, ctx.http.clone(), guild_id, player_channel).await;
+
+        first_handle
+    }
+
+    /// Creates the reply confirming a play request, mentioning any tracks left out due to the
+    /// guild's filters and limits.
+    pub fn play_success_response(outcome: PlayOutcome) -> CreateReply {
+        let mut reply_content = if outcome.number_of_tracks > 1 {
+            format!(
+                "✅ Added playlist: with {} tracks",
+                outcome.number_of_tracks
+            )
         } else {
-            format!("✅ Added to queue: {}", metadata.title)
+            format!("✅ Added to queue: {}", outcome.first_track.title)
         };
+        for note in &outcome.notes {
+            reply_content.push_str(&format!("\n⚠️ {}", note));
+        }
 
         embedded_messages::generic_success("Music", &reply_content)
     }
 
-    pub async fn add_to_queue(call: &mut Call, metadata: TrackMetadata) {
+    /// Enqueues a track in the guild's call and registers a `SongEndNotifier` on it,
+    /// so that the track is recorded as played and autoplay can kick in once it ends.
+    /// A `TrackErrorNotifier` reports the track in the player channel if it can't be played, and
+    /// a `TrackStartNotifier` announces it when it starts.
+    /// Returns the handle of the queued track, or `None` if the metadata has no URL.
+    ///
+    /// Tracks requested by users are placed ahead of any tracks queued by autoplay. If the guild
+    /// uses a fair queue, user requests are interleaved round-robin by requester.
+    /// When the track's duration is known, the next track is pre-buffered before this one ends,
+    /// and the guild's crossfade (if any) is scheduled.
+    pub async fn add_to_queue(
+        http: &Arc<serenity::Http>,
+        guild_id: GuildId,
+        call_lock: &Arc<SerenityMutex<Call>>,
+        metadata: TrackMetadata,
+    ) -> Option<TrackHandle> {
         let Some(url) = metadata.url.clone() else {
             warn!("Track metadata is missing a URL: {}", metadata.title);
-            return;
+            return None;
         };
         let input = YoutubeDl::new(HTTP_CLIENT.clone(), url);
         let mut track = Track::from(input);
-        track.user_data = Arc::new(metadata);
-        call.enqueue(track).await;
+        track.user_data = Arc::new(metadata.clone());
+
+        let is_autoplay = metadata.is_autoplay();
+        let duration = metadata.duration;
+        let fair = fair_queue::is_fair_queue_enabled(guild_id).await;
+        let handle = {
+            let mut call = call_lock.lock().await;
+            // Use the known duration to pre-buffer the following track.
+            let handle = call.enqueue_with_preload(track, crossfade::preload_time(duration));
+
+            if fair && !is_autoplay {
+                // Interleave user requests by requester, ahead of pending autoplay tracks.
+                call.queue().modify_queue(fair_queue::reorder);
+            } else if !is_autoplay {
+                // Move user requests in front of pending autoplay tracks.
+                call.queue().modify_queue(|q| {
+                    let flags = q
+                        .iter()
+                        .map(|queued| queued.data::<TrackMetadata>().is_autoplay());
+                    if let Some(index) = user_track_position(flags)
+                        && let Some(queued) = q.pop_back()
+                    {
+                        q.insert(index, queued);
+                    }
+                });
+            }
+
+            handle
+        };
+
+        // Notify the autoplay logic when this track ends.
+        let notifier = SongEndNotifier {
+            http: http.clone(),
+            guild_id,
+            call: call_lock.clone(),
+            track_metadata: metadata.clone(),
+        };
+        if let Err(e) = handle.add_event(Event::Track(TrackEvent::End), notifier) {
+            warn!("Failed to register end event for track: {}", e);
+        }
+
+        // Report the track and look for a replacement if it can't be played.
+        let notifier = TrackErrorNotifier {
+            http: http.clone(),
+            guild_id,
+            call: call_lock.clone(),
+            track_metadata: metadata.clone(),
+        };
+        if let Err(e) = handle.add_event(Event::Track(TrackEvent::Error), notifier) {
+            warn!("Failed to register error event for track: {}", e);
+        }
+
+        // Announce the track once it starts.
+        let notifier = TrackStartNotifier {
+            http: http.clone(),
+            guild_id,
+            call: call_lock.clone(),
+            track_metadata: metadata,
+        };
+        if let Err(e) = handle.add_event(Event::Track(TrackEvent::Play), notifier) {
+            warn!("Failed to register start event for track: {}", e);
+        }
+
+        // Re-render the player whenever the track starts, pauses, ends, or fails.
+        for event in [
+            TrackEvent::Play,
+            TrackEvent::Pause,
+            TrackEvent::End,
+            TrackEvent::Error,
+        ] {
+            if let Err(e) = handle.add_event(Event::Track(event), PlayerUpdateNotifier { guild_id })
+            {
+                warn!("Failed to register player update event for track: {}", e);
+            }
+        }
+
+        // Fade into the next track shortly before this one ends.
+        let fade_duration = crossfade::get_crossfade(guild_id).await;
+        if let Some(start) = crossfade::crossfade_start(duration, fade_duration) {
+            let notifier = CrossfadeNotifier {
+                guild_id,
+                call: call_lock.clone(),
+            };
+            if let Err(e) = handle.add_event(Event::Delayed(start), notifier) {
+                warn!("Failed to register crossfade event for track: {}", e);
+            }
+        }
+
+        // Show the new track in the upcoming tracks.
+        Self::request_player_update(guild_id).await;
+
+        Some(handle)
     }
 
     /// If it's a URL, it iterates through `AUDIO_APIS` to find a handler.
     /// If it's not a URL, it performs a YouTube search.
+    /// Tracks refused by the guild's content filter are left out.
     async fn query_to_youtube_inputs(
+        guild_id: GuildId,
+        input: &String,
+        requestor_name: String,
+    ) -> Result<LimitedTracks, MusicError> {
+        let tracks = Self::resolve_query(input, requestor_name).await?;
+        content_filter::get_content_filter(guild_id)
+            .await
+            .apply(tracks)
+    }
+
+    /// Resolves a URL or search query into the metadata of the tracks it refers to.
+    async fn resolve_query(
         input: &String,
         requestor_name: String,
     ) -> Result<Vec<TrackMetadata>, MusicError> {
@@ -571,6 +1152,9 @@ impl MusicManager {
     }
 
     /// Gets the user's current voice channel and joins it using Songbird.
+    ///
+    /// If the bot is already in another channel, it only moves over when nobody else is
+    /// listening there; otherwise the request is refused with `SessionInProgress`.
     async fn try_join_voice(
         ctx: &Context,
         manager: &Songbird,
@@ -578,31 +1162,227 @@ impl MusicManager {
         user_id: UserId,
     ) -> Result<(), MusicError> {
         // Get the user's voice channel ID
-        let channel_id = match Self::get_user_voice_channel(ctx, guild_id, user_id) {
-            Ok(id) => id,
-            Err(_) => return Err(MusicError::UserNotInVoiceChannel),
+        let channel_id = Self::get_user_voice_channel(ctx, guild_id, user_id)?;
+
+        // Decide whether to join, stay, or refuse based on where the bot currently is.
+        let bot_channel = Self::get_bot_voice_channel(ctx, guild_id).await;
+        let listeners = bot_channel
+            .map(|bot_channel| Self::count_listeners(ctx, guild_id, bot_channel, user_id))
+            .unwrap_or(0);
+        if voice_action(bot_channel, channel_id, listeners)? == VoiceAction::Stay {
+            return Ok(());
+        }
+
+        // Join the user's channel. An existing call is moved over with its queue intact.
+        Self::join_voice_channel(manager, guild_id, channel_id).await
+    }
+
+    /// Moves the bot, with its queue, to the user's voice channel.
+    ///
+    /// Refuses with `SessionInProgress` if others are listening in the bot's current channel,
+    /// unless `force` is set. Returns the channel the bot is now in.
+    pub async fn move_to_user(
+        ctx: &Context,
+        guild_id: GuildId,
+        user_id: UserId,
+        force: bool,
+    ) -> MusicResult<ChannelId> {
+        let manager = Self::get_songbird(ctx).await?;
+        let bot_channel = Self::get_bot_voice_channel(ctx, guild_id)
+            .await
+            .ok_or(MusicError::NotConnected)?;
+        let channel_id = Self::get_user_voice_channel(ctx, guild_id, user_id)?;
+
+        let listeners = if force {
+            0
+        } else {
+            Self::count_listeners(ctx, guild_id, bot_channel, user_id)
         };
+        if voice_action(Some(bot_channel), channel_id, listeners)? == VoiceAction::Move {
+            info!(
+                "Moving from voice channel {} to {} in guild {}",
+                bot_channel, channel_id, guild_id
+            );
+            Self::join_voice_channel(&manager, guild_id, channel_id).await?;
+        }
 
-        // Join the voice channel if not already connected, or get the existing call
-        if manager.get(guild_id).is_none() {
-            // Not connected, attempt to join
-            if let Err(err) = manager.join(guild_id, channel_id).await {
-                error!(
-                    "Failed to join voice channel {} for guild {}: {}",
-                    channel_id, guild_id, err
-                );
-                return Err(MusicError::JoinError(err.to_string()));
-            }
+        Ok(channel_id)
+    }
+
+    /// Ensures the bot is connected and the user is in the same voice channel.
+    /// Returns the channel they share.
+    pub async fn ensure_same_channel(
+        ctx: &Context,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> MusicResult<ChannelId> {
+        let bot_channel = Self::get_bot_voice_channel(ctx, guild_id)
+            .await
+            .ok_or(MusicError::NotConnected)?;
+        match Self::get_user_voice_channel(ctx, guild_id, user_id) {
+            Ok(channel_id) if channel_id == bot_channel => Ok(bot_channel),
+            _ => Err(MusicError::NotInBotChannel(bot_channel)),
         }
+    }
+
+    /// Gets the `ChannelId` of the voice channel the bot is connected to in a guild, if any.
+    pub async fn get_bot_voice_channel(ctx: &Context, guild_id: GuildId) -> Option<ChannelId> {
+        let songbird = Self::get_songbird(ctx).await.ok()?;
+        let call = songbird.get(guild_id)?;
+        let channel_id = call.lock().await.current_channel()?;
+        Some(ChannelId::new(channel_id.0.get()))
+    }
+
+    /// Counts the users in a voice channel, excluding bots and `except`.
+    /// Uses the Serenity cache.
+    fn count_listeners(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        except: UserId,
+    ) -> usize {
+        let Some(guild) = ctx.cache.guild(guild_id) else {
+            return 0;
+        };
+        guild
+            .voice_states
+            .values()
+            .filter(|state| state.channel_id == Some(channel_id) && state.user_id != except)
+            .filter(|state| {
+                let member = state
+                    .member
+                    .as_ref()
+                    .or_else(|| guild.members.get(&state.user_id));
+                !member.is_some_and(|member| member.user.bot)
+            })
+            .count()
+    }
 
+    /// Joins (or moves to) a voice channel using Songbird.
+    async fn join_voice_channel(
+        manager: &Songbird,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> MusicResult<()> {
+        if let Err(err) = manager.join(guild_id, channel_id).await {
+            error!(
+                "Failed to join voice channel {} for guild {}: {}",
+                channel_id, guild_id, err
+            );
+            return Err(MusicError::JoinError(err.to_string()));
+        }
         Ok(())
     }
 }
 
+/// Checks whether an error is Discord rate-limiting a request (HTTP 429).
+fn is_rate_limited(error: &(dyn std::error::Error + 'static)) -> bool {
+    match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(e)) => {
+            e.status_code().is_some_and(|status| status.as_u16() == 429)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the delay before the next player message update after being rate-limited,
+/// doubling the previous delay if the last update was rate-limited too.
+fn next_backoff(previous: Option<Duration>) -> Duration {
+    previous
+        .map(|delay| (delay * 2).min(MAX_RATE_LIMIT_BACKOFF))
+        .unwrap_or(RATE_LIMIT_BACKOFF)
+}
+
+/// Returns how long the update task waits without events before re-rendering the player.
+fn progress_tick(playing: bool) -> Duration {
+    if playing { PROGRESS_TICK } else { IDLE_TICK }
+}
+
+/// What to do with the bot's voice connection when a user in `user_channel` asks for music.
+#[derive(Debug, PartialEq, Eq)]
+enum VoiceAction {
+    /// The bot isn't connected yet and joins the user's channel.
+    Join,
+    /// The bot is already in the user's channel.
+    Stay,
+    /// The bot leaves a channel nobody else is listening in for the user's channel.
+    Move,
+}
+
+/// Decides how to handle a music request given the bot's current voice channel, the user's
+/// voice channel, and how many others are listening in the bot's channel.
+///
+/// Fails with `SessionInProgress` rather than pulling the bot away from other listeners.
+fn voice_action(
+    bot_channel: Option<ChannelId>,
+    user_channel: ChannelId,
+    other_listeners: usize,
+) -> MusicResult<VoiceAction> {
+    match bot_channel {
+        None => Ok(VoiceAction::Join),
+        Some(bot_channel) if bot_channel == user_channel => Ok(VoiceAction::Stay),
+        Some(_) if other_listeners == 0 => Ok(VoiceAction::Move),
+        Some(bot_channel) => Err(MusicError::SessionInProgress(bot_channel)),
+    }
+}
+
+/// Finds where a newly enqueued user track (the last entry) should be moved so that it plays
+/// before any pending autoplay tracks, given whether each queue entry was autoplayed.
+///
+/// The head of the queue is the track currently playing and is never displaced.
+/// Returns `None` if the track is already in the right place.
+fn user_track_position(autoplay_flags: impl Iterator<Item = bool>) -> Option<usize> {
+    let flags: Vec<bool> = autoplay_flags.collect();
+    // The new track is the last entry; look for the first autoplay track before it.
+    let last = flags.len().checked_sub(1)?;
+    (1..last).find(|&index| flags[index])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    #[test]
+    fn test_next_backoff() {
+        assert_eq!(next_backoff(None), RATE_LIMIT_BACKOFF);
+        assert_eq!(
+            next_backoff(Some(RATE_LIMIT_BACKOFF)),
+            RATE_LIMIT_BACKOFF * 2
+        );
+        // The delay is capped.
+        assert_eq!(
+            next_backoff(Some(MAX_RATE_LIMIT_BACKOFF)),
+            MAX_RATE_LIMIT_BACKOFF
+        );
+    }
+
+    #[test]
+    fn test_progress_tick() {
+        assert_eq!(progress_tick(true), PROGRESS_TICK);
+        assert_eq!(progress_tick(false), IDLE_TICK);
+    }
+
+    #[test]
+    fn test_voice_action() {
+        let here = ChannelId::new(1);
+        let there = ChannelId::new(2);
+
+        assert_eq!(voice_action(None, here, 0).unwrap(), VoiceAction::Join);
+        assert_eq!(
+            voice_action(Some(here), here, 3).unwrap(),
+            VoiceAction::Stay
+        );
+        // An abandoned session is handed over to the new channel.
+        assert_eq!(
+            voice_action(Some(there), here, 0).unwrap(),
+            VoiceAction::Move
+        );
+        // An active session is never hijacked.
+        assert!(matches!(
+            voice_action(Some(there), here, 2),
+            Err(MusicError::SessionInProgress(channel)) if channel == there
+        ));
+    }
 
     // Helper to create a dummy GuildId for testing
     fn test_guild_id() -> GuildId {
@@ -614,14 +1394,12 @@ mod tests {
         let guild_id = test_guild_id();
 
         // Initial state should default to true (or whatever the default is, let's assume true)
-        let initial_state =
-            with_manager(|m| m.show_queue.get(&guild_id).copied().unwrap_or(true)).await;
+        let initial_state = with_guild_state(guild_id, |state| state.show_queue);
         assert!(initial_state, "Initial state should be true");
 
         // Toggle 1: true -> false
         MusicManager::toggle_queue_view(guild_id).await;
-        let state_after_toggle1 =
-            with_manager(|m| m.show_queue.get(&guild_id).copied().unwrap()).await;
+        let state_after_toggle1 = with_guild_state(guild_id, |state| state.show_queue);
         assert!(
             !state_after_toggle1,
             "State after first toggle should be false"
@@ -629,20 +1407,95 @@ mod tests {
 
         // Toggle 2: false -> true
         MusicManager::toggle_queue_view(guild_id).await;
-        let state_after_toggle2 =
-            with_manager(|m| m.show_queue.get(&guild_id).copied().unwrap()).await;
+        let state_after_toggle2 = with_guild_state(guild_id, |state| state.show_queue);
         assert!(
             state_after_toggle2,
             "State after second toggle should be true"
         );
 
         // Clean up state for other tests if necessary (though LazyLock persists)
-        with_manager_mut(|m| m.show_queue.remove(&guild_id)).await;
+        GUILD_STATES.remove(&guild_id);
+    }
+
+    #[test]
+    fn test_user_track_position() {
+        // Empty queue or only the new track: nothing to move.
+        assert_eq!(user_track_position([].into_iter()), None);
+        assert_eq!(user_track_position([false].into_iter()), None);
+        // No pending autoplay tracks.
+        assert_eq!(user_track_position([false, false, false].into_iter()), None);
+        // Currently playing autoplay track is not displaced.
+        assert_eq!(user_track_position([true, false].into_iter()), None);
+        // Move ahead of the first pending autoplay track.
+        assert_eq!(
+            user_track_position([true, false, true, true, false].into_iter()),
+            Some(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_tracks_are_bounded() {
+        let guild_id = GuildId::new(2);
+
+        // Record more tracks than the limit.
+        for i in 0..RECENT_TRACKS_LIMIT + 5 {
+            let metadata = TrackMetadata {
+                title: format!("Track {}", i),
+                ..Default::default()
+            };
+            MusicManager::record_played_track(guild_id, metadata).await;
+        }
+
+        // Only the newest tracks are kept, oldest first.
+        let recent = MusicManager::get_recent_tracks(guild_id).await;
+        assert_eq!(recent.len(), RECENT_TRACKS_LIMIT);
+        assert_eq!(recent[0].title, "Track 5");
+        assert_eq!(
+            recent.last().map(|t| t.title.as_str()),
+            Some(format!("Track {}", RECENT_TRACKS_LIMIT + 4).as_str())
+        );
+
+        // Clean up
+        MusicManager::drop_all(&guild_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_tracks_are_retried_once() {
+        let guild_id = GuildId::new(5);
+
+        // A track that fails for the first time may be retried.
+        assert!(MusicManager::record_failed_track(guild_id, "https://a").await);
+
+        // Its replacement isn't retried if it fails too.
+        MusicManager::record_retry_track(guild_id, "https://b").await;
+        assert!(!MusicManager::record_failed_track(guild_id, "https://b").await);
+
+        let failed = MusicManager::get_failed_tracks(guild_id).await;
+        assert!(failed.contains("https://a") && failed.contains("https://b"));
+
+        // Clean up
+        GUILD_STATES.remove(&guild_id);
+    }
+
+    #[tokio::test]
+    async fn test_autoplay_in_progress() {
+        let guild_id = GuildId::new(4);
+
+        // Only the first caller gets to run autoplay.
+        assert!(MusicManager::begin_autoplay(guild_id).await);
+        assert!(!MusicManager::begin_autoplay(guild_id).await);
+
+        // Once finished, autoplay can run again.
+        MusicManager::finish_autoplay(guild_id).await;
+        assert!(MusicManager::begin_autoplay(guild_id).await);
+
+        // Clean up
+        GUILD_STATES.remove(&guild_id);
     }
 
     #[tokio::test]
     async fn test_repeat_state() {
-        let guild_id = test_guild_id();
+        let guild_id = GuildId::new(3);
 
         // Initial state should be Disabled
         let initial_state = MusicManager::get_repeat_state(guild_id).await;
@@ -659,6 +1512,6 @@ mod tests {
         assert_eq!(state_disabled, RepeatState::Disabled);
 
         // Clean up
-        with_manager_mut(|m| m.repeat_state.remove(&guild_id)).await;
+        GUILD_STATES.remove(&guild_id);
     }
 }
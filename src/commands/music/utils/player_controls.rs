@@ -0,0 +1,247 @@
+//! Playback controls shared by the player buttons and their slash/prefix command equivalents.
+//! Each control checks that the user is in the bot's voice channel, applies the change through
+//! the `MusicManager`, and reports what happened so the caller can respond appropriately.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::Context;
+use serenity::model::id::{GuildId, UserId};
+use songbird::tracks::{PlayMode, TrackHandle};
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+
+use super::announcements;
+use super::button_controls::RepeatState;
+use super::music_manager::{MusicError, MusicManager, MusicResult};
+use super::saved_sessions;
+
+/// Ensures the bot is connected to a voice channel in the guild.
+pub async fn ensure_connected(ctx: &Context, guild_id: GuildId) -> MusicResult<()> {
+    MusicManager::get_call(ctx, guild_id).await.map(|_| ())
+}
+
+/// Ensures the user is in the bot's voice channel, so they can't control playback for
+/// listeners in another channel.
+pub async fn ensure_same_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> MusicResult<()> {
+    MusicManager::ensure_same_channel(ctx, guild_id, user_id)
+        .await
+        .map(|_| ())
+}
+
+/// Returns the handle of the track currently playing, or `NothingPlaying`.
+async fn current_track(guild_id: GuildId) -> MusicResult<TrackHandle> {
+    MusicManager::get_current_track(&guild_id)
+        .await
+        .ok_or(MusicError::NothingPlaying)
+}
+
+/// Converts a Songbird control error into a `MusicError`.
+fn control_error(e: songbird::error::ControlError) -> MusicError {
+    MusicError::AudioSourceError(e.to_string())
+}
+
+/// Pauses or resumes the current track.
+///
+/// If `paused` is `None`, the playback state is toggled.
+/// Returns `true` if playback is paused afterwards.
+async fn set_paused(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    paused: Option<bool>,
+) -> MusicResult<bool> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+    let track = current_track(guild_id).await?;
+
+    // Determine the desired state, toggling the current one if unspecified.
+    let pause = match paused {
+        Some(pause) => pause,
+        None => {
+            let info = track.get_info().await.map_err(control_error)?;
+            info.playing == PlayMode::Play
+        }
+    };
+
+    if pause {
+        track.pause().map_err(control_error)?;
+    } else {
+        track.play().map_err(control_error)?;
+    }
+
+    Ok(pause)
+}
+
+/// Toggles between playing and paused. Returns `true` if playback is paused afterwards.
+pub async fn toggle_pause(ctx: &Context, guild_id: GuildId, user_id: UserId) -> MusicResult<bool> {
+    set_paused(ctx, guild_id, user_id, None).await
+}
+
+/// Pauses the current track.
+pub async fn pause(ctx: &Context, guild_id: GuildId, user_id: UserId) -> MusicResult<()> {
+    set_paused(ctx, guild_id, user_id, Some(true))
+        .await
+        .map(|_| ())
+}
+
+/// Resumes the current track.
+pub async fn resume(ctx: &Context, guild_id: GuildId, user_id: UserId) -> MusicResult<()> {
+    set_paused(ctx, guild_id, user_id, Some(false))
+        .await
+        .map(|_| ())
+}
+
+/// Skips the current track, letting the queue move on to the next one.
+/// Returns the metadata of the skipped track.
+pub async fn skip(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> MusicResult<Arc<TrackMetadata>> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+    let track = current_track(guild_id).await?;
+    let metadata: Arc<TrackMetadata> = track.data();
+
+    // Stop the track. The queue's event handler will start the next song.
+    track.stop().map_err(control_error)?;
+
+    // Short delay to allow Songbird event handlers to potentially update state.
+    sleep(Duration::from_millis(100)).await;
+
+    Ok(metadata)
+}
+
+/// Stops playback, clears the queue, leaves the voice channel, deletes the player message,
+/// and forgets the guild's music state. The queue is saved first, so it can be resumed later.
+pub async fn stop(ctx: &Context, guild_id: GuildId, user_id: UserId) -> MusicResult<()> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+
+    // Save the session so it can be resumed with `/resume_session`.
+    saved_sessions::save_session(guild_id).await;
+
+    // Clear the now playing status of the voice channel before leaving it.
+    let voice_channel = MusicManager::get_bot_voice_channel(ctx, guild_id).await;
+    announcements::clear_voice_status(&ctx.http, guild_id, voice_channel).await;
+
+    // Stop playback and clear the queue.
+    if let Some(queue) = MusicManager::get_queue(&guild_id).await {
+        queue.stop();
+    }
+
+    // Attempt to leave the voice channel.
+    if let Err(e) = MusicManager::leave_channel(ctx, guild_id).await {
+        warn!("Failed to leave voice channel on stop: {}", e);
+        // Don't return error, proceed to delete message if possible
+    }
+
+    // Try to delete the player message.
+    if let (Some(channel_id), Some(message_id)) = (
+        MusicManager::get_channel_id(guild_id).await,
+        MusicManager::get_message_id(guild_id).await,
+    ) {
+        if let Err(e) = ctx.http.delete_message(channel_id, message_id, None).await {
+            warn!(
+                "Failed to delete player message {} in channel {}: {}",
+                message_id, channel_id, e
+            );
+        }
+    } else {
+        warn!(
+            "Could not find channel/message ID to delete for guild {}",
+            guild_id
+        );
+    }
+
+    // Clean up any remaining guild-specific data in the MusicManager.
+    MusicManager::drop_all(&guild_id).await;
+
+    Ok(())
+}
+
+/// Toggles looping of the current track (Disabled <-> Track). Returns the new state.
+pub async fn toggle_repeat(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> MusicResult<RepeatState> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+    let track = current_track(guild_id).await?;
+
+    // Determine the new state and enable/disable looping on the track handle.
+    let new_state = match MusicManager::get_repeat_state(guild_id).await {
+        RepeatState::Disabled => {
+            debug!("Looping track '{}'", track.data::<TrackMetadata>().title);
+            track.enable_loop().map_err(control_error)?;
+            RepeatState::Track
+        }
+        RepeatState::Track => {
+            debug!(
+                "Disabling loop for track '{}'",
+                track.data::<TrackMetadata>().title
+            );
+            track.disable_loop().map_err(control_error)?;
+            RepeatState::Disabled
+        }
+    };
+
+    // Store the new repeat state in the manager.
+    MusicManager::set_repeat_state(guild_id, new_state).await;
+
+    Ok(new_state)
+}
+
+/// Shuffles the upcoming tracks, keeping the current one playing.
+pub async fn shuffle(ctx: &Context, guild_id: GuildId, user_id: UserId) -> MusicResult<()> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+    MusicManager::get_queue(&guild_id)
+        .await
+        .ok_or(MusicError::NoQueue)?;
+    MusicManager::shuffle_queue(&guild_id).await;
+    Ok(())
+}
+
+/// Toggles whether the player message lists upcoming tracks. Returns the new state.
+pub async fn toggle_queue_view(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> MusicResult<bool> {
+    ensure_same_channel(ctx, guild_id, user_id).await?;
+    Ok(MusicManager::toggle_queue_view(guild_id).await)
+}
+
+/// Returns the metadata and playback position of the current track.
+/// Anyone can check what is playing, even from outside the bot's voice channel.
+pub async fn now_playing(
+    ctx: &Context,
+    guild_id: GuildId,
+) -> MusicResult<(Arc<TrackMetadata>, Duration)> {
+    ensure_connected(ctx, guild_id).await?;
+    let track = current_track(guild_id).await?;
+    let info = track.get_info().await.map_err(control_error)?;
+    Ok((track.data(), info.position))
+}
+
+/// Updates the player message, if one exists, to reflect a change made outside of its buttons.
+/// The update is left to the guild's update task when one is running, so it can be coalesced
+/// with other changes.
+pub async fn refresh_player(ctx: &Context, guild_id: GuildId) {
+    if MusicManager::request_player_update(guild_id).await {
+        return;
+    }
+    if let Some(channel_id) = MusicManager::get_channel_id(guild_id).await
+        && let Err(e) =
+            MusicManager::send_or_update_message(ctx.http.clone(), guild_id, channel_id).await
+    {
+        warn!(
+            "Failed to refresh player message for guild {}: {}",
+            guild_id, e
+        );
+    }
+}
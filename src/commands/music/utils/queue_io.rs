@@ -0,0 +1,184 @@
+//! Exporting the queue to a file and reading queue files back in.
+//! Supports JSON (serialized `TrackMetadata`), M3U playlists, and plain text with one URL or
+//! search query per line.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+
+use super::music_manager::{MusicError, MusicResult};
+
+/// The largest queue file that can be imported, in bytes.
+pub const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+
+/// A file format the queue can be exported to and imported from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum QueueFormat {
+    /// The full metadata of every track, as a JSON array.
+    #[default]
+    #[name = "JSON"]
+    Json,
+    /// An extended M3U playlist, readable by most media players.
+    #[name = "M3U"]
+    M3u,
+    /// Plain text with one URL (or title, if there is no URL) per line.
+    #[name = "Text"]
+    Txt,
+}
+
+impl QueueFormat {
+    /// Returns the file extension used for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            QueueFormat::Json => "json",
+            QueueFormat::M3u => "m3u",
+            QueueFormat::Txt => "txt",
+        }
+    }
+
+    /// Detects the format of a queue file from its name, or failing that, its content.
+    pub fn detect(filename: &str, content: &str) -> Self {
+        let extension = filename
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("json") => QueueFormat::Json,
+            Some("m3u" | "m3u8") => QueueFormat::M3u,
+            Some("txt") => QueueFormat::Txt,
+            _ if content.trim_start().starts_with('[') => QueueFormat::Json,
+            _ if content.trim_start().starts_with("#EXTM3U") => QueueFormat::M3u,
+            _ => QueueFormat::Txt,
+        }
+    }
+}
+
+impl fmt::Display for QueueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            QueueFormat::Json => "JSON",
+            QueueFormat::M3u => "M3U",
+            QueueFormat::Txt => "Text",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Writes the tracks in the given format.
+pub fn export(tracks: &[TrackMetadata], format: QueueFormat) -> MusicResult<String> {
+    match format {
+        QueueFormat::Json => serde_json::to_string_pretty(tracks).map_err(|e| {
+            MusicError::AudioSourceError(format!("Failed to serialize the queue: {}", e))
+        }),
+        QueueFormat::M3u => {
+            let mut playlist = String::from("#EXTM3U\n");
+            for track in tracks {
+                // M3U uses -1 for an unknown duration.
+                let seconds = track
+                    .duration
+                    .map_or(-1, |duration| duration.as_secs() as i64);
+                playlist.push_str(&format!("#EXTINF:{},{}\n", seconds, track.title));
+                playlist.push_str(&entry(track));
+                playlist.push('\n');
+            }
+            Ok(playlist)
+        }
+        QueueFormat::Txt => Ok(tracks.iter().map(|track| entry(track) + "\n").collect()),
+    }
+}
+
+/// Reads the entries of a queue file: the URL (or search query) of each track, in order.
+pub fn parse_entries(content: &str, format: QueueFormat) -> MusicResult<Vec<String>> {
+    match format {
+        QueueFormat::Json => {
+            let tracks: Vec<TrackMetadata> = serde_json::from_str(content).map_err(|e| {
+                MusicError::AudioSourceError(format!("Not a valid queue file: {}", e))
+            })?;
+            Ok(tracks.iter().map(entry).collect())
+        }
+        // Comments (including M3U directives) and blank lines are skipped.
+        QueueFormat::M3u | QueueFormat::Txt => Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()),
+    }
+}
+
+/// Returns what identifies a track in a queue file: its URL, or its title if it has none.
+fn entry(track: &TrackMetadata) -> String {
+    track.url.clone().unwrap_or_else(|| track.title.clone())
+}
+
+/// Returns the total duration of the tracks, ignoring tracks of unknown duration.
+pub fn total_duration(tracks: &[TrackMetadata]) -> Duration {
+    tracks.iter().filter_map(|track| track.duration).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks() -> Vec<TrackMetadata> {
+        vec![
+            TrackMetadata {
+                title: "First".to_string(),
+                url: Some("https://www.youtube.com/watch?v=aaaaaaaaaaa".to_string()),
+                duration: Some(Duration::from_secs(212)),
+                ..Default::default()
+            },
+            TrackMetadata {
+                title: "Second".to_string(),
+                url: None,
+                duration: None,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_export_m3u() {
+        let playlist = export(&tracks(), QueueFormat::M3u).unwrap();
+        assert_eq!(
+            playlist,
+            "#EXTM3U\n\
+             #EXTINF:212,First\n\
+             https://www.youtube.com/watch?v=aaaaaaaaaaa\n\
+             #EXTINF:-1,Second\n\
+             Second\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let expected = vec![
+            "https://www.youtube.com/watch?v=aaaaaaaaaaa".to_string(),
+            "Second".to_string(),
+        ];
+        for format in [QueueFormat::Json, QueueFormat::M3u, QueueFormat::Txt] {
+            let content = export(&tracks(), format).unwrap();
+            assert_eq!(parse_entries(&content, format).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_json() {
+        assert!(parse_entries("not json", QueueFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(QueueFormat::detect("queue.JSON", ""), QueueFormat::Json);
+        assert_eq!(QueueFormat::detect("mix.m3u8", ""), QueueFormat::M3u);
+        assert_eq!(QueueFormat::detect("list.txt", "[1]"), QueueFormat::Txt);
+        assert_eq!(QueueFormat::detect("queue", " [{}]"), QueueFormat::Json);
+        assert_eq!(QueueFormat::detect("queue", "#EXTM3U\n"), QueueFormat::M3u);
+        assert_eq!(QueueFormat::detect("queue", "some song"), QueueFormat::Txt);
+    }
+
+    #[test]
+    fn test_total_duration() {
+        assert_eq!(total_duration(&tracks()), Duration::from_secs(212));
+    }
+}
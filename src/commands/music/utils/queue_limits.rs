@@ -0,0 +1,322 @@
+//! Per-guild limits on music requests.
+//! Caps the queue length, the number of tracks per user, track durations and playlist imports,
+//! and enforces a cooldown between requests from the same user.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use serenity::model::id::{GuildId, UserId};
+use tokio::sync::Mutex;
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::utils::database::{self, QueueLimits};
+
+use super::format_duration;
+use super::music_manager::{MusicError, MusicResult};
+
+/// In-memory cache mapping GuildId to its queue limits.
+static QUEUE_LIMITS: LazyLock<Mutex<HashMap<GuildId, QueueLimits>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The time of each user's last successful request, per guild.
+static LAST_REQUESTS: LazyLock<Mutex<HashMap<(GuildId, UserId), Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The tracks of a request that fit within the guild's limits.
+#[derive(Debug, PartialEq)]
+pub struct LimitedTracks {
+    /// The tracks to queue, in request order.
+    pub tracks: Vec<TrackMetadata>,
+    /// Explanations for any tracks that were left out.
+    pub notes: Vec<String>,
+}
+
+/// Sets the queue limits for a guild, updating both the cache and the database.
+pub async fn set_queue_limits(guild_id: GuildId, limits: QueueLimits) {
+    // Update the cache.
+    QUEUE_LIMITS.lock().await.insert(guild_id, limits);
+
+    // Attempt to save the limits to the database, logging any errors.
+    if let Err(e) = database::set_queue_limits(guild_id, &limits) {
+        eprintln!("Failed to save queue limits to database: {}", e);
+    }
+}
+
+/// Gets the queue limits for a guild, checking the cache before the database.
+pub async fn get_queue_limits(guild_id: GuildId) -> QueueLimits {
+    let mut limits = QUEUE_LIMITS.lock().await;
+    *limits
+        .entry(guild_id)
+        .or_insert_with(|| database::get_queue_limits(guild_id))
+}
+
+/// Returns how long a user still has to wait before their next request, if at all.
+fn cooldown_remaining(
+    last_request: Option<Instant>,
+    now: Instant,
+    cooldown: Duration,
+) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(last_request?);
+    (elapsed < cooldown).then(|| cooldown - elapsed)
+}
+
+/// Fails with `RequestCooldown` if the user made a request too recently.
+pub async fn check_cooldown(
+    guild_id: GuildId,
+    user_id: UserId,
+    limits: &QueueLimits,
+) -> MusicResult<()> {
+    let last_request = LAST_REQUESTS
+        .lock()
+        .await
+        .get(&(guild_id, user_id))
+        .copied();
+    let cooldown = Duration::from_secs(limits.cooldown.into());
+    match cooldown_remaining(last_request, Instant::now(), cooldown) {
+        Some(remaining) => Err(MusicError::RequestCooldown(remaining)),
+        None => Ok(()),
+    }
+}
+
+/// Records a successful request, starting the user's cooldown.
+///
+/// Requests in the guild whose cooldown has passed are forgotten, and nothing is recorded if
+/// the guild has no cooldown.
+pub async fn record_request(guild_id: GuildId, user_id: UserId) {
+    let cooldown = Duration::from_secs(get_queue_limits(guild_id).await.cooldown.into());
+    let mut last_requests = LAST_REQUESTS.lock().await;
+    let now = Instant::now();
+    prune_requests(&mut last_requests, guild_id, now, cooldown);
+    if !cooldown.is_zero() {
+        last_requests.insert((guild_id, user_id), now);
+    }
+}
+
+/// Removes the requests in a guild that no longer hold back a user's next request.
+fn prune_requests(
+    last_requests: &mut HashMap<(GuildId, UserId), Instant>,
+    guild_id: GuildId,
+    now: Instant,
+    cooldown: Duration,
+) {
+    last_requests.retain(|(guild, _), last_request| {
+        *guild != guild_id || cooldown_remaining(Some(*last_request), now, cooldown).is_some()
+    });
+}
+
+/// Trims the requested tracks to what the guild's limits allow.
+///
+/// `queued` is the number of user-requested tracks already in the queue, and `queued_by_user`
+/// the number of those requested by the same user. Playlists are cut to the import limit, tracks
+/// that are too long are skipped, and the rest is cut to the space left for the user and in the
+/// queue. Fails if none of the tracks can be queued.
+pub fn apply_limits(
+    limits: &QueueLimits,
+    mut tracks: Vec<TrackMetadata>,
+    queued: usize,
+    queued_by_user: usize,
+) -> MusicResult<LimitedTracks> {
+    let mut notes = Vec::new();
+
+    // 1. Only import the start of large playlists.
+    let max_playlist = limits.max_playlist_size as usize;
+    if max_playlist > 0 && tracks.len() > max_playlist {
+        tracks.truncate(max_playlist);
+        notes.push(format!(
+            "Only the first {} tracks of the playlist were imported",
+            max_playlist
+        ));
+    }
+
+    // 2. Skip tracks that are too long. Tracks of unknown duration are allowed.
+    if limits.max_track_duration > 0 {
+        let max_duration = Duration::from_secs(limits.max_track_duration.into());
+        let requested = tracks.len();
+        tracks.retain(|track| {
+            track
+                .duration
+                .is_none_or(|duration| duration <= max_duration)
+        });
+        if tracks.is_empty() {
+            return Err(MusicError::TrackTooLong(max_duration));
+        }
+        let skipped = requested - tracks.len();
+        if skipped > 0 {
+            notes.push(format!(
+                "Skipped {} tracks longer than {}",
+                skipped,
+                format_duration(max_duration)
+            ));
+        }
+    }
+
+    // 3. Keep within the user's share of the queue.
+    let max_per_user = limits.max_tracks_per_user as usize;
+    if max_per_user > 0 {
+        let available = max_per_user.saturating_sub(queued_by_user);
+        if available == 0 {
+            return Err(MusicError::UserTrackLimit(max_per_user));
+        }
+        if tracks.len() > available {
+            tracks.truncate(available);
+            notes.push(format!(
+                "Only {} tracks were added, as each user can queue up to {}",
+                available, max_per_user
+            ));
+        }
+    }
+
+    // 4. Keep within the queue length.
+    let max_queue = limits.max_queue_length as usize;
+    if max_queue > 0 {
+        let available = max_queue.saturating_sub(queued);
+        if available == 0 {
+            return Err(MusicError::QueueFull(max_queue));
+        }
+        if tracks.len() > available {
+            tracks.truncate(available);
+            notes.push(format!(
+                "Only {} tracks were added, as the queue is limited to {}",
+                available, max_queue
+            ));
+        }
+    }
+
+    Ok(LimitedTracks { tracks, notes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(durations: &[Option<u64>]) -> Vec<TrackMetadata> {
+        durations
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| TrackMetadata {
+                title: format!("Track {}", i),
+                duration: duration.map(Duration::from_secs),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn unlimited() -> QueueLimits {
+        QueueLimits {
+            max_queue_length: 0,
+            max_tracks_per_user: 0,
+            max_track_duration: 0,
+            max_playlist_size: 0,
+            cooldown: 0,
+        }
+    }
+
+    #[test]
+    fn test_cooldown_remaining() {
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(10);
+        assert_eq!(cooldown_remaining(None, now, cooldown), None);
+        assert_eq!(
+            cooldown_remaining(Some(now), now + Duration::from_secs(4), cooldown),
+            Some(Duration::from_secs(6))
+        );
+        assert_eq!(
+            cooldown_remaining(Some(now), now + Duration::from_secs(10), cooldown),
+            None
+        );
+        assert_eq!(cooldown_remaining(Some(now), now, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_prune_requests() {
+        let now = Instant::now();
+        let guild = GuildId::new(1);
+        let other_guild = GuildId::new(2);
+        let mut last_requests = HashMap::from([
+            ((guild, UserId::new(1)), now - Duration::from_secs(30)),
+            ((guild, UserId::new(2)), now - Duration::from_secs(5)),
+            ((other_guild, UserId::new(1)), now - Duration::from_secs(30)),
+        ]);
+
+        // Only the guild's requests whose cooldown has passed are removed.
+        prune_requests(&mut last_requests, guild, now, Duration::from_secs(10));
+        assert!(!last_requests.contains_key(&(guild, UserId::new(1))));
+        assert!(last_requests.contains_key(&(guild, UserId::new(2))));
+        assert!(last_requests.contains_key(&(other_guild, UserId::new(1))));
+
+        // Without a cooldown, none of the guild's requests are kept.
+        prune_requests(&mut last_requests, guild, now, Duration::ZERO);
+        assert_eq!(last_requests.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_limits_unlimited() {
+        let result = apply_limits(&unlimited(), tracks(&[Some(5000); 3]), 1000, 1000).unwrap();
+        assert_eq!(result.tracks.len(), 3);
+        assert!(result.notes.is_empty());
+    }
+
+    #[test]
+    fn test_apply_limits_playlist_size() {
+        let limits = QueueLimits {
+            max_playlist_size: 2,
+            ..unlimited()
+        };
+        let result = apply_limits(&limits, tracks(&[None; 5]), 0, 0).unwrap();
+        assert_eq!(result.tracks, tracks(&[None; 2]));
+        assert_eq!(result.notes.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_limits_track_duration() {
+        let limits = QueueLimits {
+            max_track_duration: 60,
+            ..unlimited()
+        };
+
+        // Long tracks are skipped, tracks of unknown duration are kept.
+        let result = apply_limits(&limits, tracks(&[Some(30), Some(90), None]), 0, 0).unwrap();
+        let titles: Vec<_> = result.tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Track 0", "Track 2"]);
+        assert_eq!(result.notes.len(), 1);
+
+        // A request with only long tracks is refused.
+        assert!(matches!(
+            apply_limits(&limits, tracks(&[Some(90)]), 0, 0),
+            Err(MusicError::TrackTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_limits_per_user() {
+        let limits = QueueLimits {
+            max_tracks_per_user: 3,
+            ..unlimited()
+        };
+        let result = apply_limits(&limits, tracks(&[None; 5]), 2, 1).unwrap();
+        assert_eq!(result.tracks.len(), 2);
+        assert_eq!(result.notes.len(), 1);
+
+        assert!(matches!(
+            apply_limits(&limits, tracks(&[None]), 3, 3),
+            Err(MusicError::UserTrackLimit(3))
+        ));
+    }
+
+    #[test]
+    fn test_apply_limits_queue_length() {
+        let limits = QueueLimits {
+            max_queue_length: 10,
+            ..unlimited()
+        };
+        let result = apply_limits(&limits, tracks(&[None; 5]), 8, 0).unwrap();
+        assert_eq!(result.tracks.len(), 2);
+        assert_eq!(result.notes.len(), 1);
+
+        assert!(matches!(
+            apply_limits(&limits, tracks(&[None]), 10, 0),
+            Err(MusicError::QueueFull(10))
+        ));
+    }
+}
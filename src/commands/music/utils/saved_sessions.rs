@@ -0,0 +1,173 @@
+//! Saving and restoring music sessions.
+//! When the bot is stopped, the guild's queue and the playback position of the current track
+//! are saved, so `/resume_session` can pick up where the session left off. Saved sessions are
+//! kept for `SESSION_RETENTION`.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use serenity::model::id::GuildId;
+use tracing::{info, warn};
+
+use crate::commands::music::audio_sources::track_metadata::TrackMetadata;
+use crate::utils::database::{self, SavedSession};
+
+use super::music_manager::{MusicError, MusicManager, MusicResult};
+
+/// How long a saved session can be resumed for.
+pub const SESSION_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A music session restored from the database.
+pub struct RestoredSession {
+    /// The tracks that were queued, starting with the one that was playing.
+    pub tracks: Vec<TrackMetadata>,
+    /// The playback position of the first track.
+    pub position: Duration,
+}
+
+/// Saves the guild's current queue and playback position, replacing any previously saved
+/// session. Pending autoplay tracks are left out, as autoplay queues new ones on resume.
+/// Returns `false` if there was nothing to save.
+pub async fn save_session(guild_id: GuildId) -> bool {
+    let Some(queue) = MusicManager::get_queue(&guild_id).await else {
+        return false;
+    };
+
+    // Collect the playing track and every upcoming track requested by a user.
+    let handles = queue.current_queue();
+    let tracks: Vec<TrackMetadata> = handles
+        .iter()
+        .enumerate()
+        .map(|(index, handle)| (index, handle.data::<TrackMetadata>()))
+        .filter(|(index, metadata)| *index == 0 || !metadata.is_autoplay())
+        .map(|(_, metadata)| metadata.as_ref().clone())
+        .collect();
+    if tracks.is_empty() {
+        return false;
+    }
+
+    // Remember how far into the current track playback got.
+    let position = match handles.first() {
+        Some(handle) => handle
+            .get_info()
+            .await
+            .map(|info| info.position)
+            .unwrap_or_default(),
+        None => Duration::ZERO,
+    };
+
+    let tracks_json = match serde_json::to_string(&tracks) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize music session: {}", e);
+            return false;
+        }
+    };
+    let session = SavedSession {
+        tracks: tracks_json,
+        position_ms: u64::try_from(position.as_millis()).unwrap_or(u64::MAX),
+        saved_at: Utc::now().timestamp(),
+    };
+
+    // Purge sessions nobody resumed in time, then save this one.
+    purge_expired_sessions();
+    if let Err(e) = database::save_session(guild_id, &session) {
+        warn!("Failed to save music session to database: {}", e);
+        return false;
+    }
+
+    info!(
+        "Saved music session of {} tracks for guild {}",
+        tracks.len(),
+        guild_id
+    );
+    true
+}
+
+/// Loads the guild's saved session, if it is still within the retention window.
+/// The session is removed once the caller confirms it was restored with `forget_session`.
+pub fn load_session(guild_id: GuildId) -> MusicResult<RestoredSession> {
+    let session = database::get_saved_session(guild_id).ok_or(MusicError::NoSavedSession)?;
+    if is_expired(session.saved_at, Utc::now().timestamp()) {
+        forget_session(guild_id);
+        return Err(MusicError::NoSavedSession);
+    }
+
+    let tracks: Vec<TrackMetadata> = serde_json::from_str(&session.tracks).map_err(|e| {
+        MusicError::AudioSourceError(format!("Failed to read saved session: {}", e))
+    })?;
+    if tracks.is_empty() {
+        return Err(MusicError::NoSavedSession);
+    }
+
+    Ok(RestoredSession {
+        tracks,
+        position: Duration::from_millis(session.position_ms),
+    })
+}
+
+/// Deletes the guild's saved session.
+pub fn forget_session(guild_id: GuildId) {
+    if let Err(e) = database::delete_saved_session(guild_id) {
+        warn!("Failed to delete saved music session: {}", e);
+    }
+}
+
+/// Deletes every saved session older than `SESSION_RETENTION`.
+fn purge_expired_sessions() {
+    let cutoff = Utc::now().timestamp() - SESSION_RETENTION.as_secs() as i64;
+    match database::delete_expired_sessions(cutoff) {
+        Ok(0) => {}
+        Ok(count) => info!("Deleted {} expired music sessions", count),
+        Err(e) => warn!("Failed to delete expired music sessions: {}", e),
+    }
+}
+
+/// Checks whether a session saved at `saved_at` is past the retention window at `now`
+/// (both Unix timestamps in seconds).
+fn is_expired(saved_at: i64, now: i64) -> bool {
+    now.saturating_sub(saved_at) > SESSION_RETENTION.as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_expired() {
+        let retention = SESSION_RETENTION.as_secs() as i64;
+        let saved_at = 1_000_000;
+
+        assert!(!is_expired(saved_at, saved_at));
+        assert!(!is_expired(saved_at, saved_at + retention));
+        assert!(is_expired(saved_at, saved_at + retention + 1));
+    }
+
+    #[test]
+    fn test_tracks_round_trip_through_json() {
+        let tracks = vec![TrackMetadata {
+            title: "Track".to_string(),
+            url: Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+            duration: Some(Duration::from_secs(212)),
+            ..Default::default()
+        }];
+
+        let json = serde_json::to_string(&tracks).unwrap();
+        let restored: Vec<TrackMetadata> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, tracks);
+    }
+
+    #[test]
+    fn test_autoplay_is_not_a_requester_name() {
+        // Sessions saved before tracks were flagged as autoplay still load as user requests.
+        let json = r#"[{"title":"Track","url":null,"duration":null,"thumbnail":null,"requested_by":"Autoplay"}]"#;
+        let restored: Vec<TrackMetadata> = serde_json::from_str(json).unwrap();
+        assert!(!restored[0].is_autoplay());
+
+        let track = TrackMetadata {
+            autoplay: true,
+            ..Default::default()
+        };
+        assert!(track.is_autoplay());
+    }
+}
@@ -1,12 +1,13 @@
-//! This module handles Discord gateway events, specifically interaction events.
+//! This module handles Discord gateway events, specifically interaction and message events.
 
-use serenity::all::ComponentInteraction;
+use serenity::all::{ComponentInteraction, Message};
 use serenity::async_trait;
 use serenity::model::application::Interaction;
 use serenity::prelude::*;
 use tracing::error;
 
-use crate::commands::music::utils::component_handlers;
+use crate::commands::ai::message_chat;
+use crate::commands::music::utils::{component_handlers, music_channel};
 
 /// The main event handler struct for the bot.
 ///
@@ -20,12 +21,23 @@ impl serenity::prelude::EventHandler for Handler {
     /// Currently, it only handles component interactions (like buttons) whose custom IDs
     /// start with "music_", delegating them to `music_component_interaction`.
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::Component(mut component) = interaction {
-            if component.data.custom_id.starts_with("music_") {
-                music_component_interaction(&ctx, &mut component).await;
-            }
+        if let Interaction::Component(mut component) = interaction
+            && component.data.custom_id.starts_with("music_")
+        {
+            music_component_interaction(&ctx, &mut component).await;
         }
     }
+
+    /// Called when a message is posted in a channel the bot can see.
+    ///
+    /// Messages that mention the bot or reply to one of its AI messages are answered by the AI.
+    /// Other plain messages in a guild's music request channel are queued as play requests.
+    async fn message(&self, ctx: Context, message: Message) {
+        if message_chat::handle_message(&ctx, &message).await {
+            return;
+        }
+        music_channel::handle_request_message(&ctx, &message).await;
+    }
 }
 
 /// Handle component interactions for components with identities starting with "music_"
@@ -19,7 +19,11 @@ mod events;
 mod utils;
 
 use commands::{
-    ai::{chat::*, get_model::*, list_models::*, set_model::*},
+    ai::{
+        ask_about_image::*, chat::*, chat_export::*, chat_history::*, chat_mode::*,
+        chat_options::*, chat_reset::*, chat_thread::*, get_model::*, list_models::*, persona::*,
+        set_model::*,
+    },
     coingecko::coin::*,
     general::ping::*,
     music::remove::remove,
@@ -35,6 +39,22 @@ type CommandResult = Result<(), Error>;
 /// Lazily initialized static HTTP client for making requests.
 pub static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
 
+/// The prefix for prefix commands, e.g. `~play`.
+const COMMAND_PREFIX: &str = "~";
+
+/// The alternative prefix for prefix commands: the bot's name, e.g. `rusty, play`.
+static NAME_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\brusty\b,?").expect("Valid name prefix"));
+
+/// Checks whether a message starts with one of the prefixes of prefix commands, the same way
+/// the framework matches them.
+pub fn has_command_prefix(content: &str) -> bool {
+    content.starts_with(COMMAND_PREFIX)
+        || NAME_PREFIX
+            .find(content)
+            .is_some_and(|prefix| prefix.start() == 0)
+}
+
 /// Struct to hold shared data accessible across commands and events.
 /// Currently empty, but can be expanded as needed.
 struct Data {}
@@ -134,9 +154,17 @@ async fn main() -> Result<(), Error> {
         // General commands
         ping(),
         // AI-centric commands
+        ask_about_image(),
         chat(),
+        chat_export(),
+        chat_history(),
+        chat_mode(),
+        chat_options(),
+        chat_reset(),
+        chat_thread(),
         get_model(),
         list_models(),
+        persona(),
         set_model(),
         // Coingecko commands
         coin(),
@@ -155,10 +183,32 @@ async fn main() -> Result<(), Error> {
     {
         check_ytdlp();
 
-        use commands::music::{autoplay::*, play::*};
+        use commands::music::{
+            autoplay::*, crossfade::*, move_here::*, nowplaying::*, pause::*, play::*, player::*,
+            queue::*, repeat::*, resume::*, resume_session::*, settings::*, shuffle::*, skip::*,
+            stop::*, toggle_queue::*,
+        };
 
         // Add music commands
-        commands.extend(vec![autoplay(), play(), remove()]);
+        commands.extend(vec![
+            autoplay(),
+            crossfade(),
+            move_here(),
+            music(),
+            nowplaying(),
+            pause(),
+            play(),
+            player(),
+            queue(),
+            remove(),
+            repeat(),
+            resume(),
+            resume_session(),
+            shuffle(),
+            skip(),
+            stop(),
+            toggle_queue(),
+        ]);
     }
 
     // Configure and build the poise framework.
@@ -166,13 +216,13 @@ async fn main() -> Result<(), Error> {
         .options(poise::FrameworkOptions {
             commands,
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("~".into()),
+                prefix: Some(COMMAND_PREFIX.into()),
                 edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                     Duration::from_secs(3600),
                 ))),
-                additional_prefixes: vec![poise::Prefix::Regex(
-                    Regex::new(r"(?i)\brusty\b,?").unwrap(),
-                )],
+                additional_prefixes: vec![poise::Prefix::Regex(NAME_PREFIX.clone())],
+                // Mentions are for chatting with the AI, not for running commands.
+                mention_as_prefix: false,
                 ..Default::default()
             },
             pre_command: |ctx| {
@@ -0,0 +1,470 @@
+//! Tools the AI can call to use what the bot already knows how to do, like searching the web,
+//! looking up coin prices, or queueing music.
+//! Each tool implements `BotTool`, and a `ToolRegistry` describes the tools to the model and runs
+//! the calls it makes.
+
+#[cfg(feature = "brave_search")]
+use std::env;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use ollama_rs::generation::tools::{ToolCall, ToolInfo};
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, User};
+use serde_json::{Map, Value, json};
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "music")]
+use crate::commands::music::utils::music_manager::MusicManager;
+#[cfg(feature = "brave_search")]
+use crate::utils::brave;
+use crate::{Context, Error};
+
+/// Where a tool is called: the chat the AI was asked in, and who asked.
+#[derive(Clone)]
+pub struct ToolContext {
+    /// The Discord context, for tools that act on Discord; `None` outside of Discord events.
+    pub discord: Option<serenity::Context>,
+    /// The guild of the chat, if any.
+    pub guild_id: Option<GuildId>,
+    /// The channel of the chat.
+    pub channel_id: ChannelId,
+    /// The user who asked the AI.
+    pub user: User,
+}
+
+impl ToolContext {
+    /// Creates the context of a command.
+    pub fn from_command(ctx: Context<'_>) -> Self {
+        Self {
+            discord: Some(ctx.serenity_context().clone()),
+            guild_id: ctx.guild_id(),
+            channel_id: ctx.channel_id(),
+            user: ctx.author().clone(),
+        }
+    }
+
+    /// Creates the context of a message addressed to the AI.
+    pub fn from_message(ctx: &serenity::Context, message: &serenity::Message) -> Self {
+        Self {
+            discord: Some(ctx.clone()),
+            guild_id: message.guild_id,
+            channel_id: message.channel_id,
+            user: message.author.clone(),
+        }
+    }
+}
+
+/// A capability of the bot that the AI can call as a tool.
+pub trait BotTool: Send + Sync {
+    /// The name the model calls the tool by.
+    fn name(&self) -> &'static str;
+
+    /// Tells the model what the tool does and when to use it.
+    fn description(&self) -> &'static str;
+
+    /// The JSON schema of the tool's arguments.
+    fn parameters(&self) -> Value;
+
+    /// Calls the tool with the arguments given by the model, returning the result for the model.
+    fn call<'a>(
+        &'a self,
+        ctx: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> BoxFuture<'a, Result<String, Error>>;
+}
+
+/// The tools the AI can call.
+#[derive(Default)]
+pub struct ToolRegistry {
+    /// The registered tools.
+    tools: Vec<Box<dyn BotTool>>,
+}
+
+impl ToolRegistry {
+    /// Creates a registry with the bot's tools. Tools of disabled features are left out.
+    pub fn with_bot_tools() -> Self {
+        let mut registry = Self::default();
+        #[cfg(feature = "brave_search")]
+        registry.register(WebSearch);
+        registry.register(CoinPrice);
+        #[cfg(feature = "music")]
+        registry.register(EnqueueMusic);
+        registry.register(CurrentTime);
+        registry
+    }
+
+    /// Adds a tool to the registry.
+    pub fn register(&mut self, tool: impl BotTool + 'static) {
+        self.tools.push(Box::new(tool));
+    }
+
+    /// Returns whether the registry has no tools.
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Describes the tools to the model.
+    pub fn tool_infos(&self) -> Vec<ToolInfo> {
+        self.tools
+            .iter()
+            .filter_map(|tool| {
+                serde_json::from_value(json!({
+                    "type": "Function",
+                    "function": {
+                        "name": tool.name(),
+                        "description": tool.description(),
+                        "parameters": tool.parameters(),
+                    }
+                }))
+                .inspect_err(|e| warn!("Failed to describe tool {}: {}", tool.name(), e))
+                .ok()
+            })
+            .collect()
+    }
+
+    /// Runs a tool call made by the model and returns the result for the model.
+    /// Failures are returned as text too, so the model can explain them.
+    pub async fn call(&self, ctx: &ToolContext, call: &ToolCall) -> String {
+        let name = &call.function.name;
+        let Some(tool) = self.tools.iter().find(|tool| tool.name() == name) else {
+            warn!("AI called unknown tool '{}'", name);
+            return format!("Error: there is no tool named '{}'.", name);
+        };
+
+        info!(
+            "AI called tool {} with {} for user {}",
+            name, call.function.arguments, ctx.user.name
+        );
+        match tool.call(ctx, &call.function.arguments).await {
+            Ok(result) => {
+                debug!("Tool {} returned {} characters", name, result.len());
+                result
+            }
+            Err(e) => {
+                warn!("Tool {} failed: {}", name, e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+/// Starts the line summarizing the tools the AI used at the end of its reply.
+const TOOL_SUMMARY_PREFIX: &str = "-# 🔧 Used ";
+
+/// Summarizes the tool calls the AI made, as a line of small text for the end of its reply.
+pub fn summarize_tool_calls(calls: &[ToolCall]) -> String {
+    let calls: Vec<String> = calls
+        .iter()
+        .map(|call| {
+            let arguments: Vec<String> = match &call.function.arguments {
+                Value::Object(arguments) => arguments
+                    .values()
+                    .map(|value| match value {
+                        Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if arguments.is_empty() {
+                format!("`{}`", call.function.name)
+            } else {
+                format!("`{}` ({})", call.function.name, arguments.join(", "))
+            }
+        })
+        .collect();
+    format!("{}{}", TOOL_SUMMARY_PREFIX, calls.join(", "))
+}
+
+/// Removes the summary of the tools used from the end of an AI reply read back from Discord.
+pub fn strip_tool_summary(content: &str) -> &str {
+    match content.rsplit_once('\n') {
+        Some((text, last)) if last.starts_with(TOOL_SUMMARY_PREFIX) => text.trim_end(),
+        None if content.starts_with(TOOL_SUMMARY_PREFIX) => "",
+        _ => content,
+    }
+}
+
+/// Builds the JSON schema of tool arguments that are all required strings, from their names
+/// and descriptions.
+fn string_parameters(parameters: &[(&str, &str)]) -> Value {
+    let properties: Map<String, Value> = parameters
+        .iter()
+        .map(|(name, description)| {
+            (
+                name.to_string(),
+                json!({ "type": "string", "description": description }),
+            )
+        })
+        .collect();
+    let required: Vec<&str> = parameters.iter().map(|(name, _)| *name).collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Reads a required, non-empty string argument of a tool call.
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, Error> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| format!("the '{}' argument is missing", name).into())
+}
+
+/// Searches the web with Brave Search.
+#[cfg(feature = "brave_search")]
+struct WebSearch;
+
+#[cfg(feature = "brave_search")]
+impl BotTool for WebSearch {
+    fn name(&self) -> &'static str {
+        "web_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Searches the web and returns the top results. Use it for recent events and facts you don't know."
+    }
+
+    fn parameters(&self) -> Value {
+        string_parameters(&[("query", "What to search for")])
+    }
+
+    fn call<'a>(
+        &'a self,
+        _ctx: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let query = string_argument(arguments, "query")?;
+            let api_key =
+                env::var("BRAVE_API_KEY").map_err(|_| "web search isn't configured on this bot")?;
+            let results = brave::search(query, brave::API_URL, &api_key).await?;
+            Ok(brave::format_search_results(&results, query))
+        })
+    }
+}
+
+/// Looks up a cryptocurrency's price on CoinGecko.
+struct CoinPrice;
+
+impl BotTool for CoinPrice {
+    fn name(&self) -> &'static str {
+        "coin_price"
+    }
+
+    fn description(&self) -> &'static str {
+        "Looks up the current USD price and 24 hour change of a cryptocurrency."
+    }
+
+    fn parameters(&self) -> Value {
+        string_parameters(&[(
+            "coin",
+            "Name or CoinGecko ID of the coin, e.g. bitcoin or ethereum",
+        )])
+    }
+
+    fn call<'a>(
+        &'a self,
+        _ctx: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let coin = string_argument(arguments, "coin")?;
+            crate::commands::coingecko::price_summary(coin).await
+        })
+    }
+}
+
+/// Queues music for the user, like `/play`.
+#[cfg(feature = "music")]
+struct EnqueueMusic;
+
+#[cfg(feature = "music")]
+impl BotTool for EnqueueMusic {
+    fn name(&self) -> &'static str {
+        "enqueue_music"
+    }
+
+    fn description(&self) -> &'static str {
+        "Adds a song or playlist to the music queue of the user's voice channel. Only use it when the user asks for music."
+    }
+
+    fn parameters(&self) -> Value {
+        string_parameters(&[(
+            "query",
+            "A YouTube or Spotify URL, or a search query like an artist and song title",
+        )])
+    }
+
+    fn call<'a>(
+        &'a self,
+        ctx: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let query = string_argument(arguments, "query")?;
+            let (Some(discord), Some(guild_id)) = (&ctx.discord, ctx.guild_id) else {
+                return Err("music can only be played in a server".into());
+            };
+
+            let outcome = MusicManager::process_play_request(
+                discord,
+                guild_id,
+                ctx.channel_id,
+                &ctx.user,
+                query.to_string(),
+            )
+            .await?;
+            let mut result = if outcome.number_of_tracks > 1 {
+                format!(
+                    "Queued a playlist of {} tracks, starting with {}.",
+                    outcome.number_of_tracks, outcome.first_track.title
+                )
+            } else {
+                format!("Queued {}.", outcome.first_track.title)
+            };
+            for note in &outcome.notes {
+                result.push_str(&format!(" Note: {}", note));
+            }
+            Ok(result)
+        })
+    }
+}
+
+/// Tells the current date and time.
+struct CurrentTime;
+
+impl BotTool for CurrentTime {
+    fn name(&self) -> &'static str {
+        "current_time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Returns the current date and time in UTC."
+    }
+
+    fn parameters(&self) -> Value {
+        string_parameters(&[])
+    }
+
+    fn call<'a>(
+        &'a self,
+        _ctx: &'a ToolContext,
+        _arguments: &'a Value,
+    ) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move { Ok(describe_time(Utc::now())) })
+    }
+}
+
+/// Describes a point in time for the model, with the day of the week.
+fn describe_time(time: DateTime<Utc>) -> String {
+    time.format("%A, %B %-d, %Y, %H:%M:%S UTC").to_string()
+}
+
+/// Module containing tests for the AI's tools.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Test helper: Creates a tool context without Discord.
+    fn test_context() -> ToolContext {
+        ToolContext {
+            discord: None,
+            guild_id: None,
+            channel_id: ChannelId::new(1),
+            user: serde_json::from_value(json!({
+                "id": "123456789012345678",
+                "avatar": null,
+                "bot": false,
+                "discriminator": "1234",
+                "username": "TestUser",
+            }))
+            .expect("Valid user"),
+        }
+    }
+
+    /// Test helper: Creates a tool call.
+    fn tool_call(name: &str, arguments: Value) -> ToolCall {
+        serde_json::from_value(json!({ "function": { "name": name, "arguments": arguments } }))
+            .expect("Valid tool call")
+    }
+
+    /// Tests describing the tools to the model.
+    #[test]
+    fn test_tool_infos() {
+        let mut registry = ToolRegistry::default();
+        assert!(registry.is_empty());
+        registry.register(CoinPrice);
+        registry.register(CurrentTime);
+
+        let infos = registry.tool_infos();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].function.name, "coin_price");
+        assert_eq!(
+            infos[0].function.parameters.get("required"),
+            Some(&json!(["coin"]))
+        );
+        assert_eq!(infos[1].function.name, "current_time");
+    }
+
+    /// Tests running tool calls, including unknown tools and missing arguments.
+    #[tokio::test]
+    async fn test_call() {
+        let mut registry = ToolRegistry::default();
+        registry.register(CoinPrice);
+        registry.register(CurrentTime);
+        let ctx = test_context();
+
+        let result = registry
+            .call(&ctx, &tool_call("current_time", json!({})))
+            .await;
+        assert!(result.ends_with("UTC"));
+
+        let result = registry
+            .call(&ctx, &tool_call("coin_price", json!({})))
+            .await;
+        assert_eq!(result, "Error: the 'coin' argument is missing");
+
+        let result = registry.call(&ctx, &tool_call("launch", json!({}))).await;
+        assert_eq!(result, "Error: there is no tool named 'launch'.");
+    }
+
+    /// Tests summarizing the tool calls the AI made.
+    #[test]
+    fn test_summarize_tool_calls() {
+        let calls = [
+            tool_call("web_search", json!({ "query": "rust 2024 edition" })),
+            tool_call("current_time", json!({})),
+        ];
+        assert_eq!(
+            summarize_tool_calls(&calls),
+            "-# 🔧 Used `web_search` (rust 2024 edition), `current_time`"
+        );
+    }
+
+    /// Tests removing the summary of the tools used from a reply.
+    #[test]
+    fn test_strip_tool_summary() {
+        let summary = summarize_tool_calls(&[tool_call("current_time", json!({}))]);
+        assert_eq!(
+            strip_tool_summary(&format!("It's Sunday.\n\n{}", summary)),
+            "It's Sunday."
+        );
+        assert_eq!(strip_tool_summary(&summary), "");
+        assert_eq!(
+            strip_tool_summary("It's Sunday.\nIt's sunny."),
+            "It's Sunday.\nIt's sunny."
+        );
+    }
+
+    /// Tests describing the current time.
+    #[test]
+    fn test_describe_time() {
+        let time = Utc.with_ymd_and_hms(2026, 10, 18, 9, 5, 0).unwrap();
+        assert_eq!(
+            describe_time(time),
+            "Sunday, October 18, 2026, 09:05:00 UTC"
+        );
+    }
+}
@@ -5,6 +5,9 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+/// Base URL of the Brave Search API.
+pub const API_URL: &str = "https://api.search.brave.com";
+
 /// Errors that can occur during Brave Search API interactions.
 #[derive(Error, Debug)]
 pub enum BraveSearchError {
@@ -0,0 +1,279 @@
+//! Keeps AI conversations within the model's context window.
+//! Token counts are estimated from message length, each model has a token budget, and once a
+//! conversation outgrows it the oldest turns are folded into a rolling summary.
+
+use std::collections::HashMap;
+use std::env;
+
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use tracing::warn;
+
+/// The token budget used for models without a budget of their own.
+pub const DEFAULT_CONTEXT_BUDGET: usize = 4096;
+
+/// The average number of characters per token, used to estimate token counts.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// The tokens each message costs on top of its content, for its role and delimiters.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// The start of the stored message that summarizes the older part of a conversation.
+pub const SUMMARY_PREFIX: &str = "Summary of the earlier conversation: ";
+
+/// The instruction given to the model when asking it to summarize a conversation.
+const SUMMARY_INSTRUCTION: &str = "Summarize the following conversation between a user and an AI assistant in a short paragraph. Keep the names, facts, preferences, and decisions the assistant will need to continue the conversation. Reply with the summary only.";
+
+/// Estimates the number of tokens a message takes up in the model's context.
+pub fn estimate_tokens(message: &ChatMessage) -> usize {
+    message.content.chars().count().div_ceil(CHARS_PER_TOKEN) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Estimates the number of tokens a list of messages takes up in the model's context.
+pub fn total_tokens(messages: &[ChatMessage]) -> usize {
+    messages.iter().map(estimate_tokens).sum()
+}
+
+/// The token budget of each model for the conversation history and prompt sent to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextBudgets {
+    /// The budget of models not listed in `models`.
+    default: usize,
+    /// Budgets by model name, with or without the tag (e.g. `llama3.1` or `llama3.1:8b`).
+    models: HashMap<String, usize>,
+}
+
+impl Default for ContextBudgets {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_CONTEXT_BUDGET,
+            models: HashMap::new(),
+        }
+    }
+}
+
+impl ContextBudgets {
+    /// Reads the budgets from the environment.
+    /// `OLLAMA_CONTEXT_BUDGET` sets the default budget, and `OLLAMA_CONTEXT_BUDGETS` sets the
+    /// budgets of specific models as a comma-separated list of `model=tokens` pairs.
+    pub fn from_env() -> Self {
+        Self::parse(
+            env::var("OLLAMA_CONTEXT_BUDGET").ok().as_deref(),
+            env::var("OLLAMA_CONTEXT_BUDGETS").ok().as_deref(),
+        )
+    }
+
+    /// Parses the default budget and the per-model budgets, skipping invalid entries.
+    fn parse(default: Option<&str>, models: Option<&str>) -> Self {
+        let default = match default.map(|value| value.trim().parse::<usize>()) {
+            Some(Ok(budget)) if budget > 0 => budget,
+            Some(_) => {
+                warn!(
+                    "Invalid OLLAMA_CONTEXT_BUDGET, using {} tokens",
+                    DEFAULT_CONTEXT_BUDGET
+                );
+                DEFAULT_CONTEXT_BUDGET
+            }
+            None => DEFAULT_CONTEXT_BUDGET,
+        };
+
+        let mut budgets = HashMap::new();
+        for entry in models.unwrap_or_default().split(',') {
+            if entry.trim().is_empty() {
+                continue;
+            }
+            match entry
+                .split_once('=')
+                .map(|(model, budget)| (model.trim(), budget.trim().parse::<usize>()))
+            {
+                Some((model, Ok(budget))) if !model.is_empty() && budget > 0 => {
+                    budgets.insert(model.to_string(), budget);
+                }
+                _ => warn!("Skipping invalid OLLAMA_CONTEXT_BUDGETS entry '{}'", entry),
+            }
+        }
+
+        Self {
+            default,
+            models: budgets,
+        }
+    }
+
+    /// Returns the token budget of a model. A budget set for the exact model name wins over one
+    /// set for the model without its tag.
+    pub fn budget(&self, model: &str) -> usize {
+        self.models
+            .get(model)
+            .or_else(|| {
+                let (name, _tag) = model.split_once(':')?;
+                self.models.get(name)
+            })
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Returns how many of the oldest history messages should be summarized for the history and
+/// the prompt to fit the budget, or `None` if they already fit.
+///
+/// The newest messages that fit in half the budget are kept as they are, leaving room for the
+/// summary and the model's response to grow into.
+pub fn messages_to_summarize(
+    history: &[ChatMessage],
+    prompt: &ChatMessage,
+    budget: usize,
+) -> Option<usize> {
+    if history.is_empty() || total_tokens(history) + estimate_tokens(prompt) <= budget {
+        return None;
+    }
+
+    // Keep the newest messages that fit in half the budget.
+    let mut kept_tokens = estimate_tokens(prompt);
+    let mut keep_from = history.len();
+    for (index, message) in history.iter().enumerate().rev() {
+        kept_tokens += estimate_tokens(message);
+        if kept_tokens > budget / 2 {
+            break;
+        }
+        keep_from = index;
+    }
+
+    // Summarizing only an existing summary would not shrink the conversation.
+    let count = keep_from.max(1);
+    if count == 1 && is_summary(&history[0]) {
+        return None;
+    }
+    Some(count)
+}
+
+/// Builds the messages asking the model to summarize part of a conversation. An earlier
+/// summary at the start of the conversation is folded into the new one.
+pub fn summary_request(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    let transcript: Vec<String> = messages
+        .iter()
+        .map(|message| match message.role {
+            MessageRole::User => format!("User: {}", message.content),
+            MessageRole::Assistant => format!("Assistant: {}", message.content),
+            _ => message.content.clone(),
+        })
+        .collect();
+    vec![
+        ChatMessage::system(SUMMARY_INSTRUCTION.to_string()),
+        ChatMessage::user(transcript.join("\n\n")),
+    ]
+}
+
+/// Returns whether a message is the summary of the earlier part of a conversation.
+pub fn is_summary(message: &ChatMessage) -> bool {
+    message.role == MessageRole::System && message.content.starts_with(SUMMARY_PREFIX)
+}
+
+/// Drops the oldest messages until the messages fit the budget, always keeping the last one
+/// (the prompt). Used when a conversation can't be summarized.
+pub fn fit_to_budget(messages: &mut Vec<ChatMessage>, budget: usize) {
+    let mut total = total_tokens(messages);
+    let mut drop = 0;
+    while total > budget && drop + 1 < messages.len() {
+        total -= estimate_tokens(&messages[drop]);
+        drop += 1;
+    }
+    messages.drain(..drop);
+}
+
+/// Module containing tests for context window management.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test helper: Creates a user message of the given length.
+    fn message(length: usize) -> ChatMessage {
+        ChatMessage::user("a".repeat(length))
+    }
+
+    /// Tests estimating the tokens of a message.
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(&message(0)), MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimate_tokens(&message(8)), 2 + MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimate_tokens(&message(9)), 3 + MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(
+            total_tokens(&[message(8), message(8)]),
+            2 * (2 + MESSAGE_OVERHEAD_TOKENS)
+        );
+    }
+
+    /// Tests parsing the budgets and looking them up by model name.
+    #[test]
+    fn test_context_budgets() {
+        let budgets = ContextBudgets::parse(
+            Some("2048"),
+            Some("llama3.1=8192, llama3.1:70b=32000,broken,mistral=zero,"),
+        );
+        assert_eq!(budgets.budget("llama3.1:8b"), 8192);
+        assert_eq!(budgets.budget("llama3.1"), 8192);
+        assert_eq!(budgets.budget("llama3.1:70b"), 32000);
+        assert_eq!(budgets.budget("mistral:latest"), 2048);
+
+        let budgets = ContextBudgets::parse(Some("lots"), None);
+        assert_eq!(budgets, ContextBudgets::default());
+        assert_eq!(budgets.budget("any"), DEFAULT_CONTEXT_BUDGET);
+    }
+
+    /// Tests choosing which messages to summarize.
+    #[test]
+    fn test_messages_to_summarize() {
+        // Each message takes up 10 + 4 tokens.
+        let history: Vec<ChatMessage> = (0..6).map(|_| message(40)).collect();
+        let prompt = message(40);
+
+        // Everything fits.
+        assert_eq!(messages_to_summarize(&history, &prompt, 100), None);
+
+        // Only the newest message and the prompt fit in half the budget.
+        assert_eq!(messages_to_summarize(&history, &prompt, 60), Some(5));
+
+        // With a tiny budget, the whole history is summarized.
+        assert_eq!(messages_to_summarize(&history, &prompt, 10), Some(6));
+
+        // A lone summary isn't summarized again.
+        let summarized = vec![
+            ChatMessage::system(format!("{}{}", SUMMARY_PREFIX, "a".repeat(200))),
+            message(40),
+        ];
+        assert_eq!(messages_to_summarize(&summarized, &prompt, 60), None);
+        assert_eq!(messages_to_summarize(&[], &prompt, 1), None);
+    }
+
+    /// Tests building the summarization request.
+    #[test]
+    fn test_summary_request() {
+        let request = summary_request(&[
+            ChatMessage::system(format!("{}They met.", SUMMARY_PREFIX)),
+            ChatMessage::user("Hi".to_string()),
+            ChatMessage::assistant("Hello!".to_string()),
+        ]);
+        assert_eq!(request.len(), 2);
+        assert_eq!(request[0].role, MessageRole::System);
+        assert_eq!(
+            request[1].content,
+            format!(
+                "{}They met.\n\nUser: Hi\n\nAssistant: Hello!",
+                SUMMARY_PREFIX
+            )
+        );
+    }
+
+    /// Tests dropping the oldest messages to fit the budget.
+    #[test]
+    fn test_fit_to_budget() {
+        let mut messages: Vec<ChatMessage> = (0..4).map(message).collect();
+        messages.push(message(400));
+        fit_to_budget(&mut messages, 110);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "aaa");
+
+        // The prompt is kept even if it doesn't fit.
+        fit_to_budget(&mut messages, 1);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content.len(), 400);
+    }
+}
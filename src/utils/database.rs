@@ -4,17 +4,27 @@
 
 use rusqlite::{Connection, Result as SqlResult, params};
 use serenity::all::User;
-use serenity::model::id::GuildId;
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
 use std::sync::Once;
 
+use crate::utils::generation_options::GenerationOptions;
 use crate::utils::ollama_client::OLLAMA_CLIENT;
 
 /// The filename for the SQLite database.
 pub const APPDATA_DB: &str = "application_data.db";
 /// Ensures that database table creation logic runs only once.
 static DB_INIT: Once = Once::new();
+/// The columns of `user_preferences` holding a user's default generation options, and their
+/// types.
+const USER_OPTION_COLUMNS: &[(&str, &str)] = &[
+    ("temperature", "REAL"),
+    ("num_predict", "INTEGER"),
+    ("seed", "INTEGER"),
+    ("num_ctx", "INTEGER"),
+];
 
 /// Represents a user's preference settings stored in the database.
+/// The user's default generation options are stored in the same row, see `get_user_options`.
 pub struct UserPreference {
     /// The Discord user ID.
     pub user_id: String,
@@ -24,6 +34,141 @@ pub struct UserPreference {
     pub model: String,
 }
 
+/// Represents a guild's dedicated music channel settings stored in the database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MusicChannelSetting {
+    /// The channel where the player message is posted, if one is configured.
+    pub channel_id: Option<ChannelId>,
+    /// Re-send the player once this many messages have been posted below it (0 disables).
+    pub reanchor_after: u32,
+    /// Whether plain messages in the music channel are treated as play requests.
+    pub request_mode: bool,
+}
+
+/// Represents a blocklist or allowlist entry of a guild's music content filter.
+pub struct MusicFilterEntry {
+    /// The list the entry belongs to (`block` or `allow`).
+    pub list: String,
+    /// The track property the entry matches (`title`, `uploader`, `video_id` or `domain`).
+    pub kind: String,
+    /// The pattern the property is matched against.
+    pub pattern: String,
+}
+
+/// Represents a guild's now-playing announcement settings stored in the database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnnouncementSettings {
+    /// Whether a "Now playing" message is posted in the player channel when a track starts.
+    pub announce: bool,
+    /// Delete announcements after this many seconds (0 keeps them).
+    pub delete_after: u32,
+    /// Whether requesters get a direct message when their track starts.
+    pub dm_requester: bool,
+    /// Whether the bot's voice channel status shows the track playing.
+    pub voice_status: bool,
+}
+
+/// Identifies an AI conversation: a user's own conversation in a channel, or the conversation
+/// shared by everyone in a channel or thread.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConversationKey {
+    /// A user's own conversation in a channel.
+    User(UserId, ChannelId),
+    /// The conversation shared by everyone in a channel or thread.
+    Shared(ChannelId),
+}
+
+impl ConversationKey {
+    /// Returns the channel or thread the conversation takes place in.
+    pub fn channel_id(self) -> ChannelId {
+        match self {
+            ConversationKey::User(_, channel_id) | ConversationKey::Shared(channel_id) => {
+                channel_id
+            }
+        }
+    }
+
+    /// Returns whether everyone in the channel takes part in the conversation.
+    pub fn is_shared(self) -> bool {
+        matches!(self, ConversationKey::Shared(_))
+    }
+
+    /// Returns the value stored in the `user_id` column: the user's ID, or 0 for shared
+    /// conversations (no Discord ID is 0).
+    fn user_column(self) -> u64 {
+        match self {
+            ConversationKey::User(user_id, _) => user_id.get(),
+            ConversationKey::Shared(_) => 0,
+        }
+    }
+}
+
+/// Represents a message of an AI conversation stored in the database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversationMessage {
+    /// Who wrote the message (`user`, `assistant` or `system`).
+    pub role: String,
+    /// The text of the message.
+    pub content: String,
+    /// When the message was sent, as a Unix timestamp in seconds.
+    pub created_at: i64,
+}
+
+/// Represents a named system prompt and model options the AI can take on in a guild.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Persona {
+    /// The name users pick the persona by (case-insensitive within a guild).
+    pub name: String,
+    /// The system prompt sent at the start of every conversation.
+    pub system_prompt: String,
+    /// The sampling temperature, or `None` for the model's default.
+    pub temperature: Option<f32>,
+    /// The nucleus sampling probability, or `None` for the model's default.
+    pub top_p: Option<f32>,
+    /// The context window size in tokens, or `None` for the model's default.
+    pub num_ctx: Option<u32>,
+    /// The user who created the persona.
+    pub created_by: UserId,
+}
+
+/// Represents a guild's music session saved when the bot left, so it can be resumed later.
+pub struct SavedSession {
+    /// The queued tracks, serialized as a JSON array of track metadata.
+    pub tracks: String,
+    /// The playback position of the first track, in milliseconds.
+    pub position_ms: u64,
+    /// When the session was saved, as a Unix timestamp in seconds.
+    pub saved_at: i64,
+}
+
+/// Represents a guild's music queue limits stored in the database.
+/// A value of 0 disables the corresponding limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueLimits {
+    /// The most tracks requested by users that can be queued at once.
+    pub max_queue_length: u32,
+    /// The most tracks a single user can have queued at once.
+    pub max_tracks_per_user: u32,
+    /// The longest track, in seconds, that can be queued.
+    pub max_track_duration: u32,
+    /// The most tracks imported from a single playlist or album.
+    pub max_playlist_size: u32,
+    /// The time, in seconds, a user has to wait between two requests.
+    pub cooldown: u32,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        Self {
+            max_queue_length: 500,
+            max_tracks_per_user: 0,
+            max_track_duration: 0,
+            max_playlist_size: 100,
+            cooldown: 0,
+        }
+    }
+}
+
 /// Initializes the database by ensuring the necessary tables are created.
 /// Uses `std::sync::Once` to guarantee table creation happens only once per application run.
 pub fn init_db() -> SqlResult<()> {
@@ -38,7 +183,11 @@ pub fn init_db() -> SqlResult<()> {
     Ok(())
 }
 
-/// Creates the database tables (`user_preferences`, `autoplay_settings`) if they don't exist.
+/// Creates the database tables (`user_preferences`, `autoplay_settings`, `autoplay_modes`,
+/// `autoplay_seed_playlists`, `crossfade_settings`, `music_channel_settings`, `queue_limits`,
+/// `fair_queue_settings`, `music_filters`, `explicit_filter_settings`, `saved_sessions`,
+/// `announcement_settings`, `conversation_messages`, `conversation_modes`, `ai_messages`,
+/// `personas`, `guild_personas`, `user_personas`) if they don't exist.
 fn create_tables() -> SqlResult<()> {
     // Open a connection to the database file.
     let conn = Connection::open(APPDATA_DB)?;
@@ -48,10 +197,16 @@ fn create_tables() -> SqlResult<()> {
         "CREATE TABLE IF NOT EXISTS user_preferences (
             user_id TEXT PRIMARY KEY,
             username TEXT NOT NULL,
-            model TEXT NOT NULL
+            model TEXT NOT NULL,
+            temperature REAL,
+            num_predict INTEGER,
+            seed INTEGER,
+            num_ctx INTEGER
         )",
         [],
     )?;
+    // Add the generation option columns to tables created before they existed.
+    add_missing_columns(&conn, "user_preferences", USER_OPTION_COLUMNS)?;
 
     // SQL to create the autoplay_settings table.
     conn.execute(
@@ -62,52 +217,290 @@ fn create_tables() -> SqlResult<()> {
         [],
     )?;
 
+    // SQL to create the autoplay_modes table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS autoplay_modes (
+            guild_id INTEGER PRIMARY KEY,
+            mode TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the autoplay_seed_playlists table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS autoplay_seed_playlists (
+            guild_id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the crossfade_settings table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS crossfade_settings (
+            guild_id INTEGER PRIMARY KEY,
+            seconds INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the music_channel_settings table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS music_channel_settings (
+            guild_id INTEGER PRIMARY KEY,
+            channel_id INTEGER,
+            reanchor_after INTEGER NOT NULL DEFAULT 0,
+            request_mode BOOLEAN NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // SQL to create the queue_limits table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS queue_limits (
+            guild_id INTEGER PRIMARY KEY,
+            max_queue_length INTEGER NOT NULL,
+            max_tracks_per_user INTEGER NOT NULL,
+            max_track_duration INTEGER NOT NULL,
+            max_playlist_size INTEGER NOT NULL,
+            cooldown INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the fair_queue_settings table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS fair_queue_settings (
+            guild_id INTEGER PRIMARY KEY,
+            enabled BOOLEAN NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the music_filters table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS music_filters (
+            guild_id INTEGER NOT NULL,
+            list TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            pattern TEXT NOT NULL,
+            PRIMARY KEY (guild_id, list, kind, pattern)
+        )",
+        [],
+    )?;
+
+    // SQL to create the explicit_filter_settings table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS explicit_filter_settings (
+            guild_id INTEGER PRIMARY KEY,
+            enabled BOOLEAN NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the saved_sessions table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS saved_sessions (
+            guild_id INTEGER PRIMARY KEY,
+            tracks TEXT NOT NULL,
+            position_ms INTEGER NOT NULL,
+            saved_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the announcement_settings table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS announcement_settings (
+            guild_id INTEGER PRIMARY KEY,
+            announce BOOLEAN NOT NULL,
+            delete_after INTEGER NOT NULL,
+            dm_requester BOOLEAN NOT NULL,
+            voice_status BOOLEAN NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the conversation_messages table, and an index to look up conversations.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS conversation_messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            channel_id INTEGER NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS conversation_messages_by_conversation
+            ON conversation_messages (user_id, channel_id)",
+        [],
+    )?;
+
+    // SQL to create the conversation_modes table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS conversation_modes (
+            channel_id INTEGER PRIMARY KEY,
+            shared BOOLEAN NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the ai_messages table.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ai_messages (
+            message_id INTEGER PRIMARY KEY,
+            channel_id INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // SQL to create the personas table, and the tables of the personas picked in each guild
+    // and by each user.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS personas (
+            guild_id INTEGER NOT NULL,
+            name TEXT NOT NULL COLLATE NOCASE,
+            system_prompt TEXT NOT NULL,
+            temperature REAL,
+            top_p REAL,
+            num_ctx INTEGER,
+            created_by INTEGER NOT NULL,
+            PRIMARY KEY (guild_id, name)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS guild_personas (
+            guild_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL COLLATE NOCASE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS user_personas (
+            guild_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL COLLATE NOCASE,
+            PRIMARY KEY (guild_id, user_id)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Adds the columns missing from a table created by an older version of the bot.
+/// `columns` holds the name and type of each column that should exist.
+fn add_missing_columns(conn: &Connection, table: &str, columns: &[(&str, &str)]) -> SqlResult<()> {
+    // Read the names of the table's existing columns.
+    let existing: Vec<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| row.get(1))?
+        .collect::<SqlResult<_>>()?;
+    for (name, definition) in columns {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition),
+                [],
+            )?;
+        }
+    }
     Ok(())
 }
 
 /// Retrieves the preferred AI model for a given user.
 ///
 /// 1. Attempts to query the `user_preferences` table.
-/// 2. If a preference is found, returns it.
+/// 2. If a preference is found, returns it. An empty model means the user only saved
+///    generation options.
 /// 3. If no preference is found or a database error occurs, falls back to the default model
 ///    obtained from the `OLLAMA_CLIENT`.
 pub fn get_user_model(user: &User) -> Option<String> {
     // Try opening the database connection.
-    if let Ok(conn) = Connection::open(APPDATA_DB) {
-        if let Ok(mut statement) =
-            // Prepare the SQL statement.
+    if let Ok(conn) = Connection::open(APPDATA_DB)
+        // Prepare the SQL statement.
+        && let Ok(mut statement) =
             conn.prepare("SELECT model FROM user_preferences WHERE user_id = ?1")
-        {
-            // Execute the query with the user ID.
-            if let Ok(mut rows) = statement.query([user.id.to_string()]) {
-                // Get the next (and only expected) row.
-                if let Ok(Some(row)) = rows.next() {
-                    // Try to get the 'model' column value.
-                    if let Ok(model) = row.get(0) {
-                        // Return the found model preference.
-                        return model;
-                    }
-                }
-            }
-        }
+        // Execute the query with the user ID.
+        && let Ok(mut rows) = statement.query([user.id.to_string()])
+        // Get the next (and only expected) row.
+        && let Ok(Some(row)) = rows.next()
+        // Try to get the 'model' column value.
+        && let Ok(model) = row.get::<_, String>(0)
+        && !model.is_empty()
+    {
+        // Return the found model preference.
+        return Some(model);
     }
 
     // Fallback: If DB query fails or no preference exists, get the default model.
     OLLAMA_CLIENT.clone().get_default_model()
 }
 
-/// Inserts or replaces a user's preference settings in the database.
+/// Inserts or updates a user's preferred model in the database, keeping their saved
+/// generation options.
 pub fn set_user_preference(pref: &UserPreference) -> SqlResult<()> {
     // Open database connection.
     let conn = Connection::open(APPDATA_DB)?;
-    // Execute INSERT OR REPLACE statement.
+    // Execute INSERT statement, updating the existing row on conflict.
     conn.execute(
-        "INSERT OR REPLACE INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3)",
+        "INSERT INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, model = excluded.model",
         (&pref.user_id, &pref.username, &pref.model),
     )?;
     Ok(())
 }
 
+/// Retrieves the generation options a user saved as their defaults.
+/// Returns empty options if none are saved or a database error occurs.
+pub fn get_user_options(user_id: UserId) -> GenerationOptions {
+    // Open database connection, returning empty options on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return GenerationOptions::default();
+    };
+    // Query the option columns for the user.
+    conn.query_row(
+        "SELECT temperature, num_predict, seed, num_ctx FROM user_preferences WHERE user_id = ?1",
+        [user_id.to_string()],
+        user_options_from_row,
+    )
+    .unwrap_or_default()
+}
+
+/// Reads a user's generation options from a row of the `user_preferences` table.
+fn user_options_from_row(row: &rusqlite::Row) -> SqlResult<GenerationOptions> {
+    Ok(GenerationOptions {
+        temperature: row.get(0)?,
+        num_predict: row.get(1)?,
+        seed: row.get(2)?,
+        num_ctx: row.get(3)?,
+        ..GenerationOptions::default()
+    })
+}
+
+/// Saves the generation options a user uses by default, replacing the ones saved before.
+/// Users without a preferred model keep using the default one.
+pub fn set_user_options(user: &User, options: &GenerationOptions) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT statement with an empty model, updating the existing row on conflict.
+    conn.execute(
+        "INSERT INTO user_preferences (user_id, username, model, temperature, num_predict, seed, num_ctx) VALUES (?1, ?2, '', ?3, ?4, ?5, ?6) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, temperature = excluded.temperature, num_predict = excluded.num_predict, seed = excluded.seed, num_ctx = excluded.num_ctx",
+        params![
+            user.id.to_string(),
+            user.name,
+            options.temperature,
+            options.num_predict,
+            options.seed,
+            options.num_ctx
+        ],
+    )?;
+    Ok(())
+}
+
 /// Inserts or replaces the autoplay setting for a specific guild.
 pub fn set_autoplay_setting(guild_id: GuildId, enabled: bool) -> SqlResult<()> {
     // Open database connection.
@@ -124,27 +517,802 @@ pub fn set_autoplay_setting(guild_id: GuildId, enabled: bool) -> SqlResult<()> {
 /// Returns `false` (disabled) if the setting is not found or a database error occurs.
 pub fn get_autoplay_setting(guild_id: GuildId) -> bool {
     // Try opening the database connection.
-    if let Ok(conn) = Connection::open(APPDATA_DB) {
-        if let Ok(mut statement) =
-            // Prepare the SQL statement.
+    if let Ok(conn) = Connection::open(APPDATA_DB)
+        // Prepare the SQL statement.
+        && let Ok(mut statement) =
             conn.prepare("SELECT enabled FROM autoplay_settings WHERE guild_id = ?1")
-        {
-            // Execute the query with the guild ID.
-            if let Ok(mut rows) = statement.query(params![guild_id.get()]) {
-                // Get the next (and only expected) row.
-                if let Ok(Some(row)) = rows.next() {
-                    // Try to get the 'enabled' column value.
-                    if let Ok(enabled) = row.get(0) {
-                        // Return the found setting.
-                        return enabled;
-                    }
-                }
-            }
-        }
+        // Execute the query with the guild ID.
+        && let Ok(mut rows) = statement.query(params![guild_id.get()])
+        // Get the next (and only expected) row.
+        && let Ok(Some(row)) = rows.next()
+        // Try to get the 'enabled' column value.
+        && let Ok(enabled) = row.get(0)
+    {
+        // Return the found setting.
+        return enabled;
+    }
+
+    // Default to false if DB query fails or no setting exists.
+    false
+}
+
+/// Inserts or replaces the autoplay mode identifier for a specific guild.
+pub fn set_autoplay_mode_setting(guild_id: GuildId, mode: &str) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO autoplay_modes (guild_id, mode) VALUES (?1, ?2)",
+        params![guild_id.get(), mode],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the autoplay mode identifier for a specific guild.
+/// Returns `None` if no mode is stored or a database error occurs.
+pub fn get_autoplay_mode_setting(guild_id: GuildId) -> Option<String> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the single 'mode' column for the guild.
+    conn.query_row(
+        "SELECT mode FROM autoplay_modes WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// Inserts or replaces the autoplay seed playlist URL for a specific guild.
+pub fn set_autoplay_seed_playlist(guild_id: GuildId, url: &str) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO autoplay_seed_playlists (guild_id, url) VALUES (?1, ?2)",
+        params![guild_id.get(), url],
+    )?;
+    Ok(())
+}
+
+/// Removes the autoplay seed playlist for a specific guild, if any.
+pub fn clear_autoplay_seed_playlist(guild_id: GuildId) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement.
+    conn.execute(
+        "DELETE FROM autoplay_seed_playlists WHERE guild_id = ?1",
+        params![guild_id.get()],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the autoplay seed playlist URL for a specific guild.
+/// Returns `None` if no playlist is stored or a database error occurs.
+pub fn get_autoplay_seed_playlist(guild_id: GuildId) -> Option<String> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the single 'url' column for the guild.
+    conn.query_row(
+        "SELECT url FROM autoplay_seed_playlists WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// Inserts or replaces the crossfade duration, in seconds, for a specific guild.
+pub fn set_crossfade_setting(guild_id: GuildId, seconds: u64) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO crossfade_settings (guild_id, seconds) VALUES (?1, ?2)",
+        params![guild_id.get(), seconds],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the crossfade duration, in seconds, for a specific guild.
+/// Returns 0 (crossfade disabled) if no setting is stored or a database error occurs.
+pub fn get_crossfade_setting(guild_id: GuildId) -> u64 {
+    // Open database connection, returning 0 on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return 0;
+    };
+    // Query the single 'seconds' column for the guild.
+    conn.query_row(
+        "SELECT seconds FROM crossfade_settings WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .unwrap_or(0)
+}
+
+/// Inserts or replaces the music channel settings for a specific guild.
+pub fn set_music_channel_setting(
+    guild_id: GuildId,
+    setting: &MusicChannelSetting,
+) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO music_channel_settings (guild_id, channel_id, reanchor_after, request_mode) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            guild_id.get(),
+            setting.channel_id.map(|id| id.get()),
+            setting.reanchor_after,
+            setting.request_mode
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the music channel settings for a specific guild.
+/// Returns the default settings (no dedicated channel) if none are stored or a database error occurs.
+pub fn get_music_channel_setting(guild_id: GuildId) -> MusicChannelSetting {
+    // Open database connection, returning the defaults on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return MusicChannelSetting::default();
+    };
+    // Query the settings row for the guild.
+    conn.query_row(
+        "SELECT channel_id, reanchor_after, request_mode FROM music_channel_settings WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| {
+            Ok(MusicChannelSetting {
+                channel_id: row.get::<_, Option<u64>>(0)?.map(ChannelId::new),
+                reanchor_after: row.get(1)?,
+                request_mode: row.get(2)?,
+            })
+        },
+    )
+    .unwrap_or_default()
+}
+
+/// Inserts or replaces the queue limits for a specific guild.
+pub fn set_queue_limits(guild_id: GuildId, limits: &QueueLimits) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO queue_limits (guild_id, max_queue_length, max_tracks_per_user, max_track_duration, max_playlist_size, cooldown) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            guild_id.get(),
+            limits.max_queue_length,
+            limits.max_tracks_per_user,
+            limits.max_track_duration,
+            limits.max_playlist_size,
+            limits.cooldown
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the queue limits for a specific guild.
+/// Returns the default limits if none are stored or a database error occurs.
+pub fn get_queue_limits(guild_id: GuildId) -> QueueLimits {
+    // Open database connection, returning the defaults on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return QueueLimits::default();
+    };
+    // Query the limits row for the guild.
+    conn.query_row(
+        "SELECT max_queue_length, max_tracks_per_user, max_track_duration, max_playlist_size, cooldown FROM queue_limits WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| {
+            Ok(QueueLimits {
+                max_queue_length: row.get(0)?,
+                max_tracks_per_user: row.get(1)?,
+                max_track_duration: row.get(2)?,
+                max_playlist_size: row.get(3)?,
+                cooldown: row.get(4)?,
+            })
+        },
+    )
+    .unwrap_or_default()
+}
+
+/// Inserts or replaces the fair queue setting for a specific guild.
+pub fn set_fair_queue_setting(guild_id: GuildId, enabled: bool) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO fair_queue_settings (guild_id, enabled) VALUES (?1, ?2)",
+        params![guild_id.get(), enabled],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the fair queue setting for a specific guild.
+/// Returns `false` (plain FIFO order) if no setting is stored or a database error occurs.
+pub fn get_fair_queue_setting(guild_id: GuildId) -> bool {
+    // Open database connection, returning false on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return false;
+    };
+    // Query the single 'enabled' column for the guild.
+    conn.query_row(
+        "SELECT enabled FROM fair_queue_settings WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Adds an entry to a guild's music content filter.
+/// Returns `false` if the entry already existed.
+pub fn add_music_filter(guild_id: GuildId, entry: &MusicFilterEntry) -> SqlResult<bool> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR IGNORE statement, counting the inserted rows.
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO music_filters (guild_id, list, kind, pattern) VALUES (?1, ?2, ?3, ?4)",
+        params![guild_id.get(), entry.list, entry.kind, entry.pattern],
+    )?;
+    Ok(inserted > 0)
+}
+
+/// Removes an entry from a guild's music content filter.
+/// Returns `false` if there was no such entry.
+pub fn remove_music_filter(guild_id: GuildId, entry: &MusicFilterEntry) -> SqlResult<bool> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    let removed = conn.execute(
+        "DELETE FROM music_filters WHERE guild_id = ?1 AND list = ?2 AND kind = ?3 AND pattern = ?4",
+        params![guild_id.get(), entry.list, entry.kind, entry.pattern],
+    )?;
+    Ok(removed > 0)
+}
+
+/// Retrieves all entries of a guild's music content filter.
+/// Returns an empty list if none are stored or a database error occurs.
+pub fn get_music_filters(guild_id: GuildId) -> Vec<MusicFilterEntry> {
+    // Open database connection, returning no entries on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return Vec::new();
+    };
+    // Prepare the query for the guild's entries.
+    let Ok(mut statement) = conn.prepare(
+        "SELECT list, kind, pattern FROM music_filters WHERE guild_id = ?1 ORDER BY list, kind, pattern",
+    ) else {
+        return Vec::new();
+    };
+    // Map each row to an entry, skipping unreadable rows.
+    statement
+        .query_map(params![guild_id.get()], |row| {
+            Ok(MusicFilterEntry {
+                list: row.get(0)?,
+                kind: row.get(1)?,
+                pattern: row.get(2)?,
+            })
+        })
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default()
+}
+
+/// Inserts or replaces the explicit content filter setting for a specific guild.
+pub fn set_explicit_filter_setting(guild_id: GuildId, enabled: bool) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO explicit_filter_settings (guild_id, enabled) VALUES (?1, ?2)",
+        params![guild_id.get(), enabled],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the explicit content filter setting for a specific guild.
+/// Returns `false` (explicit tracks allowed) if no setting is stored or a database error occurs.
+pub fn get_explicit_filter_setting(guild_id: GuildId) -> bool {
+    // Open database connection, returning false on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return false;
+    };
+    // Query the single 'enabled' column for the guild.
+    conn.query_row(
+        "SELECT enabled FROM explicit_filter_settings WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Inserts or replaces the saved music session for a specific guild.
+pub fn save_session(guild_id: GuildId, session: &SavedSession) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO saved_sessions (guild_id, tracks, position_ms, saved_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            guild_id.get(),
+            session.tracks,
+            session.position_ms,
+            session.saved_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the saved music session for a specific guild.
+/// Returns `None` if no session is stored or a database error occurs.
+pub fn get_saved_session(guild_id: GuildId) -> Option<SavedSession> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the session columns for the guild.
+    conn.query_row(
+        "SELECT tracks, position_ms, saved_at FROM saved_sessions WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| {
+            Ok(SavedSession {
+                tracks: row.get(0)?,
+                position_ms: row.get(1)?,
+                saved_at: row.get(2)?,
+            })
+        },
+    )
+    .ok()
+}
+
+/// Deletes the saved music session for a specific guild.
+pub fn delete_saved_session(guild_id: GuildId) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement.
+    conn.execute(
+        "DELETE FROM saved_sessions WHERE guild_id = ?1",
+        params![guild_id.get()],
+    )?;
+    Ok(())
+}
+
+/// Deletes all saved music sessions saved before the given Unix timestamp.
+/// Returns the number of sessions deleted.
+pub fn delete_expired_sessions(saved_before: i64) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    conn.execute(
+        "DELETE FROM saved_sessions WHERE saved_at < ?1",
+        params![saved_before],
+    )
+}
+
+/// Inserts or replaces the now-playing announcement settings for a specific guild.
+pub fn set_announcement_settings(
+    guild_id: GuildId,
+    settings: &AnnouncementSettings,
+) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO announcement_settings (guild_id, announce, delete_after, dm_requester, voice_status) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            guild_id.get(),
+            settings.announce,
+            settings.delete_after,
+            settings.dm_requester,
+            settings.voice_status
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the now-playing announcement settings for a specific guild.
+/// Returns the default settings (everything off) if none are stored or a database error occurs.
+pub fn get_announcement_settings(guild_id: GuildId) -> AnnouncementSettings {
+    // Open database connection, returning the defaults on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return AnnouncementSettings::default();
+    };
+    // Query the settings row for the guild.
+    conn.query_row(
+        "SELECT announce, delete_after, dm_requester, voice_status FROM announcement_settings WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| {
+            Ok(AnnouncementSettings {
+                announce: row.get(0)?,
+                delete_after: row.get(1)?,
+                dm_requester: row.get(2)?,
+                voice_status: row.get(3)?,
+            })
+        },
+    )
+    .unwrap_or_default()
+}
+
+/// Appends a message to an AI conversation.
+pub fn add_conversation_message(
+    conversation: ConversationKey,
+    message: &ConversationMessage,
+) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT statement.
+    conn.execute(
+        "INSERT INTO conversation_messages (user_id, channel_id, role, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            conversation.user_column(),
+            conversation.channel_id().get(),
+            message.role,
+            message.content,
+            message.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the messages of an AI conversation, oldest first.
+/// Returns an empty list if there are none or a database error occurs.
+pub fn get_conversation_messages(conversation: ConversationKey) -> Vec<ConversationMessage> {
+    get_conversation_rows(conversation)
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+}
+
+/// Retrieves the messages of an AI conversation with the ids of their rows, oldest first.
+/// Returns an empty list if there are none or a database error occurs.
+pub fn get_conversation_rows(conversation: ConversationKey) -> Vec<(i64, ConversationMessage)> {
+    // Open database connection, returning an empty list on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return Vec::new();
+    };
+    // Prepare the query for the conversation's messages, in the order they were added.
+    let Ok(mut statement) = conn.prepare(
+        "SELECT id, role, content, created_at FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 ORDER BY id",
+    ) else {
+        return Vec::new();
+    };
+    statement
+        .query_map(
+            params![conversation.user_column(), conversation.channel_id().get()],
+            |row| {
+                let message = ConversationMessage {
+                    role: row.get(1)?,
+                    content: row.get(2)?,
+                    created_at: row.get(3)?,
+                };
+                Ok((row.get(0)?, message))
+            },
+        )
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+/// Deletes an AI conversation. Returns the number of messages deleted.
+pub fn delete_conversation(conversation: ConversationKey) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    conn.execute(
+        "DELETE FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2",
+        params![conversation.user_column(), conversation.channel_id().get()],
+    )
+}
+
+/// Deletes a user's own AI conversations in every channel. Shared conversations are kept.
+/// Returns the number of messages deleted.
+pub fn delete_user_conversations(user_id: UserId) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    conn.execute(
+        "DELETE FROM conversation_messages WHERE user_id = ?1",
+        params![user_id.get()],
+    )
+}
+
+/// Deletes all but the `keep` most recent messages of an AI conversation.
+/// Returns the number of messages deleted.
+pub fn trim_conversation(conversation: ConversationKey, keep: usize) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, keeping the newest rows of the conversation.
+    conn.execute(
+        "DELETE FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 AND id NOT IN (
+            SELECT id FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 ORDER BY id DESC LIMIT ?3
+        )",
+        params![
+            conversation.user_column(),
+            conversation.channel_id().get(),
+            keep as i64
+        ],
+    )
+}
+
+/// Replaces the messages of an AI conversation up to the one whose row id is `last_id` with a
+/// summary. The summary takes the place of that message, so it keeps its position and
+/// timestamp. Nothing changes if that message is gone, e.g. because a concurrent request
+/// already summarized it.
+pub fn summarize_conversation(
+    conversation: ConversationKey,
+    last_id: i64,
+    summary: &str,
+) -> SqlResult<()> {
+    // Open database connection, and replace the messages in one transaction.
+    let mut conn = Connection::open(APPDATA_DB)?;
+    let transaction = conn.transaction()?;
+    // Turn the newest of the summarized messages into the summary.
+    let updated = transaction.execute(
+        "UPDATE conversation_messages SET role = 'system', content = ?1 WHERE id = ?2 AND user_id = ?3 AND channel_id = ?4",
+        params![
+            summary,
+            last_id,
+            conversation.user_column(),
+            conversation.channel_id().get()
+        ],
+    )?;
+    if updated > 0 {
+        // Delete the messages before it.
+        transaction.execute(
+            "DELETE FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 AND id < ?3",
+            params![
+                conversation.user_column(),
+                conversation.channel_id().get(),
+                last_id
+            ],
+        )?;
     }
+    transaction.commit()
+}
+
+/// Sets whether the AI conversation in a channel or thread is shared by everyone in it.
+pub fn set_conversation_shared(channel_id: ChannelId, shared: bool) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO conversation_modes (channel_id, shared) VALUES (?1, ?2)",
+        params![channel_id.get(), shared],
+    )?;
+    Ok(())
+}
+
+/// Retrieves whether the AI conversation in a channel or thread is shared by everyone in it.
+/// Returns `false` (each user has their own conversation) if no mode is stored or a database
+/// error occurs.
+pub fn get_conversation_shared(channel_id: ChannelId) -> bool {
+    // Open database connection, returning false on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return false;
+    };
+    // Query the single 'shared' column for the channel.
+    conn.query_row(
+        "SELECT shared FROM conversation_modes WHERE channel_id = ?1",
+        params![channel_id.get()],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Returns the AI conversation a user's messages in a channel belong to: the channel's shared
+/// conversation if it has one, or the user's own conversation otherwise.
+pub fn get_conversation_key(user_id: UserId, channel_id: ChannelId) -> ConversationKey {
+    if get_conversation_shared(channel_id) {
+        ConversationKey::Shared(channel_id)
+    } else {
+        ConversationKey::User(user_id, channel_id)
+    }
+}
+
+/// Records a Discord message as written by the AI, sent at the given Unix timestamp.
+pub fn add_ai_message(
+    message_id: MessageId,
+    channel_id: ChannelId,
+    created_at: i64,
+) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement.
+    conn.execute(
+        "INSERT OR REPLACE INTO ai_messages (message_id, channel_id, created_at) VALUES (?1, ?2, ?3)",
+        params![message_id.get(), channel_id.get(), created_at],
+    )?;
+    Ok(())
+}
+
+/// Returns whether a Discord message was written by the AI.
+/// Returns `false` if the message isn't recorded or a database error occurs.
+pub fn is_ai_message(message_id: MessageId) -> bool {
+    // Open database connection, returning false on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return false;
+    };
+    // Check whether a row exists for the message.
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM ai_messages WHERE message_id = ?1)",
+        params![message_id.get()],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// Forgets the AI messages sent before the given Unix timestamp.
+/// Returns the number of messages forgotten.
+pub fn delete_old_ai_messages(sent_before: i64) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    conn.execute(
+        "DELETE FROM ai_messages WHERE created_at < ?1",
+        params![sent_before],
+    )
+}
+
+/// Saves a new persona in a guild.
+/// Returns `false` without changing anything if the guild already has a persona of that name.
+pub fn add_persona(guild_id: GuildId, persona: &Persona) -> SqlResult<bool> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR IGNORE statement, checking if a row was inserted.
+    conn.execute(
+        "INSERT OR IGNORE INTO personas (guild_id, name, system_prompt, temperature, top_p, num_ctx, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            guild_id.get(),
+            persona.name,
+            persona.system_prompt,
+            persona.temperature,
+            persona.top_p,
+            persona.num_ctx,
+            persona.created_by.get()
+        ],
+    )
+    .map(|inserted| inserted > 0)
+}
+
+/// Reads a persona from a row of the `personas` table.
+fn persona_from_row(row: &rusqlite::Row) -> SqlResult<Persona> {
+    Ok(Persona {
+        name: row.get(0)?,
+        system_prompt: row.get(1)?,
+        temperature: row.get(2)?,
+        top_p: row.get(3)?,
+        num_ctx: row.get(4)?,
+        created_by: UserId::new(row.get(5)?),
+    })
+}
+
+/// Retrieves a guild's persona by name (case-insensitive).
+/// Returns `None` if there is no such persona or a database error occurs.
+pub fn get_persona(guild_id: GuildId, name: &str) -> Option<Persona> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the persona's columns.
+    conn.query_row(
+        "SELECT name, system_prompt, temperature, top_p, num_ctx, created_by FROM personas WHERE guild_id = ?1 AND name = ?2",
+        params![guild_id.get(), name],
+        persona_from_row,
+    )
+    .ok()
+}
+
+/// Retrieves all personas of a guild, sorted by name.
+/// Returns an empty list if there are none or a database error occurs.
+pub fn get_personas(guild_id: GuildId) -> Vec<Persona> {
+    // Open database connection, returning an empty list on failure.
+    let Ok(conn) = Connection::open(APPDATA_DB) else {
+        return Vec::new();
+    };
+    // Prepare the query for the guild's personas.
+    let Ok(mut statement) = conn.prepare(
+        "SELECT name, system_prompt, temperature, top_p, num_ctx, created_by FROM personas WHERE guild_id = ?1 ORDER BY name",
+    ) else {
+        return Vec::new();
+    };
+    statement
+        .query_map(params![guild_id.get()], persona_from_row)
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+/// Deletes a guild's persona, and stops it being the guild's default or anyone's choice.
+/// Returns `true` if the persona existed.
+pub fn delete_persona(guild_id: GuildId, name: &str) -> SqlResult<bool> {
+    // Open database connection, and delete the persona and its uses in one transaction.
+    let mut conn = Connection::open(APPDATA_DB)?;
+    let transaction = conn.transaction()?;
+    let deleted = transaction.execute(
+        "DELETE FROM personas WHERE guild_id = ?1 AND name = ?2",
+        params![guild_id.get(), name],
+    )?;
+    for table in ["guild_personas", "user_personas"] {
+        transaction.execute(
+            &format!("DELETE FROM {} WHERE guild_id = ?1 AND name = ?2", table),
+            params![guild_id.get(), name],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(deleted > 0)
+}
+
+/// Sets the persona used in a guild by users who haven't picked one, or clears it with `None`.
+pub fn set_guild_persona(guild_id: GuildId, name: Option<&str>) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement, or DELETE statement to clear the default.
+    match name {
+        Some(name) => conn.execute(
+            "INSERT OR REPLACE INTO guild_personas (guild_id, name) VALUES (?1, ?2)",
+            params![guild_id.get(), name],
+        )?,
+        None => conn.execute(
+            "DELETE FROM guild_personas WHERE guild_id = ?1",
+            params![guild_id.get()],
+        )?,
+    };
+    Ok(())
+}
+
+/// Retrieves the name of a guild's default persona.
+/// Returns `None` if no default is set or a database error occurs.
+pub fn get_guild_persona(guild_id: GuildId) -> Option<String> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the single 'name' column for the guild.
+    conn.query_row(
+        "SELECT name FROM guild_personas WHERE guild_id = ?1",
+        params![guild_id.get()],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// Sets the persona a user picked in a guild, or clears it with `None` to use the guild's
+/// default.
+pub fn set_user_persona(guild_id: GuildId, user_id: UserId, name: Option<&str>) -> SqlResult<()> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute INSERT OR REPLACE statement, or DELETE statement to clear the choice.
+    match name {
+        Some(name) => conn.execute(
+            "INSERT OR REPLACE INTO user_personas (guild_id, user_id, name) VALUES (?1, ?2, ?3)",
+            params![guild_id.get(), user_id.get(), name],
+        )?,
+        None => conn.execute(
+            "DELETE FROM user_personas WHERE guild_id = ?1 AND user_id = ?2",
+            params![guild_id.get(), user_id.get()],
+        )?,
+    };
+    Ok(())
+}
 
-    // Default to false if DB query fails or no setting exists.
-    false
+/// Retrieves the name of the persona a user picked in a guild.
+/// Returns `None` if they haven't picked one or a database error occurs.
+pub fn get_user_persona(guild_id: GuildId, user_id: UserId) -> Option<String> {
+    // Open database connection, returning None on failure.
+    let conn = Connection::open(APPDATA_DB).ok()?;
+    // Query the single 'name' column for the user.
+    conn.query_row(
+        "SELECT name FROM user_personas WHERE guild_id = ?1 AND user_id = ?2",
+        params![guild_id.get(), user_id.get()],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// Returns the persona the AI takes on for a user in a guild: the one they picked, or else the
+/// guild's default. Returns `None` if neither is set.
+pub fn get_active_persona(guild_id: GuildId, user_id: UserId) -> Option<Persona> {
+    let name = get_user_persona(guild_id, user_id).or_else(|| get_guild_persona(guild_id))?;
+    get_persona(guild_id, &name)
+}
+
+/// Deletes all AI conversation messages sent before the given Unix timestamp.
+/// Returns the number of messages deleted.
+pub fn delete_old_conversation_messages(sent_before: i64) -> SqlResult<usize> {
+    // Open database connection.
+    let conn = Connection::open(APPDATA_DB)?;
+    // Execute DELETE statement, counting the removed rows.
+    conn.execute(
+        "DELETE FROM conversation_messages WHERE created_at < ?1",
+        params![sent_before],
+    )
 }
 
 /// Module containing tests for the database utility functions.
@@ -168,6 +1336,9 @@ mod tests {
             [],
         )
         .expect("Failed to create user_preferences table");
+        // Add the generation option columns, like for a database created by an older version.
+        add_missing_columns(&conn, "user_preferences", USER_OPTION_COLUMNS)
+            .expect("Failed to add user_preferences columns");
         // Create autoplay_settings table.
         conn.execute(
             "CREATE TABLE autoplay_settings (
@@ -177,6 +1348,182 @@ mod tests {
             [],
         )
         .expect("Failed to create autoplay_settings table");
+        // Create autoplay_modes table.
+        conn.execute(
+            "CREATE TABLE autoplay_modes (
+                guild_id INTEGER PRIMARY KEY,
+                mode TEXT NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create autoplay_modes table");
+        // Create autoplay_seed_playlists table.
+        conn.execute(
+            "CREATE TABLE autoplay_seed_playlists (
+                guild_id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create autoplay_seed_playlists table");
+        // Create crossfade_settings table.
+        conn.execute(
+            "CREATE TABLE crossfade_settings (
+                guild_id INTEGER PRIMARY KEY,
+                seconds INTEGER NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create crossfade_settings table");
+        // Create music_channel_settings table.
+        conn.execute(
+            "CREATE TABLE music_channel_settings (
+                guild_id INTEGER PRIMARY KEY,
+                channel_id INTEGER,
+                reanchor_after INTEGER NOT NULL DEFAULT 0,
+                request_mode BOOLEAN NOT NULL DEFAULT 0
+            )",
+            [],
+        )
+        .expect("Failed to create music_channel_settings table");
+        // Create queue_limits table.
+        conn.execute(
+            "CREATE TABLE queue_limits (
+                guild_id INTEGER PRIMARY KEY,
+                max_queue_length INTEGER NOT NULL,
+                max_tracks_per_user INTEGER NOT NULL,
+                max_track_duration INTEGER NOT NULL,
+                max_playlist_size INTEGER NOT NULL,
+                cooldown INTEGER NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create queue_limits table");
+        // Create fair_queue_settings table.
+        conn.execute(
+            "CREATE TABLE fair_queue_settings (
+                guild_id INTEGER PRIMARY KEY,
+                enabled BOOLEAN NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create fair_queue_settings table");
+        // Create music_filters table.
+        conn.execute(
+            "CREATE TABLE music_filters (
+                guild_id INTEGER NOT NULL,
+                list TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                PRIMARY KEY (guild_id, list, kind, pattern)
+            )",
+            [],
+        )
+        .expect("Failed to create music_filters table");
+        // Create explicit_filter_settings table.
+        conn.execute(
+            "CREATE TABLE explicit_filter_settings (
+                guild_id INTEGER PRIMARY KEY,
+                enabled BOOLEAN NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create explicit_filter_settings table");
+        // Create saved_sessions table.
+        conn.execute(
+            "CREATE TABLE saved_sessions (
+                guild_id INTEGER PRIMARY KEY,
+                tracks TEXT NOT NULL,
+                position_ms INTEGER NOT NULL,
+                saved_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create saved_sessions table");
+        // Create announcement_settings table.
+        conn.execute(
+            "CREATE TABLE announcement_settings (
+                guild_id INTEGER PRIMARY KEY,
+                announce BOOLEAN NOT NULL,
+                delete_after INTEGER NOT NULL,
+                dm_requester BOOLEAN NOT NULL,
+                voice_status BOOLEAN NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create announcement_settings table");
+        // Create conversation_messages table.
+        conn.execute(
+            "CREATE TABLE conversation_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                channel_id INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create conversation_messages table");
+
+        // Create conversation_modes table.
+        conn.execute(
+            "CREATE TABLE conversation_modes (
+                channel_id INTEGER PRIMARY KEY,
+                shared BOOLEAN NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create conversation_modes table");
+
+        // Create ai_messages table.
+        conn.execute(
+            "CREATE TABLE ai_messages (
+                message_id INTEGER PRIMARY KEY,
+                channel_id INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create ai_messages table");
+
+        // Create personas table.
+        conn.execute(
+            "CREATE TABLE personas (
+                guild_id INTEGER NOT NULL,
+                name TEXT NOT NULL COLLATE NOCASE,
+                system_prompt TEXT NOT NULL,
+                temperature REAL,
+                top_p REAL,
+                num_ctx INTEGER,
+                created_by INTEGER NOT NULL,
+                PRIMARY KEY (guild_id, name)
+            )",
+            [],
+        )
+        .expect("Failed to create personas table");
+
+        // Create guild_personas table.
+        conn.execute(
+            "CREATE TABLE guild_personas (
+                guild_id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL COLLATE NOCASE
+            )",
+            [],
+        )
+        .expect("Failed to create guild_personas table");
+
+        // Create user_personas table.
+        conn.execute(
+            "CREATE TABLE user_personas (
+                guild_id INTEGER NOT NULL,
+                user_id INTEGER NOT NULL,
+                name TEXT NOT NULL COLLATE NOCASE,
+                PRIMARY KEY (guild_id, user_id)
+            )",
+            [],
+        )
+        .expect("Failed to create user_personas table");
         conn
     }
 
@@ -190,7 +1537,7 @@ mod tests {
 
         // Simulate setting the preference.
         conn.execute(
-            "INSERT OR REPLACE INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3)",
+            "INSERT INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, model = excluded.model",
             params![&user_id, &username, &model],
         )
         .expect("Failed to insert user preference");
@@ -206,7 +1553,7 @@ mod tests {
         // Test replacing the preference.
         let new_model = "test-model-v2".to_string();
         conn.execute(
-            "INSERT OR REPLACE INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3)",
+            "INSERT INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, model = excluded.model",
             params![&user_id, &username, &new_model], // Same user_id, new model
         )
         .expect("Failed to replace user preference");
@@ -220,6 +1567,89 @@ mod tests {
         assert_eq!(updated_model, Some(new_model));
     }
 
+    /// Tests adding the columns missing from an older table, which does nothing the second time.
+    #[test]
+    fn test_add_missing_columns() {
+        let conn = setup_db();
+        add_missing_columns(&conn, "user_preferences", USER_OPTION_COLUMNS)
+            .expect("Adding existing columns is a no-op");
+        let columns: Vec<String> = conn
+            .prepare("PRAGMA table_info(user_preferences)")
+            .unwrap()
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<SqlResult<_>>()
+            .unwrap();
+        assert_eq!(
+            columns,
+            [
+                "user_id",
+                "username",
+                "model",
+                "temperature",
+                "num_predict",
+                "seed",
+                "num_ctx"
+            ]
+        );
+    }
+
+    /// Tests saving a user's generation options alongside their preferred model.
+    #[test]
+    fn test_user_options() {
+        let conn = setup_db();
+        let user_id = UserId::new(123456789);
+        let set_options = |options: &GenerationOptions| {
+            conn.execute(
+                "INSERT INTO user_preferences (user_id, username, model, temperature, num_predict, seed, num_ctx) VALUES (?1, ?2, '', ?3, ?4, ?5, ?6) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, temperature = excluded.temperature, num_predict = excluded.num_predict, seed = excluded.seed, num_ctx = excluded.num_ctx",
+                params![user_id.to_string(), "testuser", options.temperature, options.num_predict, options.seed, options.num_ctx],
+            )
+            .expect("Failed to set user options");
+        };
+        let get_options = || -> GenerationOptions {
+            conn.query_row(
+                "SELECT temperature, num_predict, seed, num_ctx FROM user_preferences WHERE user_id = ?1",
+                [user_id.to_string()],
+                user_options_from_row,
+            )
+            .unwrap_or_default()
+        };
+        let get_model = || -> String {
+            conn.query_row(
+                "SELECT model FROM user_preferences WHERE user_id = ?1",
+                [user_id.to_string()],
+                |row| row.get(0),
+            )
+            .expect("Preference exists")
+        };
+        assert!(get_options().is_empty());
+
+        // Saving options without a preferred model leaves the model empty.
+        let options = GenerationOptions {
+            temperature: Some(0.5),
+            seed: Some(42),
+            ..GenerationOptions::default()
+        };
+        set_options(&options);
+        assert_eq!(get_options(), options);
+        assert_eq!(get_model(), "");
+
+        // Setting the model keeps the options, and saving options keeps the model.
+        conn.execute(
+            "INSERT INTO user_preferences (user_id, username, model) VALUES (?1, ?2, ?3) ON CONFLICT(user_id) DO UPDATE SET username = excluded.username, model = excluded.model",
+            params![user_id.to_string(), "testuser", "test-model"],
+        )
+        .expect("Failed to set user preference");
+        assert_eq!(get_options(), options);
+        set_options(&GenerationOptions {
+            num_ctx: Some(8192),
+            ..GenerationOptions::default()
+        });
+        assert_eq!(get_options().num_ctx, Some(8192));
+        assert_eq!(get_options().temperature, None);
+        assert_eq!(get_model(), "test-model");
+    }
+
     /// Tests retrieving a preference for a user that doesn't exist in the database.
     #[test]
     fn test_get_user_model_non_existent() {
@@ -306,6 +1736,641 @@ mod tests {
         // This test verifies the database interaction part correctly identifies no record found.
     }
 
+    /// Tests setting the autoplay mode for a guild, replacing it, and retrieving it.
+    #[test]
+    fn test_set_and_get_autoplay_mode() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(777888999);
+
+        // Simulate setting the mode.
+        conn.execute(
+            "INSERT OR REPLACE INTO autoplay_modes (guild_id, mode) VALUES (?1, ?2)",
+            params![guild_id.get(), "same_genre"],
+        )
+        .expect("Failed to set autoplay mode");
+
+        // Simulate replacing the mode.
+        conn.execute(
+            "INSERT OR REPLACE INTO autoplay_modes (guild_id, mode) VALUES (?1, ?2)",
+            params![guild_id.get(), "discovery"],
+        )
+        .expect("Failed to replace autoplay mode");
+
+        // Verify only the latest mode is stored.
+        let mode: Option<String> = conn
+            .query_row(
+                "SELECT mode FROM autoplay_modes WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(mode, Some("discovery".to_string()));
+    }
+
+    /// Tests setting, retrieving, and clearing the autoplay seed playlist for a guild.
+    #[test]
+    fn test_set_get_and_clear_autoplay_seed_playlist() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(123123123);
+        let url = "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M";
+
+        // Simulate setting the seed playlist.
+        conn.execute(
+            "INSERT OR REPLACE INTO autoplay_seed_playlists (guild_id, url) VALUES (?1, ?2)",
+            params![guild_id.get(), url],
+        )
+        .expect("Failed to set seed playlist");
+
+        // Verify the playlist is stored.
+        let stored: Option<String> = conn
+            .query_row(
+                "SELECT url FROM autoplay_seed_playlists WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(stored, Some(url.to_string()));
+
+        // Simulate clearing the seed playlist.
+        conn.execute(
+            "DELETE FROM autoplay_seed_playlists WHERE guild_id = ?1",
+            params![guild_id.get()],
+        )
+        .expect("Failed to clear seed playlist");
+
+        // Verify the playlist is gone.
+        let cleared: Option<String> = conn
+            .query_row(
+                "SELECT url FROM autoplay_seed_playlists WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(cleared, None);
+    }
+
+    /// Tests setting the crossfade duration for a guild and retrieving it.
+    #[test]
+    fn test_set_and_get_crossfade_setting() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(321321321);
+
+        // Simulate setting the crossfade duration.
+        conn.execute(
+            "INSERT OR REPLACE INTO crossfade_settings (guild_id, seconds) VALUES (?1, ?2)",
+            params![guild_id.get(), 6u64],
+        )
+        .expect("Failed to set crossfade");
+
+        // Verify the duration is stored.
+        let seconds: Option<u64> = conn
+            .query_row(
+                "SELECT seconds FROM crossfade_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(seconds, Some(6));
+
+        // A guild without a setting has no row.
+        let missing: Option<u64> = conn
+            .query_row(
+                "SELECT seconds FROM crossfade_settings WHERE guild_id = ?1",
+                params![GuildId::new(1).get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(missing, None);
+    }
+
+    /// Tests setting the music channel settings for a guild and retrieving them.
+    #[test]
+    fn test_set_and_get_music_channel_setting() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(654654654);
+
+        // Simulate configuring a request channel that re-anchors after 10 messages.
+        conn.execute(
+            "INSERT OR REPLACE INTO music_channel_settings (guild_id, channel_id, reanchor_after, request_mode) VALUES (?1, ?2, ?3, ?4)",
+            params![guild_id.get(), Some(987u64), 10u32, true],
+        )
+        .expect("Failed to set music channel settings");
+
+        let read = |conn: &Connection| -> Option<(Option<u64>, u32, bool)> {
+            conn.query_row(
+                "SELECT channel_id, reanchor_after, request_mode FROM music_channel_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .ok()
+        };
+        assert_eq!(read(&conn), Some((Some(987), 10, true)));
+
+        // Simulate clearing the channel while keeping a row for the guild.
+        conn.execute(
+            "INSERT OR REPLACE INTO music_channel_settings (guild_id, channel_id, reanchor_after, request_mode) VALUES (?1, ?2, ?3, ?4)",
+            params![guild_id.get(), None::<u64>, 0u32, false],
+        )
+        .expect("Failed to clear music channel settings");
+        assert_eq!(read(&conn), Some((None, 0, false)));
+    }
+
+    /// Tests setting the queue limits for a guild and retrieving them.
+    #[test]
+    fn test_set_and_get_queue_limits() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(777888999);
+        let limits = QueueLimits {
+            max_queue_length: 200,
+            max_tracks_per_user: 10,
+            max_track_duration: 600,
+            max_playlist_size: 50,
+            cooldown: 5,
+        };
+
+        // Simulate setting the limits.
+        conn.execute(
+            "INSERT OR REPLACE INTO queue_limits (guild_id, max_queue_length, max_tracks_per_user, max_track_duration, max_playlist_size, cooldown) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                guild_id.get(),
+                limits.max_queue_length,
+                limits.max_tracks_per_user,
+                limits.max_track_duration,
+                limits.max_playlist_size,
+                limits.cooldown
+            ],
+        )
+        .expect("Failed to set queue limits");
+
+        // Verify the limits are read back unchanged.
+        let read = conn
+            .query_row(
+                "SELECT max_queue_length, max_tracks_per_user, max_track_duration, max_playlist_size, cooldown FROM queue_limits WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| {
+                    Ok(QueueLimits {
+                        max_queue_length: row.get(0)?,
+                        max_tracks_per_user: row.get(1)?,
+                        max_track_duration: row.get(2)?,
+                        max_playlist_size: row.get(3)?,
+                        cooldown: row.get(4)?,
+                    })
+                },
+            )
+            .expect("Failed to read queue limits");
+        assert_eq!(read, limits);
+
+        // A guild without limits has no row and falls back to the defaults.
+        let missing = conn
+            .query_row(
+                "SELECT max_queue_length FROM queue_limits WHERE guild_id = ?1",
+                params![GuildId::new(1).get()],
+                |row| row.get::<_, u32>(0),
+            )
+            .ok();
+        assert_eq!(missing, None);
+    }
+
+    /// Tests enabling and disabling the fair queue for a guild.
+    #[test]
+    fn test_set_and_get_fair_queue_setting() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(135792468);
+        let read = |conn: &Connection, guild_id: GuildId| -> Option<bool> {
+            conn.query_row(
+                "SELECT enabled FROM fair_queue_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok()
+        };
+
+        // Simulate enabling the fair queue.
+        conn.execute(
+            "INSERT OR REPLACE INTO fair_queue_settings (guild_id, enabled) VALUES (?1, ?2)",
+            params![guild_id.get(), true],
+        )
+        .expect("Failed to enable fair queue");
+        assert_eq!(read(&conn, guild_id), Some(true));
+
+        // Simulate disabling it again.
+        conn.execute(
+            "INSERT OR REPLACE INTO fair_queue_settings (guild_id, enabled) VALUES (?1, ?2)",
+            params![guild_id.get(), false],
+        )
+        .expect("Failed to disable fair queue");
+        assert_eq!(read(&conn, guild_id), Some(false));
+
+        // A guild without a setting has no row.
+        assert_eq!(read(&conn, GuildId::new(1)), None);
+    }
+
+    /// Tests adding, listing, and removing music filter entries.
+    #[test]
+    fn test_add_list_and_remove_music_filters() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(246813579);
+        let insert = |conn: &Connection, pattern: &str| -> usize {
+            conn.execute(
+                "INSERT OR IGNORE INTO music_filters (guild_id, list, kind, pattern) VALUES (?1, ?2, ?3, ?4)",
+                params![guild_id.get(), "block", "uploader", pattern],
+            )
+            .expect("Failed to add music filter")
+        };
+        let list = |conn: &Connection| -> Vec<String> {
+            let mut statement = conn
+                .prepare("SELECT pattern FROM music_filters WHERE guild_id = ?1 ORDER BY list, kind, pattern")
+                .unwrap();
+            statement
+                .query_map(params![guild_id.get()], |row| row.get(0))
+                .unwrap()
+                .flatten()
+                .collect()
+        };
+
+        // Adding an entry twice only stores it once.
+        assert_eq!(insert(&conn, "someone"), 1);
+        assert_eq!(insert(&conn, "someone"), 0);
+        assert_eq!(insert(&conn, "another"), 1);
+        assert_eq!(list(&conn), vec!["another", "someone"]);
+
+        // Removing an entry deletes only that entry.
+        let removed = conn
+            .execute(
+                "DELETE FROM music_filters WHERE guild_id = ?1 AND list = ?2 AND kind = ?3 AND pattern = ?4",
+                params![guild_id.get(), "block", "uploader", "someone"],
+            )
+            .expect("Failed to remove music filter");
+        assert_eq!(removed, 1);
+        assert_eq!(list(&conn), vec!["another"]);
+    }
+
+    /// Tests enabling the explicit content filter for a guild.
+    #[test]
+    fn test_set_and_get_explicit_filter_setting() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(975318642);
+
+        // Simulate enabling the filter.
+        conn.execute(
+            "INSERT OR REPLACE INTO explicit_filter_settings (guild_id, enabled) VALUES (?1, ?2)",
+            params![guild_id.get(), true],
+        )
+        .expect("Failed to enable explicit filter");
+
+        let enabled: Option<bool> = conn
+            .query_row(
+                "SELECT enabled FROM explicit_filter_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(enabled, Some(true));
+    }
+
+    /// Tests saving a music session and expiring old sessions.
+    #[test]
+    fn test_save_and_expire_sessions() {
+        let conn = setup_db();
+        let old_guild = GuildId::new(100200300);
+        let new_guild = GuildId::new(400500600);
+
+        // Simulate saving a session for each guild, at different times.
+        let save = |guild_id: GuildId, saved_at: i64| {
+            conn.execute(
+                "INSERT OR REPLACE INTO saved_sessions (guild_id, tracks, position_ms, saved_at) VALUES (?1, ?2, ?3, ?4)",
+                params![guild_id.get(), "[]", 83_000u64, saved_at],
+            )
+            .expect("Failed to save session")
+        };
+        save(old_guild, 1_000);
+        save(new_guild, 5_000);
+
+        let position: Option<u64> = conn
+            .query_row(
+                "SELECT position_ms FROM saved_sessions WHERE guild_id = ?1",
+                params![new_guild.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(position, Some(83_000));
+
+        // Only the session saved before the cutoff is deleted.
+        let deleted = conn
+            .execute(
+                "DELETE FROM saved_sessions WHERE saved_at < ?1",
+                params![2_000i64],
+            )
+            .expect("Failed to delete expired sessions");
+        assert_eq!(deleted, 1);
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM saved_sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    /// Tests setting the now-playing announcement settings for a guild and retrieving them.
+    #[test]
+    fn test_set_and_get_announcement_settings() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(864213579);
+
+        // Simulate enabling announcements that are deleted after a minute, with DMs.
+        conn.execute(
+            "INSERT OR REPLACE INTO announcement_settings (guild_id, announce, delete_after, dm_requester, voice_status) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![guild_id.get(), true, 60u32, true, false],
+        )
+        .expect("Failed to set announcement settings");
+
+        let settings = conn
+            .query_row(
+                "SELECT announce, delete_after, dm_requester, voice_status FROM announcement_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| {
+                    Ok(AnnouncementSettings {
+                        announce: row.get(0)?,
+                        delete_after: row.get(1)?,
+                        dm_requester: row.get(2)?,
+                        voice_status: row.get(3)?,
+                    })
+                },
+            )
+            .expect("Failed to get announcement settings");
+        assert_eq!(
+            settings,
+            AnnouncementSettings {
+                announce: true,
+                delete_after: 60,
+                dm_requester: true,
+                voice_status: false,
+            }
+        );
+    }
+
+    /// Tests storing conversation messages per user and channel, trimming and summarizing a
+    /// conversation, and pruning old messages.
+    #[test]
+    fn test_conversation_messages() {
+        let conn = setup_db();
+        let user_id = UserId::new(13579);
+        let channel_id = ChannelId::new(111);
+        let other_channel_id = ChannelId::new(222);
+
+        // Simulate a conversation in two channels.
+        let add = |channel_id: ChannelId, role: &str, content: &str, created_at: i64| {
+            conn.execute(
+                "INSERT INTO conversation_messages (user_id, channel_id, role, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![user_id.get(), channel_id.get(), role, content, created_at],
+            )
+            .expect("Failed to add conversation message")
+        };
+        add(channel_id, "user", "Hi", 1_000);
+        add(channel_id, "assistant", "Hello!", 1_001);
+        add(channel_id, "user", "How are you?", 5_000);
+        add(other_channel_id, "user", "Elsewhere", 5_000);
+
+        let get_rows = |channel_id: ChannelId| -> Vec<(i64, ConversationMessage)> {
+            let mut statement = conn
+                .prepare("SELECT id, role, content, created_at FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 ORDER BY id")
+                .expect("Failed to prepare query");
+            statement
+                .query_map(params![user_id.get(), channel_id.get()], |row| {
+                    let message = ConversationMessage {
+                        role: row.get(1)?,
+                        content: row.get(2)?,
+                        created_at: row.get(3)?,
+                    };
+                    Ok((row.get(0)?, message))
+                })
+                .expect("Failed to get conversation messages")
+                .map(|row| row.unwrap())
+                .collect()
+        };
+        let get = |channel_id: ChannelId| -> Vec<ConversationMessage> {
+            get_rows(channel_id)
+                .into_iter()
+                .map(|(_, message)| message)
+                .collect()
+        };
+        let messages = get(channel_id);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "Hi");
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(get(other_channel_id).len(), 1);
+
+        // Simulate trimming the conversation to its two most recent messages.
+        let deleted = conn
+            .execute(
+                "DELETE FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 AND id NOT IN (
+                    SELECT id FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 ORDER BY id DESC LIMIT ?3
+                )",
+                params![user_id.get(), channel_id.get(), 2i64],
+            )
+            .expect("Failed to trim conversation");
+        assert_eq!(deleted, 1);
+        assert_eq!(get(channel_id)[0].content, "Hello!");
+
+        // Simulate replacing the two oldest messages of another conversation with a summary.
+        add(other_channel_id, "assistant", "Hi there", 5_001);
+        add(other_channel_id, "user", "Latest", 5_002);
+        let rows = get_rows(other_channel_id);
+        let summarize = |last_id: i64, summary: &str| {
+            let updated = conn
+                .execute(
+                    "UPDATE conversation_messages SET role = 'system', content = ?1 WHERE id = ?2 AND user_id = ?3 AND channel_id = ?4",
+                    params![summary, last_id, user_id.get(), other_channel_id.get()],
+                )
+                .expect("Failed to save summary");
+            if updated > 0 {
+                conn.execute(
+                    "DELETE FROM conversation_messages WHERE user_id = ?1 AND channel_id = ?2 AND id < ?3",
+                    params![user_id.get(), other_channel_id.get(), last_id],
+                )
+                .expect("Failed to delete summarized messages");
+            }
+        };
+        summarize(rows[1].0, "Summary");
+        let summarized = get(other_channel_id);
+        assert_eq!(summarized.len(), 2);
+        assert_eq!(summarized[0].role, "system");
+        assert_eq!(summarized[0].content, "Summary");
+        assert_eq!(summarized[0].created_at, 5_001);
+        assert_eq!(summarized[1].content, "Latest");
+
+        // A stale summary of messages that were already summarized changes nothing.
+        summarize(rows[0].0, "Stale summary");
+        assert_eq!(get(other_channel_id), summarized);
+
+        // Simulate pruning messages older than the retention window.
+        let deleted = conn
+            .execute(
+                "DELETE FROM conversation_messages WHERE created_at < ?1",
+                params![2_000i64],
+            )
+            .expect("Failed to delete old conversation messages");
+        assert_eq!(deleted, 1);
+        assert_eq!(get(channel_id).len(), 1);
+        assert_eq!(get(other_channel_id).len(), 2);
+    }
+
+    /// Tests setting and getting whether a channel's conversation is shared.
+    #[test]
+    fn test_set_and_get_conversation_shared() {
+        let conn = setup_db();
+        let channel_id = ChannelId::new(864213579);
+
+        // Simulate sharing the channel's conversation.
+        conn.execute(
+            "INSERT OR REPLACE INTO conversation_modes (channel_id, shared) VALUES (?1, ?2)",
+            params![channel_id.get(), true],
+        )
+        .expect("Failed to share conversation");
+
+        let shared: Option<bool> = conn
+            .query_row(
+                "SELECT shared FROM conversation_modes WHERE channel_id = ?1",
+                params![channel_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(shared, Some(true));
+    }
+
+    /// Tests recording AI messages and forgetting old ones.
+    #[test]
+    fn test_ai_messages() {
+        let conn = setup_db();
+        let channel_id = ChannelId::new(111);
+
+        // Simulate recording two AI messages.
+        for (message_id, created_at) in [(1001u64, 1_000i64), (1002, 5_000)] {
+            conn.execute(
+                "INSERT OR REPLACE INTO ai_messages (message_id, channel_id, created_at) VALUES (?1, ?2, ?3)",
+                params![message_id, channel_id.get(), created_at],
+            )
+            .expect("Failed to add AI message");
+        }
+
+        let is_ai_message = |message_id: u64| -> bool {
+            conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM ai_messages WHERE message_id = ?1)",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .expect("Failed to look up AI message")
+        };
+        assert!(is_ai_message(1001));
+        assert!(!is_ai_message(2002));
+
+        // Simulate forgetting messages older than the retention window.
+        let deleted = conn
+            .execute(
+                "DELETE FROM ai_messages WHERE created_at < ?1",
+                params![2_000i64],
+            )
+            .expect("Failed to delete old AI messages");
+        assert_eq!(deleted, 1);
+        assert!(!is_ai_message(1001));
+        assert!(is_ai_message(1002));
+    }
+
+    /// Tests adding, listing, picking, and deleting personas.
+    #[test]
+    fn test_personas() {
+        let conn = setup_db();
+        let guild_id = GuildId::new(112233);
+        let user_id = UserId::new(13579);
+
+        // Simulate adding a persona, and adding one of the same name again.
+        let add = |name: &str, temperature: Option<f32>| -> usize {
+            conn.execute(
+                "INSERT OR IGNORE INTO personas (guild_id, name, system_prompt, temperature, top_p, num_ctx, created_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![guild_id.get(), name, "You are a pirate.", temperature, None::<f32>, Some(8192u32), user_id.get()],
+            )
+            .expect("Failed to add persona")
+        };
+        assert_eq!(add("Pirate", Some(0.9)), 1);
+        assert_eq!(add("pirate", None), 0);
+        assert_eq!(add("Poet", None), 1);
+
+        let get = |name: &str| -> Option<Persona> {
+            conn.query_row(
+                "SELECT name, system_prompt, temperature, top_p, num_ctx, created_by FROM personas WHERE guild_id = ?1 AND name = ?2",
+                params![guild_id.get(), name],
+                persona_from_row,
+            )
+            .ok()
+        };
+        let persona = get("PIRATE").expect("Persona exists");
+        assert_eq!(persona.name, "Pirate");
+        assert_eq!(persona.temperature, Some(0.9));
+        assert_eq!(persona.top_p, None);
+        assert_eq!(persona.num_ctx, Some(8192));
+        assert_eq!(persona.created_by, user_id);
+
+        // Simulate picking the persona as the guild default and as the user's choice.
+        conn.execute(
+            "INSERT OR REPLACE INTO guild_personas (guild_id, name) VALUES (?1, ?2)",
+            params![guild_id.get(), "Poet"],
+        )
+        .expect("Failed to set guild persona");
+        conn.execute(
+            "INSERT OR REPLACE INTO user_personas (guild_id, user_id, name) VALUES (?1, ?2, ?3)",
+            params![guild_id.get(), user_id.get(), "pirate"],
+        )
+        .expect("Failed to set user persona");
+
+        // Simulate deleting the persona, which also clears the user's choice.
+        let deleted = conn
+            .execute(
+                "DELETE FROM personas WHERE guild_id = ?1 AND name = ?2",
+                params![guild_id.get(), "Pirate"],
+            )
+            .expect("Failed to delete persona");
+        assert_eq!(deleted, 1);
+        for table in ["guild_personas", "user_personas"] {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE guild_id = ?1 AND name = ?2", table),
+                params![guild_id.get(), "Pirate"],
+            )
+            .expect("Failed to clear persona choices");
+        }
+        assert!(get("Pirate").is_none());
+        let user_persona: Option<String> = conn
+            .query_row(
+                "SELECT name FROM user_personas WHERE guild_id = ?1 AND user_id = ?2",
+                params![guild_id.get(), user_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(user_persona, None);
+        let guild_persona: Option<String> = conn
+            .query_row(
+                "SELECT name FROM guild_personas WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .ok();
+        assert_eq!(guild_persona.as_deref(), Some("Poet"));
+    }
+
+    /// Tests how conversations are keyed in the database.
+    #[test]
+    fn test_conversation_key() {
+        let user_id = UserId::new(13579);
+        let channel_id = ChannelId::new(111);
+
+        let own = ConversationKey::User(user_id, channel_id);
+        assert_eq!(own.user_column(), 13579);
+        assert_eq!(own.channel_id(), channel_id);
+        assert!(!own.is_shared());
+
+        let shared = ConversationKey::Shared(channel_id);
+        assert_eq!(shared.user_column(), 0);
+        assert_eq!(shared.channel_id(), channel_id);
+        assert!(shared.is_shared());
+    }
+
     // Note: Testing init_db() directly is complex due to std::sync::Once.
     // The setup_db helper effectively tests the table creation SQL.
     // Testing the actual public functions' interaction with the test DB is limited
@@ -0,0 +1,228 @@
+//! Options controlling how a model generates its responses, like its temperature or seed.
+//! Options come from several places: per-model defaults in the configuration, the active
+//! persona, the user's saved defaults, and the request itself, each overriding the ones before.
+
+use std::collections::HashMap;
+use std::env;
+
+use ollama_rs::models::ModelOptions;
+use tracing::warn;
+
+use crate::utils::database::Persona;
+
+/// Generation options for the Ollama API. Options left as `None` use the model's default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GenerationOptions {
+    /// The sampling temperature (higher is more creative).
+    pub temperature: Option<f32>,
+    /// The nucleus sampling probability.
+    pub top_p: Option<f32>,
+    /// The most tokens the model generates in a response.
+    pub num_predict: Option<u32>,
+    /// The random seed, for reproducible responses.
+    pub seed: Option<i32>,
+    /// The context window size in tokens.
+    pub num_ctx: Option<u32>,
+}
+
+impl GenerationOptions {
+    /// Returns whether no option is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fills the options that aren't set with the ones of `fallback`.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            num_predict: self.num_predict.or(fallback.num_predict),
+            seed: self.seed.or(fallback.seed),
+            num_ctx: self.num_ctx.or(fallback.num_ctx),
+        }
+    }
+
+    /// Converts the options into options for the Ollama API, leaving unset ones out.
+    pub fn to_model_options(self) -> ModelOptions {
+        let mut options = ModelOptions::default();
+        if let Some(temperature) = self.temperature {
+            options = options.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            options = options.top_p(top_p);
+        }
+        if let Some(num_predict) = self.num_predict {
+            options = options.num_predict(i32::try_from(num_predict).unwrap_or(i32::MAX));
+        }
+        if let Some(seed) = self.seed {
+            options = options.seed(seed);
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            options = options.num_ctx(num_ctx.into());
+        }
+        options
+    }
+
+    /// Sets the option with the given Ollama name from its text value.
+    /// Returns `false` if the name is unknown or the value is invalid.
+    fn set(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "temperature" => value
+                .parse()
+                .ok()
+                .map(|value| self.temperature = Some(value)),
+            "top_p" => value.parse().ok().map(|value| self.top_p = Some(value)),
+            "num_predict" => value
+                .parse()
+                .ok()
+                .map(|value| self.num_predict = Some(value)),
+            "seed" => value.parse().ok().map(|value| self.seed = Some(value)),
+            "num_ctx" => value.parse().ok().map(|value| self.num_ctx = Some(value)),
+            _ => return false,
+        }
+        .is_some()
+    }
+}
+
+impl From<&Persona> for GenerationOptions {
+    fn from(persona: &Persona) -> Self {
+        Self {
+            temperature: persona.temperature,
+            top_p: persona.top_p,
+            num_ctx: persona.num_ctx,
+            ..Self::default()
+        }
+    }
+}
+
+/// The default generation options of each model, from the configuration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelDefaults {
+    /// Options by model name, with or without the tag (e.g. `llama3.1` or `llama3.1:8b`).
+    models: HashMap<String, GenerationOptions>,
+}
+
+impl ModelDefaults {
+    /// Reads the defaults from the `OLLAMA_MODEL_OPTIONS` environment variable: a
+    /// comma-separated list of `model=options` entries, whose options are `name:value` pairs
+    /// separated by semicolons, e.g. `llama3.1=temperature:0.7;num_ctx:8192`.
+    pub fn from_env() -> Self {
+        Self::parse(env::var("OLLAMA_MODEL_OPTIONS").ok().as_deref())
+    }
+
+    /// Parses the per-model options, skipping invalid entries and options.
+    fn parse(models: Option<&str>) -> Self {
+        let mut defaults = HashMap::new();
+        for entry in models.unwrap_or_default().split(',') {
+            if entry.trim().is_empty() {
+                continue;
+            }
+            let Some((model, list)) = entry
+                .split_once('=')
+                .filter(|(model, _)| !model.trim().is_empty())
+            else {
+                warn!("Skipping invalid OLLAMA_MODEL_OPTIONS entry '{}'", entry);
+                continue;
+            };
+
+            let mut options = GenerationOptions::default();
+            for option in list.split(';').filter(|option| !option.trim().is_empty()) {
+                let valid = option
+                    .split_once(':')
+                    .is_some_and(|(name, value)| options.set(name.trim(), value.trim()));
+                if !valid {
+                    warn!(
+                        "Skipping invalid option '{}' of model '{}' in OLLAMA_MODEL_OPTIONS",
+                        option,
+                        model.trim()
+                    );
+                }
+            }
+            defaults.insert(model.trim().to_string(), options);
+        }
+        Self { models: defaults }
+    }
+
+    /// Returns the default options of a model. Options set for the exact model name win over
+    /// the ones set for the model without its tag.
+    pub fn options(&self, model: &str) -> GenerationOptions {
+        let exact = self.models.get(model).copied().unwrap_or_default();
+        let untagged = model
+            .split_once(':')
+            .and_then(|(name, _tag)| self.models.get(name))
+            .copied()
+            .unwrap_or_default();
+        exact.or(untagged)
+    }
+}
+
+/// Module containing tests for generation options.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Tests that set options win over the fallback's.
+    #[test]
+    fn test_or() {
+        let request = GenerationOptions {
+            temperature: Some(0.2),
+            ..GenerationOptions::default()
+        };
+        let saved = GenerationOptions {
+            temperature: Some(0.9),
+            seed: Some(42),
+            ..GenerationOptions::default()
+        };
+        let merged = request.or(saved);
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.seed, Some(42));
+        assert_eq!(merged.num_ctx, None);
+        assert!(GenerationOptions::default().is_empty());
+        assert!(!merged.is_empty());
+    }
+
+    /// Tests converting options into model options, leaving unset ones out.
+    #[test]
+    fn test_to_model_options() {
+        let options = GenerationOptions {
+            temperature: Some(0.5),
+            num_predict: Some(256),
+            seed: Some(7),
+            num_ctx: Some(8192),
+            ..GenerationOptions::default()
+        };
+        assert_eq!(
+            serde_json::to_value(options.to_model_options()).expect("Options serialize"),
+            json!({ "temperature": 0.5, "num_predict": 256, "seed": 7, "num_ctx": 8192 })
+        );
+        assert_eq!(
+            serde_json::to_value(GenerationOptions::default().to_model_options())
+                .expect("Options serialize"),
+            json!({})
+        );
+    }
+
+    /// Tests parsing the per-model defaults and looking them up by model name.
+    #[test]
+    fn test_model_defaults() {
+        let defaults = ModelDefaults::parse(Some(
+            "llama3.1=temperature:0.7;num_ctx:8192, llama3.1:70b=temperature:0.3;bogus:1,broken,mistral=seed:x;num_predict:128,",
+        ));
+        let options = defaults.options("llama3.1:8b");
+        assert_eq!(options.temperature, Some(0.7));
+        assert_eq!(options.num_ctx, Some(8192));
+
+        // The tagged model's options win, and the untagged model's fill the rest.
+        let options = defaults.options("llama3.1:70b");
+        assert_eq!(options.temperature, Some(0.3));
+        assert_eq!(options.num_ctx, Some(8192));
+
+        let options = defaults.options("mistral");
+        assert_eq!(options.seed, None);
+        assert_eq!(options.num_predict, Some(128));
+
+        assert!(defaults.options("phi3").is_empty());
+        assert_eq!(ModelDefaults::parse(None), ModelDefaults::default());
+    }
+}
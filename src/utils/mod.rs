@@ -1,8 +1,14 @@
 //! This module aggregates various utility submodules used throughout the application.
 
+/// Tools the AI can call to use the bot's capabilities.
+pub(crate) mod ai_tools;
 /// Utilities for interacting with the Brave Search API.
 pub(crate) mod brave;
+/// Utilities for keeping AI conversations within a model's context window.
+pub(crate) mod context_window;
 /// Utilities for interacting with the application's SQLite database.
 pub(crate) mod database;
+/// Utilities for the options controlling how AI models generate responses.
+pub(crate) mod generation_options;
 /// Utilities for interacting with an Ollama client/server.
 pub(crate) mod ollama_client;
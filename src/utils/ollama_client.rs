@@ -1,19 +1,50 @@
 //! Provides a client wrapper for interacting with an Ollama server.
-//! Manages conversation history per user and handles model selection.
+//! Manages conversation history per user and channel (or shared by a whole channel or thread),
+//! persisted in the database and kept within each model's context window, and handles model
+//! selection, personas, generation options, images for vision models, and the tools the AI can
+//! call.
 
+use chrono::Utc;
 use dashmap::DashMap;
+use futures::StreamExt;
+use futures::channel::mpsc::{self, UnboundedSender};
 use ollama_rs::Ollama;
 use ollama_rs::error::OllamaError;
 use ollama_rs::generation::chat::request::ChatMessageRequest;
-use ollama_rs::generation::chat::{ChatMessage, ChatMessageResponse};
+use ollama_rs::generation::chat::{
+    ChatMessage, ChatMessageResponse, ChatMessageResponseStream, MessageRole,
+};
+use ollama_rs::generation::images::Image;
 use ollama_rs::models::LocalModel;
 use serenity::all::User;
 use std::env;
-use std::sync::Mutex;
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
-use crate::utils::database;
+use crate::utils::ai_tools::{self, ToolContext, ToolRegistry};
+use crate::utils::context_window::{self, ContextBudgets, SUMMARY_PREFIX};
+use crate::utils::database::{self, ConversationKey, ConversationMessage, Persona};
+use crate::utils::generation_options::{GenerationOptions, ModelDefaults};
+
+/// How long conversation messages are kept before they are pruned.
+pub const CONVERSATION_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The most messages kept per conversation; older messages are pruned.
+pub const MAX_CONVERSATION_MESSAGES: usize = 100;
+
+/// Tells the model how to read a conversation shared by several people.
+const SHARED_CONVERSATION_PROMPT: &str = "You are talking with several people in a group conversation. Each of their messages starts with the author's name, followed by a colon.";
+
+/// The capability the Ollama server reports for models that can see images.
+const VISION_CAPABILITY: &str = "vision";
+
+/// The capability the Ollama server reports for models that can call tools.
+const TOOLS_CAPABILITY: &str = "tools";
+
+/// The most rounds of tool calls the model can make for one message. After the last round, the
+/// model has to answer without tools.
+pub const MAX_TOOL_ROUNDS: usize = 4;
 
 /// A specialized `Result` type for Ollama client operations, using `ollama_rs::OllamaError`.
 pub type OllamaResult<T> = Result<T, OllamaError>;
@@ -24,9 +55,27 @@ pub struct OllamaClient {
     client: Ollama,
     /// The default model name to use if a user hasn't set a preference.
     default_model: Option<String>,
-    /// A map storing conversation history (`Vec<ChatMessage>`) per user.
-    /// Uses `DashMap` for concurrent access and `Mutex` for interior mutability of the history vector.
-    convo_map: DashMap<User, Mutex<Vec<ChatMessage>>>,
+    /// The token budget of each model for the conversation history and prompt.
+    context_budgets: ContextBudgets,
+    /// The default generation options of each model.
+    model_defaults: ModelDefaults,
+    /// The tools the AI can call.
+    tools: Arc<ToolRegistry>,
+    /// The capabilities of each model the Ollama server was asked about, by model name.
+    capabilities: DashMap<String, Vec<String>>,
+}
+
+/// What shapes the AI's response to a chat message, besides the conversation.
+#[derive(Default)]
+pub struct ChatSettings<'a> {
+    /// The persona the AI takes on.
+    pub persona: Option<&'a Persona>,
+    /// Generation options for this message only, winning over all others.
+    pub overrides: GenerationOptions,
+    /// Images attached to the message, for vision models.
+    pub images: Vec<Image>,
+    /// Where the chat happens, letting the AI call the bot's tools there; `None` disables tools.
+    pub tools: Option<ToolContext>,
 }
 
 /// Global, thread-safe, lazily initialized instance of the `OllamaClient`.
@@ -61,13 +110,14 @@ impl OllamaClient {
         let client = Ollama::default();
         // Determine the default model.
         let default_model = set_default_model();
-        // Initialize an empty conversation map.
-        let convo_map = DashMap::new();
 
         Self {
             client,
             default_model,
-            convo_map,
+            context_budgets: ContextBudgets::from_env(),
+            model_defaults: ModelDefaults::from_env(),
+            tools: Arc::new(ToolRegistry::with_bot_tools()),
+            capabilities: DashMap::new(),
         }
     }
 
@@ -82,97 +132,482 @@ impl OllamaClient {
         self.client.list_local_models().await
     }
 
-    /// Retrieves the conversation history for a given user.
-    /// If no history exists for the user, initializes an empty history.
-    /// Returns a clone of the message vector.
-    fn get_conversation_history(&self, user: &User) -> Vec<ChatMessage> {
-        debug!("Retrieving conversation history for user {}", user.name);
-        // Get or insert an entry for the user, initializing with an empty Mutex<Vec> if new.
-        let user_convo = self.convo_map.entry(user.clone()).or_insert_with(|| {
-            debug!(
-                "Initializing new conversation history for user {}",
-                user.name
-            );
-            Mutex::new(vec![])
-        }).downgrade();
-
-        // Lock the mutex to access the message vector.
-        let messages = user_convo.lock().unwrap();
+    /// Retrieves the history of a conversation from the database with the ids of the messages'
+    /// rows, oldest first.
+    pub fn get_conversation_history(
+        &self,
+        conversation: ConversationKey,
+    ) -> Vec<(i64, ChatMessage)> {
+        let messages: Vec<(i64, ChatMessage)> = database::get_conversation_rows(conversation)
+            .iter()
+            .filter_map(|(id, message)| Some((*id, to_chat_message(message)?)))
+            .collect();
         debug!(
-            "Retrieved {} messages from history for user {}",
+            "Retrieved {} messages from history of {:?}",
             messages.len(),
-            user.name
+            conversation
         );
-        // Clone the messages to return.
-        messages.clone()
+        messages
     }
 
-    /// Sends a chat message to the Ollama server, maintaining conversation history.
-    ///
-    /// 1. Determines the model to use (user preference or default).
-    /// 2. Retrieves the user's conversation history.
-    /// 3. Sends the new message along with the history to the Ollama API.
-    /// 4. Updates the user's conversation history with the new user message and the assistant's response.
-    pub async fn chat(&self, user: &User, message: &str) -> OllamaResult<ChatMessageResponse> {
-        info!("Processing chat request for user {}", user.name);
-        // Determine the model: check user preference via database, then fallback.
-        let model = match database::get_user_model(user) {
-            Some(model) => model,
-            None => {
-                // Return error if no model could be determined.
-                return Err(OllamaError::Other(
-                    "No model set for user or default defined".to_string(),
-                ));
+    /// Saves a prompt and the assistant's response to a conversation, then prunes the
+    /// conversation to `MAX_CONVERSATION_MESSAGES` and drops messages (and records of AI
+    /// messages sent on Discord) older than `CONVERSATION_RETENTION`.
+    pub fn record_exchange(&self, conversation: ConversationKey, prompt: &str, response: &str) {
+        let now = Utc::now().timestamp();
+        for (role, content) in [("user", prompt), ("assistant", response)] {
+            let message = ConversationMessage {
+                role: role.to_string(),
+                content: content.to_string(),
+                created_at: now,
+            };
+            if let Err(e) = database::add_conversation_message(conversation, &message) {
+                error!("Failed to save conversation message: {}", e);
+                return;
             }
-        };
+        }
+
+        // Apply the retention policy.
+        if let Err(e) = database::trim_conversation(conversation, MAX_CONVERSATION_MESSAGES) {
+            warn!("Failed to trim conversation: {}", e);
+        }
+        let cutoff = now - CONVERSATION_RETENTION.as_secs() as i64;
+        match database::delete_old_conversation_messages(cutoff) {
+            Ok(0) => {}
+            Ok(count) => info!("Deleted {} expired conversation messages", count),
+            Err(e) => warn!("Failed to delete expired conversation messages: {}", e),
+        }
+        if let Err(e) = database::delete_old_ai_messages(cutoff) {
+            warn!("Failed to forget expired AI messages: {}", e);
+        }
+    }
 
-        debug!("Using model '{}' for user {}", model, user.name);
+    /// Determines the model to use for a user: their preference, or the default.
+    fn get_model(&self, user: &User) -> OllamaResult<String> {
+        // Check user preference via database, then fallback.
+        match database::get_user_model(user) {
+            Some(model) => {
+                debug!("Using model '{}' for user {}", model, user.name);
+                Ok(model)
+            }
+            // Return error if no model could be determined.
+            None => Err(OllamaError::Other(
+                "No model set for user or default defined".to_string(),
+            )),
+        }
+    }
 
-        // Get the current conversation history for the user.
-        let mut chat_history = self.get_conversation_history(user);
+    /// Checks whether a model has a capability (e.g. `vision`), as reported by the Ollama server.
+    /// Each model's capabilities are fetched once and then cached.
+    async fn has_capability(&self, model: &str, capability: &str) -> OllamaResult<bool> {
+        if let Some(capabilities) = self.capabilities.get(model) {
+            return Ok(capabilities.iter().any(|c| c == capability));
+        }
 
-        info!("Sending chat request to Ollama for user {}", user.name);
-        // Use the ollama-rs method that handles history automatically.
-        match self
-            .client
-            .clone()
-            .send_chat_messages_with_history(
-                &mut chat_history,
-                ChatMessageRequest::new(
-                    model.to_string(),
-                    vec![ChatMessage::user(message.to_string())],
-                ),
-            )
+        debug!("Fetching capabilities of model '{}'", model);
+        let info = self.client.show_model_info(model.to_string()).await?;
+        let has_capability = info.capabilities.iter().any(|c| c == capability);
+        self.capabilities
+            .insert(model.to_string(), info.capabilities);
+        Ok(has_capability)
+    }
+
+    /// Checks whether a model can see images.
+    pub async fn supports_vision(&self, model: &str) -> OllamaResult<bool> {
+        self.has_capability(model, VISION_CAPABILITY).await
+    }
+
+    /// Checks whether a model can call tools. Models whose capabilities can't be fetched are
+    /// assumed not to.
+    pub async fn supports_tools(&self, model: &str) -> bool {
+        self.has_capability(model, TOOLS_CAPABILITY)
             .await
-        {
-            // On success, log and return the response.
-            Ok(response) => {
-                debug!(
-                    "Successfully received response from Ollama for user {}",
-                    user.name
-                );
-                Ok(response)
+            .inspect_err(|e| warn!("Failed to fetch capabilities of model '{}': {}", model, e))
+            .unwrap_or(false)
+    }
+
+    /// Folds the `count` oldest messages of a conversation into a summary written by the model,
+    /// and saves the summary in their place. `last_id` is the row id of the newest of them, so
+    /// a concurrent request can't make the summary replace messages it doesn't cover.
+    /// Returns the shortened history, or the history unchanged if the summary couldn't be written.
+    async fn summarize_history(
+        &self,
+        model: &str,
+        conversation: ConversationKey,
+        mut history: Vec<ChatMessage>,
+        count: usize,
+        last_id: i64,
+    ) -> Vec<ChatMessage> {
+        info!("Summarizing {} messages of {:?}", count, conversation);
+        let request = ChatMessageRequest::new(
+            model.to_string(),
+            context_window::summary_request(&history[..count]),
+        );
+        let summary = match self.client.send_chat_messages(request).await {
+            Ok(response) if !response.message.content.trim().is_empty() => {
+                format!("{}{}", SUMMARY_PREFIX, response.message.content.trim())
+            }
+            Ok(_) => {
+                warn!("Ollama returned an empty conversation summary");
+                return history;
             }
-            // On error, log and return the error.
             Err(e) => {
+                warn!("Failed to summarize conversation: {}", e);
+                return history;
+            }
+        };
+
+        // Replace the summarized messages, in the database and in the history being sent.
+        if let Err(e) = database::summarize_conversation(conversation, last_id, &summary) {
+            warn!("Failed to save conversation summary: {}", e);
+        }
+        history.splice(..count, [ChatMessage::system(summary)]);
+        history
+    }
+
+    /// Sends a chat message to the Ollama server and streams the response.
+    ///
+    /// 1. Determines the model to use (user preference or default).
+    ///    If images are attached to the message, the model must support vision.
+    /// 2. Retrieves the conversation history, summarizing its older part if the history and the
+    ///    new message exceed the context budget.
+    /// 3. Sends the new message along with the history to the Ollama API, after the persona's
+    ///    system prompt, if any. Shared conversations also start with a note that several
+    ///    people take part, whose messages start with their names. The generation options are
+    ///    the settings' overrides, then the user's saved defaults, the persona's options, and
+    ///    the model's defaults, each filling in the options the ones before leave unset.
+    /// 4. Returns a stream whose items hold the next piece of the assistant's response; the last
+    ///    one has `done` set. An `Err` item means the response was cut off.
+    ///
+    /// If the settings give a tool context and the model can call tools, the model may call the
+    /// bot's tools before answering. A last piece from the tool role then lists the tools used;
+    /// it is meant for display and isn't part of the response. See `relay_tool_calls`.
+    ///
+    /// The exchange isn't saved to the history; call `record_exchange` once the response is
+    /// complete. Images aren't saved, so later messages only see the text.
+    pub async fn chat_stream(
+        &self,
+        user: &User,
+        conversation: ConversationKey,
+        mut settings: ChatSettings<'_>,
+        message: &str,
+    ) -> OllamaResult<ChatMessageResponseStream> {
+        info!("Processing streaming chat request for user {}", user.name);
+        let model = self.get_model(user)?;
+        let prompt = self
+            .prompt_message(user, &model, std::mem::take(&mut settings.images), message)
+            .await?;
+        let shared = conversation.is_shared();
+        let options = self.generation_options(user, &model, &settings);
+
+        // Summarize the older part of the history if it and the new message exceed the budget.
+        let (ids, mut history): (Vec<i64>, Vec<ChatMessage>) = self
+            .get_conversation_history(conversation)
+            .into_iter()
+            .unzip();
+        let system = system_messages(settings.persona, shared);
+        let budget = self.history_budget(&model, options, &system);
+        if let Some(count) = context_window::messages_to_summarize(&history, &prompt, budget) {
+            history = self
+                .summarize_history(&model, conversation, history, count, ids[count - 1])
+                .await;
+        }
+
+        history.push(prompt);
+        self.send_stream(user, model, settings, options, shared, history)
+            .await
+    }
+
+    /// Sends a chat message to the Ollama server along with the messages leading up to it
+    /// (e.g. a chain of Discord replies) and streams the response, like `chat_stream`.
+    ///
+    /// The stored conversation history isn't used, and the context is treated as a
+    /// conversation among several people whose messages start with their names.
+    pub async fn chat_stream_with_context(
+        &self,
+        user: &User,
+        mut context: Vec<ChatMessage>,
+        mut settings: ChatSettings<'_>,
+        message: &str,
+    ) -> OllamaResult<ChatMessageResponseStream> {
+        info!(
+            "Processing streaming chat request with {} context messages for user {}",
+            context.len(),
+            user.name
+        );
+        let model = self.get_model(user)?;
+        let prompt = self
+            .prompt_message(user, &model, std::mem::take(&mut settings.images), message)
+            .await?;
+        let options = self.generation_options(user, &model, &settings);
+        context.push(prompt);
+        self.send_stream(user, model, settings, options, true, context)
+            .await
+    }
+
+    /// Builds the user's chat message with the images attached to it.
+    /// Returns an error if there are images and the model can't see them.
+    async fn prompt_message(
+        &self,
+        user: &User,
+        model: &str,
+        images: Vec<Image>,
+        message: &str,
+    ) -> OllamaResult<ChatMessage> {
+        let prompt = ChatMessage::user(message.to_string());
+        if images.is_empty() {
+            return Ok(prompt);
+        }
+        if !self.supports_vision(model).await? {
+            warn!(
+                "User {} sent an image to model '{}' without vision",
+                user.name, model
+            );
+            return Err(OllamaError::Other(format!(
+                "The model **{}** can't see images. Pick a model with vision support, like `llava` or `llama3.2-vision`, with `/set_model`.",
+                model
+            )));
+        }
+        debug!("Attaching {} images for user {}", images.len(), user.name);
+        Ok(prompt.with_images(images))
+    }
+
+    /// Merges the generation options for a chat: the settings' overrides first, then the
+    /// user's saved defaults, the persona's options, and the model's defaults.
+    fn generation_options(
+        &self,
+        user: &User,
+        model: &str,
+        settings: &ChatSettings<'_>,
+    ) -> GenerationOptions {
+        settings
+            .overrides
+            .or(database::get_user_options(user.id))
+            .or(settings
+                .persona
+                .map(GenerationOptions::from)
+                .unwrap_or_default())
+            .or(self.model_defaults.options(model))
+    }
+
+    /// Returns the token budget for the history and prompt sent to a model: the context size
+    /// of the generation options if they set one, or the model's budget otherwise, less the
+    /// tokens taken up by the system messages.
+    fn history_budget(
+        &self,
+        model: &str,
+        options: GenerationOptions,
+        system: &[ChatMessage],
+    ) -> usize {
+        let budget = options.num_ctx.map_or_else(
+            || self.context_budgets.budget(model),
+            |num_ctx| num_ctx as usize,
+        );
+        budget.saturating_sub(context_window::total_tokens(system))
+    }
+
+    /// Sends messages ending with the prompt to the model with the generation options, and
+    /// streams the response. The oldest messages are dropped if they exceed the budget, and
+    /// the system messages for the persona and shared conversations are put first.
+    /// The bot's tools are offered if the settings give a tool context and the model can call
+    /// tools.
+    async fn send_stream(
+        &self,
+        user: &User,
+        model: String,
+        settings: ChatSettings<'_>,
+        options: GenerationOptions,
+        shared: bool,
+        mut messages: Vec<ChatMessage>,
+    ) -> OllamaResult<ChatMessageResponseStream> {
+        let system = system_messages(settings.persona, shared);
+        context_window::fit_to_budget(&mut messages, self.history_budget(&model, options, &system));
+        messages.splice(..0, system);
+
+        if let Some(persona) = settings.persona {
+            debug!("Using persona '{}' for user {}", persona.name, user.name);
+        }
+        let tools = match settings.tools {
+            Some(context) if !self.tools.is_empty() && self.supports_tools(&model).await => {
+                Some(context)
+            }
+            _ => None,
+        };
+        let mut request = ChatMessageRequest::new(model, messages);
+        if !options.is_empty() {
+            debug!("Using {:?} for user {}", options, user.name);
+            request = request.options(options.to_model_options());
+        }
+        if tools.is_some() {
+            request = request.tools(self.tools.tool_infos());
+        }
+
+        info!(
+            "Sending streaming chat request to Ollama for user {}",
+            user.name
+        );
+        let stream = self
+            .client
+            .send_chat_messages_stream(request.clone())
+            .await
+            .inspect_err(|e| {
                 error!(
-                    "Failed to get response from Ollama for user {}: {}",
+                    "Failed to start response stream from Ollama for user {}: {}",
                     user.name, e
-                );
-                Err(e)
+                )
+            })?;
+        let Some(context) = tools else {
+            return Ok(stream);
+        };
+
+        // Relay the response from a task that runs the tools the model calls along the way.
+        let (sender, receiver) = mpsc::unbounded();
+        tokio::spawn(relay_tool_calls(
+            self.client.clone(),
+            self.tools.clone(),
+            request,
+            stream,
+            context,
+            sender,
+        ));
+        Ok(Box::pin(receiver))
+    }
+}
+
+/// Relays the model's response from `stream` into `sender`, running the tools the model calls
+/// and sending their results back to it, until it answers without calling any.
+///
+/// Text the model writes along the way is relayed too. After `MAX_TOOL_ROUNDS` rounds of tool
+/// calls, the model has to answer without tools. The tools used are summarized in a last piece
+/// from the tool role, which is for display only and not part of the response. An `Err` item
+/// is sent if the response is cut off. No more tools are run
+/// once the receiver is gone, e.g. because the reply couldn't be sent.
+async fn relay_tool_calls(
+    client: Ollama,
+    tools: Arc<ToolRegistry>,
+    mut request: ChatMessageRequest,
+    mut stream: ChatMessageResponseStream,
+    context: ToolContext,
+    sender: UnboundedSender<Result<ChatMessageResponse, ()>>,
+) {
+    let mut used = Vec::new();
+    // Whether text was relayed before the current round, to separate the round's text from it.
+    let mut separate = false;
+    for round in 1.. {
+        let mut calls = Vec::new();
+        let mut text = String::new();
+        while let Some(piece) = stream.next().await {
+            let Ok(mut piece) = piece else {
+                warn!("AI response stream was cut off during tool calls");
+                let _ = sender.unbounded_send(Err(()));
+                return;
+            };
+            calls.append(&mut piece.message.tool_calls);
+            text.push_str(&piece.message.content);
+            if separate && !piece.message.content.is_empty() {
+                piece.message.content.insert_str(0, "\n\n");
+                separate = false;
+            }
+
+            // The answer is complete once the model stops calling tools.
+            let done = piece.done;
+            if done && (calls.is_empty() || request.tools.is_empty()) {
+                if !used.is_empty() {
+                    let summary = ai_tools::summarize_tool_calls(&used);
+                    let mut note = piece.clone();
+                    note.message = ChatMessage::tool(format!("\n\n{}", summary));
+                    piece.done = false;
+                    piece.final_data = None;
+                    let _ = sender.unbounded_send(Ok(piece));
+                    piece = note;
+                }
+                let _ = sender.unbounded_send(Ok(piece));
+                return;
+            }
+            if !piece.message.content.is_empty() {
+                piece.done = false;
+                let _ = sender.unbounded_send(Ok(piece));
+            }
+            if done {
+                break;
             }
         }
+        if calls.is_empty() {
+            warn!("AI response stream ended before it was done");
+            let _ = sender.unbounded_send(Err(()));
+            return;
+        }
+        separate |= !text.is_empty();
+        if sender.is_closed() {
+            info!("AI response is no longer relayed; skipping its tool calls");
+            return;
+        }
+
+        // Run the tools and send their results back to the model.
+        let mut assistant = ChatMessage::assistant(text);
+        assistant.tool_calls = calls.clone();
+        request.messages.push(assistant);
+        for call in &calls {
+            let result = tools.call(&context, call).await;
+            request.messages.push(ChatMessage::tool(result));
+        }
+        used.extend(calls);
+        if round >= MAX_TOOL_ROUNDS {
+            warn!(
+                "AI made {} rounds of tool calls; asking for an answer without tools",
+                round
+            );
+            request.tools.clear();
+        }
+
+        stream = match client.send_chat_messages_stream(request.clone()).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to continue response stream after tool calls: {}", e);
+                let _ = sender.unbounded_send(Err(()));
+                return;
+            }
+        };
+    }
+}
+
+/// Builds the system messages a conversation starts with: the persona's system prompt, then a
+/// note for shared conversations that several people take part.
+fn system_messages(persona: Option<&Persona>, shared: bool) -> Vec<ChatMessage> {
+    let mut system = Vec::new();
+    if let Some(persona) = persona {
+        system.push(ChatMessage::system(persona.system_prompt.clone()));
+    }
+    if shared {
+        system.push(ChatMessage::system(SHARED_CONVERSATION_PROMPT.to_string()));
     }
+    system
+}
+
+/// Converts a stored conversation message into a chat message for the Ollama API.
+/// Returns `None` for messages with an unknown role.
+fn to_chat_message(message: &ConversationMessage) -> Option<ChatMessage> {
+    let role = match message.role.as_str() {
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        "system" => MessageRole::System,
+        _ => return None,
+    };
+    Some(ChatMessage::new(role, message.content.clone()))
 }
 
 /// Module containing tests for the OllamaClient.
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::ai_tools::BotTool;
+    use futures::StreamExt;
+    use futures::future::BoxFuture;
     use ollama_rs::{Ollama, generation::chat::ChatMessageResponse, models::LocalModel};
     use serde_json::json;
+    use serenity::model::id::{ChannelId, UserId};
     use serenity::model::user::User;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_string_contains, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     /// Test helper: Creates a mock `serenity::model::user::User` for testing.
@@ -191,6 +626,36 @@ mod tests {
         serde_json::from_value(user_json).expect("Failed to deserialize test user from JSON")
     }
 
+    /// Test helper: A tool that tells a fixed day.
+    struct TestClock;
+
+    impl BotTool for TestClock {
+        fn name(&self) -> &'static str {
+            "current_time"
+        }
+
+        fn description(&self) -> &'static str {
+            "Returns the current day."
+        }
+
+        fn parameters(&self) -> serde_json::Value {
+            json!({ "type": "object", "properties": {} })
+        }
+
+        fn call<'a>(
+            &'a self,
+            _ctx: &'a ToolContext,
+            _arguments: &'a serde_json::Value,
+        ) -> BoxFuture<'a, Result<String, crate::Error>> {
+            Box::pin(async { Ok("Sunday".to_string()) })
+        }
+    }
+
+    /// Test helper: Returns the conversation used by the tests.
+    fn test_conversation() -> ConversationKey {
+        ConversationKey::User(UserId::new(1), ChannelId::new(1))
+    }
+
     /// Test helper: Creates an `OllamaClient` instance configured to use a mock server.
     async fn setup_test_client(mock_server: &MockServer) -> OllamaClient {
         // Get the base URI of the mock server.
@@ -210,7 +675,10 @@ mod tests {
             client: ollama_rs_client,
             // Set a default model, although chat() currently relies on database::get_user_model
             default_model: Some("llama3.1:8b".to_string()),
-            convo_map: DashMap::new(),
+            context_budgets: ContextBudgets::default(),
+            model_defaults: ModelDefaults::default(),
+            tools: Arc::new(ToolRegistry::default()),
+            capabilities: DashMap::new(),
         }
     }
 
@@ -282,9 +750,9 @@ mod tests {
         mock_server.verify().await;
     }
 
-    /// Tests a successful chat interaction, assuming database lookup provides the model.
+    /// Tests a successful streamed chat interaction, assuming database lookup provides the model.
     #[tokio::test]
-    async fn test_chat_success() {
+    async fn test_chat_stream_success() {
         let mock_server = MockServer::start().await;
         let client = setup_test_client(&mock_server).await;
         let user = create_test_user();
@@ -293,38 +761,317 @@ mod tests {
         // as the database lookup for the test user will fail.
         let model_name = "llama3.1:8b"; // Use the actual default model name
 
-        // Define the expected response message and structure.
-        let response_message = ChatMessage::assistant("Hi there!".to_string());
-        let expected_response = ChatMessageResponse {
+        // Define the expected response pieces, streamed as one JSON object per line.
+        let piece = |content: &str, done: bool| ChatMessageResponse {
             model: model_name.to_string(), // Expect the default model in response too
             created_at: "2024-04-05T13:00:00Z".to_string(), // Example timestamp
-            message: response_message.clone(),
-            done: true,
+            message: ChatMessage::assistant(content.to_string()),
+            done,
             final_data: None,
         };
-        let response_body = json!(expected_response);
+        let response_body = format!(
+            "{}\n{}\n",
+            json!(piece("Hi ", false)),
+            json!(piece("there!", true))
+        );
 
         // Configure the mock server to respond to the chat request.
         Mock::given(method("POST"))
             .and(path("/api/chat"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(response_body))
+            .respond_with(ResponseTemplate::new(200).set_body_string(response_body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
         // Call the method under test.
-        let result = client.chat(&user, user_message).await;
+        let result = client
+            .chat_stream(
+                &user,
+                test_conversation(),
+                ChatSettings::default(),
+                user_message,
+            )
+            .await;
 
-        // Assert the result is Ok and contains the expected response data.
+        // Assert the result is Ok and the pieces add up to the expected response.
         assert!(result.is_ok());
-        let response = result.unwrap();
-        assert_eq!(response.model, model_name);
-        assert_eq!(response.message.content, "Hi there!");
+        let pieces: Vec<ChatMessageResponse> = result
+            .unwrap()
+            .map(|piece| piece.expect("Stream was cut off"))
+            .collect()
+            .await;
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].model, model_name);
+        assert!(pieces[1].done);
+        let content: String = pieces
+            .iter()
+            .map(|piece| piece.message.content.as_str())
+            .collect();
+        assert_eq!(content, "Hi there!");
 
         // Verify the mock server received the request.
         mock_server.verify().await;
     }
 
+    /// Tests that a chat with explicit context sends the context instead of the stored history.
+    #[tokio::test]
+    async fn test_chat_stream_with_context() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+        let user = create_test_user();
+
+        let response = ChatMessageResponse {
+            model: "llama3.1:8b".to_string(),
+            created_at: "2024-04-05T13:00:00Z".to_string(),
+            message: ChatMessage::assistant("Sure!".to_string()),
+            done: true,
+            final_data: None,
+        };
+
+        // Only respond if the request holds the context and the note about several people.
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains("Alice: Any ideas?"))
+            .and(body_string_contains("group conversation"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!("{}\n", json!(response))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let context = vec![ChatMessage::user("Alice: Any ideas?".to_string())];
+        let result = client
+            .chat_stream_with_context(&user, context, ChatSettings::default(), "TestUser: Go on")
+            .await;
+        assert!(result.is_ok());
+
+        mock_server.verify().await;
+    }
+
+    /// Tests that the merged generation options are sent with the chat request.
+    #[tokio::test]
+    async fn test_chat_stream_with_options() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+        let user = create_test_user();
+        let persona = create_test_persona(Some(0.5), None);
+
+        let response = ChatMessageResponse {
+            model: "llama3.1:8b".to_string(),
+            created_at: "2024-04-05T13:00:00Z".to_string(),
+            message: ChatMessage::assistant("Arr!".to_string()),
+            done: true,
+            final_data: None,
+        };
+
+        // Only respond if the request holds the persona's temperature and the overridden seed.
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""temperature":0.5"#))
+            .and(body_string_contains(r#""seed":42"#))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!("{}\n", json!(response))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let overrides = GenerationOptions {
+            seed: Some(42),
+            ..GenerationOptions::default()
+        };
+        let result = client
+            .chat_stream(
+                &user,
+                test_conversation(),
+                ChatSettings {
+                    persona: Some(&persona),
+                    overrides,
+                    ..ChatSettings::default()
+                },
+                "Hi",
+            )
+            .await;
+        assert!(result.is_ok());
+
+        mock_server.verify().await;
+    }
+
+    /// Tests that images are only sent to models that can see them.
+    #[tokio::test]
+    async fn test_chat_stream_with_images() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+        let user = create_test_user();
+
+        // The first model info lacks vision, the second has it.
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "capabilities": ["completion"]
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "capabilities": ["completion", "vision"]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let response = ChatMessageResponse {
+            model: "llama3.1:8b".to_string(),
+            created_at: "2024-04-05T13:00:00Z".to_string(),
+            message: ChatMessage::assistant("A cat.".to_string()),
+            done: true,
+            final_data: None,
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""images":["aW1hZ2U="]"#))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!("{}\n", json!(response))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let conversation = test_conversation();
+        let settings = || ChatSettings {
+            images: vec![Image::from_base64("aW1hZ2U=")],
+            ..ChatSettings::default()
+        };
+        let error = client
+            .chat_stream(&user, conversation, settings(), "What is this?")
+            .await
+            .err()
+            .expect("The model can't see images");
+        assert!(error.to_string().contains("can't see images"));
+
+        // Forget the cached capabilities, as if the model had been replaced.
+        client.capabilities.clear();
+        let result = client
+            .chat_stream(&user, conversation, settings(), "What is this?")
+            .await;
+        assert!(result.is_ok());
+
+        mock_server.verify().await;
+    }
+
+    /// Tests that a model's capabilities are only fetched once.
+    #[tokio::test]
+    async fn test_capabilities_cached() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "capabilities": ["completion", "tools"]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(client.supports_tools("llama3.1:8b").await);
+        assert!(client.supports_tools("llama3.1:8b").await);
+        assert!(
+            !client
+                .supports_vision("llama3.1:8b")
+                .await
+                .expect("Capabilities are cached")
+        );
+
+        mock_server.verify().await;
+    }
+
+    /// Tests that tool calls are run and their results sent back before the model answers.
+    #[tokio::test]
+    async fn test_chat_stream_with_tools() {
+        let mock_server = MockServer::start().await;
+        let mut client = setup_test_client(&mock_server).await;
+        let mut tools = ToolRegistry::default();
+        tools.register(TestClock);
+        client.tools = Arc::new(tools);
+        let user = create_test_user();
+
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "capabilities": ["completion", "tools"]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // The model answers once it has the tool's result, and asks for the time before that.
+        let answer = ChatMessageResponse {
+            model: "llama3.1:8b".to_string(),
+            created_at: "2024-04-05T13:00:00Z".to_string(),
+            message: ChatMessage::assistant("It's Sunday.".to_string()),
+            done: true,
+            final_data: None,
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""role":"tool","content":"Sunday""#))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!("{}\n", json!(answer))),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let call = json!({
+            "model": "llama3.1:8b",
+            "created_at": "2024-04-05T13:00:00Z",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "function": { "name": "current_time", "arguments": {} } }]
+            },
+            "done": true
+        });
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_string_contains(r#""tools":[{"#))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}\n", call)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let settings = ChatSettings {
+            tools: Some(ToolContext {
+                discord: None,
+                guild_id: None,
+                channel_id: ChannelId::new(1),
+                user: user.clone(),
+            }),
+            ..ChatSettings::default()
+        };
+        let stream = client
+            .chat_stream(&user, test_conversation(), settings, "What day is it?")
+            .await
+            .expect("The stream starts");
+        let pieces: Vec<_> = stream.collect().await;
+        let response: String = pieces
+            .iter()
+            .map(|piece| piece.as_ref().expect("The response isn't cut off"))
+            .filter(|piece| piece.message.role == MessageRole::Assistant)
+            .map(|piece| piece.message.content.as_str())
+            .collect();
+        assert_eq!(response, "It's Sunday.");
+
+        // The summary of the tools used comes last, apart from the response.
+        let summary = pieces.last().unwrap().as_ref().unwrap();
+        assert!(summary.done);
+        assert_eq!(summary.message.role, MessageRole::Tool);
+        assert_eq!(summary.message.content, "\n\n-# 🔧 Used `current_time`");
+
+        mock_server.verify().await;
+    }
+
     /// Tests handling of an API error during a chat request.
     #[tokio::test]
     async fn test_chat_api_error() {
@@ -342,7 +1089,14 @@ mod tests {
             .await;
 
         // Call the method under test.
-        let result = client.chat(&user, user_message).await;
+        let result = client
+            .chat_stream(
+                &user,
+                test_conversation(),
+                ChatSettings::default(),
+                user_message,
+            )
+            .await;
 
         // Assert the result is an error.
         assert!(result.is_err());
@@ -363,9 +1117,176 @@ mod tests {
         // No mock server setup needed as the error should occur before the API call.
 
         // Call the method under test.
-        let result = client.chat(&user, user_message).await;
+        let result = client
+            .chat_stream(
+                &user,
+                test_conversation(),
+                ChatSettings::default(),
+                user_message,
+            )
+            .await;
 
         // Assert the result is an error (specifically, the 'No model set' error).
         assert!(result.is_err());
     }
+
+    /// Tests folding the oldest messages of a conversation into a summary.
+    #[tokio::test]
+    async fn test_summarize_history() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+
+        // Configure the mock server to respond to the summary request.
+        let response = ChatMessageResponse {
+            model: "llama3.1:8b".to_string(),
+            created_at: "2024-04-05T13:00:00Z".to_string(),
+            message: ChatMessage::assistant("The user said hi.".to_string()),
+            done: true,
+            final_data: None,
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let history = vec![
+            ChatMessage::user("Hi".to_string()),
+            ChatMessage::assistant("Hello!".to_string()),
+            ChatMessage::user("What's new?".to_string()),
+        ];
+        let summarized = client
+            .summarize_history("llama3.1:8b", test_conversation(), history, 2, 2)
+            .await;
+
+        // Assert the two oldest messages were replaced by the summary.
+        assert_eq!(summarized.len(), 2);
+        assert!(context_window::is_summary(&summarized[0]));
+        assert_eq!(
+            summarized[0].content,
+            format!("{}The user said hi.", SUMMARY_PREFIX)
+        );
+        assert_eq!(summarized[1].content, "What's new?");
+
+        mock_server.verify().await;
+    }
+
+    /// Tests that the history is left unchanged if the summary can't be written.
+    #[tokio::test]
+    async fn test_summarize_history_error() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let history = vec![
+            ChatMessage::user("Hi".to_string()),
+            ChatMessage::assistant("Hello!".to_string()),
+        ];
+        let summarized = client
+            .summarize_history("llama3.1:8b", test_conversation(), history, 1, 1)
+            .await;
+        assert_eq!(summarized.len(), 2);
+        assert_eq!(summarized[0].content, "Hi");
+
+        mock_server.verify().await;
+    }
+
+    /// Test helper: Creates a persona with the given options.
+    fn create_test_persona(temperature: Option<f32>, num_ctx: Option<u32>) -> Persona {
+        Persona {
+            name: "Pirate".to_string(),
+            system_prompt: "You are a pirate.".to_string(),
+            temperature,
+            top_p: None,
+            num_ctx,
+            created_by: UserId::new(1),
+        }
+    }
+
+    /// Tests the system messages conversations start with.
+    #[test]
+    fn test_system_messages() {
+        let persona = create_test_persona(None, None);
+        assert!(system_messages(None, false).is_empty());
+
+        let system = system_messages(Some(&persona), true);
+        assert_eq!(system.len(), 2);
+        assert_eq!(system[0].role, MessageRole::System);
+        assert_eq!(system[0].content, "You are a pirate.");
+        assert_eq!(system[1].content, SHARED_CONVERSATION_PROMPT);
+    }
+
+    /// Tests that overrides win over the persona's options when merging generation options.
+    #[tokio::test]
+    async fn test_generation_options() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+        let user = create_test_user();
+        let persona = create_test_persona(Some(0.5), Some(8192));
+        let overrides = GenerationOptions {
+            temperature: Some(1.2),
+            num_predict: Some(100),
+            ..GenerationOptions::default()
+        };
+
+        let settings = ChatSettings {
+            persona: Some(&persona),
+            overrides,
+            ..ChatSettings::default()
+        };
+        let options = client.generation_options(&user, "llama3.1:8b", &settings);
+        assert_eq!(options.temperature, Some(1.2));
+        assert_eq!(options.num_predict, Some(100));
+        assert_eq!(options.num_ctx, Some(8192));
+        assert!(
+            client
+                .generation_options(&user, "llama3.1:8b", &ChatSettings::default())
+                .is_empty()
+        );
+    }
+
+    /// Tests that the context size of the generation options replaces the model's budget.
+    #[tokio::test]
+    async fn test_history_budget() {
+        let mock_server = MockServer::start().await;
+        let client = setup_test_client(&mock_server).await;
+        let persona = create_test_persona(None, Some(8192));
+        let system = system_messages(Some(&persona), false);
+        let system_tokens = context_window::total_tokens(&system);
+
+        assert_eq!(
+            client.history_budget("llama3.1:8b", GenerationOptions::default(), &[]),
+            context_window::DEFAULT_CONTEXT_BUDGET
+        );
+        assert_eq!(
+            client.history_budget("llama3.1:8b", GenerationOptions::from(&persona), &system),
+            8192 - system_tokens
+        );
+    }
+
+    /// Tests converting stored conversation messages into chat messages.
+    #[test]
+    fn test_to_chat_message() {
+        let stored = |role: &str| ConversationMessage {
+            role: role.to_string(),
+            content: "Hello".to_string(),
+            created_at: 0,
+        };
+
+        let message = to_chat_message(&stored("assistant")).expect("Known role");
+        assert_eq!(message.role, MessageRole::Assistant);
+        assert_eq!(message.content, "Hello");
+        assert_eq!(
+            to_chat_message(&stored("user")).map(|message| message.role),
+            Some(MessageRole::User)
+        );
+        assert!(to_chat_message(&stored("narrator")).is_none());
+    }
 }